@@ -15,6 +15,10 @@ struct Cli {
     /// Definition files (TOML, .wasm, etc.)
     #[arg(required = true)]
     definitions: Vec<PathBuf>,
+
+    /// Reload when a definition file or a local .wasm it refers to changes
+    #[arg(long)]
+    watch: bool,
 }
 
 #[tokio::main]
@@ -28,11 +32,13 @@ async fn main() -> Result<()> {
 
     let cli = Cli::parse();
 
-    let runtime = Runtime::builder()
+    let mut builder = Runtime::builder()
         .from_paths(&cli.definitions)
-        .with_service::<HttpService>()
-        .build()
-        .await?;
+        .with_service::<HttpService>();
+    if cli.watch {
+        builder = builder.watch();
+    }
+    let runtime = builder.build().await?;
 
     runtime.run().await
 }
@@ -1,5 +1,5 @@
 use std::collections::HashMap;
-use std::sync::Arc;
+use std::sync::{Arc, RwLock};
 
 use anyhow::Result;
 use http_body_util::{BodyExt, Full};
@@ -20,8 +20,9 @@ use tokio::net::TcpListener;
 use tokio::sync::watch;
 
 use composable_runtime::{
-    ComponentInvoker, Message, MessageBuilder, MessageHeaders, MessageMapper, MessagePublisher,
-    PROPAGATED_HEADERS, PROPAGATION_CONTEXT, PropagatedHeader, PropagationContext, schema,
+    ComponentInvoker, InvocationError, Message, MessageBuilder, MessageHeaders, MessageMapper,
+    MessagePublisher, PROPAGATED_HEADERS, PROPAGATION_CONTEXT, PropagatedHeader,
+    PropagationContext, schema,
 };
 
 use crate::config::{
@@ -192,7 +193,7 @@ impl Route {
                 };
 
                 let mapper = MessageMapper::from_component(
-                    component_def,
+                    &component_def,
                     Some(function.clone()),
                     mapping.clone(),
                 )
@@ -551,7 +552,7 @@ impl Router {
         }
         .map_err(|e| {
             (
-                StatusCode::INTERNAL_SERVER_ERROR,
+                invocation_error_status(&e),
                 format!("invocation error: {e}"),
             )
         })?;
@@ -628,6 +629,25 @@ impl Router {
     }
 }
 
+// A component stopped by its `timeout-ms` or `fuel` limit is reported like an
+// upstream that failed to answer in time, one turned away by its `max-queue`
+// like an overloaded one, and a failed capability like a failed upstream. A
+// WIT `err` returned by the component is the request's outcome rather than a
+// server fault.
+fn invocation_error_status(error: &InvocationError) -> StatusCode {
+    match error {
+        InvocationError::InvalidArguments { .. } => StatusCode::BAD_REQUEST,
+        InvocationError::ComponentNotFound { .. } | InvocationError::FunctionNotFound { .. } => {
+            StatusCode::NOT_FOUND
+        }
+        InvocationError::ErrorResult { .. } => StatusCode::UNPROCESSABLE_ENTITY,
+        InvocationError::LimitExceeded(_) => StatusCode::GATEWAY_TIMEOUT,
+        InvocationError::Busy(_) => StatusCode::SERVICE_UNAVAILABLE,
+        InvocationError::Capability { .. } => StatusCode::BAD_GATEWAY,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
 // Parse a URL query string into a map. Repeated keys take the last value.
 fn parse_query_string(q: &str) -> HashMap<String, String> {
     form_urlencoded::parse(q.as_bytes())
@@ -914,9 +934,14 @@ fn error_response(status: StatusCode, msg: String) -> Response<Full<Bytes>> {
         .unwrap()
 }
 
+// The router currently serving a server's requests. Each request uses the
+// router current when it arrives, so replacing it leaves requests already in
+// progress on the previous one.
+type SharedRouter = Arc<RwLock<Arc<Router>>>;
+
 pub struct HttpServer {
     port: u16,
-    router: Arc<Router>,
+    router: SharedRouter,
     tracer_provider: Option<SdkTracerProvider>,
 }
 
@@ -953,11 +978,18 @@ impl HttpServer {
 
         Ok(Self {
             port: config.port,
-            router,
+            router: Arc::new(RwLock::new(router)),
             tracer_provider,
         })
     }
 
+    /// Handle for replacing this server's routes while it runs.
+    pub fn routes(&self) -> RouteHandle {
+        RouteHandle {
+            router: Arc::clone(&self.router),
+        }
+    }
+
     pub async fn run(self, mut shutdown: watch::Receiver<bool>) -> Result<()> {
         let HttpServer {
             port,
@@ -978,7 +1010,7 @@ impl HttpServer {
                     let conn = http1::Builder::new().serve_connection(
                         TokioIo::new(stream),
                         service_fn(move |req| {
-                            let router = Arc::clone(&router);
+                            let router = Arc::clone(&router.read().unwrap());
                             async move { router.handle(req).await }
                         }),
                     );
@@ -1015,6 +1047,37 @@ impl HttpServer {
     }
 }
 
+/// Replaces the routes of a running [`HttpServer`].
+pub struct RouteHandle {
+    router: SharedRouter,
+}
+
+impl RouteHandle {
+    /// Rebuild the routes from `config`, resolving components through
+    /// `invoker`. The server keeps its listener and tracer provider.
+    pub fn replace(
+        &self,
+        config: &ServerConfig,
+        invoker: Arc<dyn ComponentInvoker>,
+        publisher: Option<Arc<dyn MessagePublisher>>,
+    ) -> Result<()> {
+        let routes: Vec<Route> = config
+            .routes
+            .iter()
+            .map(|c| Route::from_config(c, &invoker))
+            .collect::<Result<_>>()?;
+        let mut current = self.router.write().unwrap();
+        let router = Router {
+            routes,
+            invoker,
+            publisher,
+            tracer_provider: current.tracer_provider.clone(),
+        };
+        *current = Arc::new(router);
+        Ok(())
+    }
+}
+
 fn build_tracer_provider(
     endpoint: &str,
     protocol: &str,
@@ -1053,6 +1116,7 @@ fn build_tracer_provider(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use composable_runtime::ExecutionLimitExceeded;
     use serde_json::json;
 
     // Construct a Route with a stub channel target for tests that exercise
@@ -1661,4 +1725,24 @@ mod tests {
         );
         assert!(headers.get("internal-skip").is_none());
     }
+
+    #[test]
+    fn exceeded_limits_return_504() {
+        let timeout = InvocationError::LimitExceeded(ExecutionLimitExceeded::Timeout {
+            component: "c".to_string(),
+            timeout_ms: 100,
+        });
+        assert_eq!(
+            invocation_error_status(&timeout),
+            StatusCode::GATEWAY_TIMEOUT
+        );
+        let out_of_fuel = InvocationError::LimitExceeded(ExecutionLimitExceeded::OutOfFuel {
+            component: "c".to_string(),
+            fuel: 1000,
+        });
+        assert_eq!(
+            invocation_error_status(&out_of_fuel),
+            StatusCode::GATEWAY_TIMEOUT
+        );
+    }
 }
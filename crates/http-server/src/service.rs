@@ -1,3 +1,4 @@
+use std::collections::HashMap;
 use std::future::Future;
 use std::pin::Pin;
 use std::sync::{Arc, Mutex};
@@ -9,42 +10,118 @@ use tokio::task::JoinHandle;
 use composable_runtime::{ComponentInvoker, ConfigHandler, MessagePublisher, Service};
 
 use crate::config::{self, HttpServerConfigHandler, ServerConfig, SharedConfig};
-use crate::server::HttpServer;
+use crate::server::{HttpServer, RouteHandle};
 
 /// HTTP Server support for the composable runtime.
 ///
 /// Register with `RuntimeBuilder::with_service::<HttpService>()`.
 /// Handles `[server.*]` definitions where `type = "http"`.
 pub struct HttpService {
-    servers: SharedConfig,
+    // Written by the config handler, and adopted by `commit_config`.
+    parsed: SharedConfig,
+    servers: Mutex<Vec<ServerConfig>>,
     invoker: Mutex<Option<Arc<dyn ComponentInvoker>>>,
     publisher: Mutex<Option<Arc<dyn MessagePublisher>>>,
+    running: Mutex<HashMap<String, RunningServer>>,
+}
+
+// The invoker and publisher a server is started with.
+type Dependencies = (Arc<dyn ComponentInvoker>, Option<Arc<dyn MessagePublisher>>);
+
+// A started server, keyed by name in `HttpService::running`.
+struct RunningServer {
+    port: u16,
+    otlp: (Option<String>, String),
+    routes: RouteHandle,
     shutdown_tx: watch::Sender<bool>,
-    shutdown_rx: watch::Receiver<bool>,
-    tasks: Mutex<Vec<JoinHandle<()>>>,
+    task: JoinHandle<()>,
+}
+
+impl RunningServer {
+    fn start(
+        config: ServerConfig,
+        invoker: Arc<dyn ComponentInvoker>,
+        publisher: Option<Arc<dyn MessagePublisher>>,
+    ) -> Result<Self> {
+        let name = config.name.clone();
+        let port = config.port;
+        let otlp = (config.otlp_endpoint.clone(), config.otlp_protocol.clone());
+        let server = HttpServer::new(config, invoker, publisher)?;
+        let routes = server.routes();
+
+        tracing::info!(server = %name, port, "starting HTTP server");
+
+        let (shutdown_tx, shutdown_rx) = watch::channel(false);
+        let task = tokio::spawn(async move {
+            if let Err(e) = server.run(shutdown_rx).await {
+                tracing::error!(server = %name, "HTTP server error: {e}");
+            }
+        });
+        Ok(Self {
+            port,
+            otlp,
+            routes,
+            shutdown_tx,
+            task,
+        })
+    }
+
+    // Whether `config` can be served by this server's listener and tracer
+    // provider, so only its routes need replacing.
+    fn serves(&self, config: &ServerConfig) -> bool {
+        self.port == config.port
+            && self.otlp.0 == config.otlp_endpoint
+            && self.otlp.1 == config.otlp_protocol
+    }
+
+    async fn stop(self) {
+        let _ = self.shutdown_tx.send(true);
+        let _ = self.task.await;
+    }
 }
 
 impl Default for HttpService {
     fn default() -> Self {
-        let (shutdown_tx, shutdown_rx) = watch::channel(false);
         Self {
-            servers: config::shared_config(),
+            parsed: config::shared_config(),
+            servers: Mutex::new(Vec::new()),
             invoker: Mutex::new(None),
             publisher: Mutex::new(None),
-            shutdown_tx,
-            shutdown_rx,
-            tasks: Mutex::new(Vec::new()),
+            running: Mutex::new(HashMap::new()),
         }
     }
 }
 
+impl HttpService {
+    fn dependencies(&self) -> Result<Dependencies> {
+        let invoker = self
+            .invoker
+            .lock()
+            .unwrap()
+            .clone()
+            .ok_or_else(|| anyhow::anyhow!("HttpService: invoker not set"))?;
+        let publisher = self.publisher.lock().unwrap().clone();
+        Ok((invoker, publisher))
+    }
+
+    fn take_servers(&self) -> Vec<ServerConfig> {
+        let mut lock = self.servers.lock().unwrap();
+        std::mem::take(&mut *lock)
+    }
+}
+
 impl Service for HttpService {
     fn config_handler(&self) -> Option<Box<dyn ConfigHandler>> {
+        self.parsed.lock().unwrap().clear();
         Some(Box::new(HttpServerConfigHandler::new(Arc::clone(
-            &self.servers,
+            &self.parsed,
         ))))
     }
 
+    fn commit_config(&self) {
+        *self.servers.lock().unwrap() = std::mem::take(&mut *self.parsed.lock().unwrap());
+    }
+
     fn set_invoker(&self, invoker: Arc<dyn ComponentInvoker>) {
         *self.invoker.lock().unwrap() = Some(invoker);
     }
@@ -54,52 +131,85 @@ impl Service for HttpService {
     }
 
     fn start(&self) -> Result<()> {
-        let invoker = self
-            .invoker
-            .lock()
-            .unwrap()
-            .clone()
-            .ok_or_else(|| anyhow::anyhow!("HttpService: invoker not set"))?;
-
-        let publisher = self.publisher.lock().unwrap().clone();
+        let (invoker, publisher) = self.dependencies()?;
+        let servers = self.take_servers();
 
-        let servers: Vec<ServerConfig> = {
-            let mut lock = self.servers.lock().unwrap();
-            std::mem::take(&mut *lock)
-        };
-
-        if servers.is_empty() {
-            return Ok(());
-        }
-
-        let mut tasks = self.tasks.lock().unwrap();
+        let mut running = self.running.lock().unwrap();
         for config in servers {
             let name = config.name.clone();
-            let port = config.port;
-            let server = HttpServer::new(config, Arc::clone(&invoker), publisher.clone())?;
+            let server = RunningServer::start(config, Arc::clone(&invoker), publisher.clone())?;
+            running.insert(name, server);
+        }
 
-            tracing::info!(server = %name, port, "starting HTTP server");
+        Ok(())
+    }
 
-            let shutdown = self.shutdown_rx.clone();
-            tasks.push(tokio::spawn(async move {
-                if let Err(e) = server.run(shutdown).await {
-                    tracing::error!(server = %name, "HTTP server error: {e}");
+    // Servers that keep their port and OTLP settings get new routes in
+    // place, without dropping connections. Others are restarted, and servers
+    // no longer defined are stopped. A server whose routes fail to build
+    // keeps its previous routes.
+    fn reload(&self) -> Pin<Box<dyn Future<Output = Result<()>> + Send + '_>> {
+        Box::pin(async {
+            let (invoker, publisher) = self.dependencies()?;
+            let servers = self.take_servers();
+            let mut previous = std::mem::take(&mut *self.running.lock().unwrap());
+
+            let mut result = Ok(());
+            let mut running = HashMap::new();
+            for config in servers {
+                let name = config.name.clone();
+                let current = match previous.remove(&name) {
+                    Some(server) if server.serves(&config) => {
+                        if let Err(e) =
+                            server
+                                .routes
+                                .replace(&config, Arc::clone(&invoker), publisher.clone())
+                        {
+                            tracing::error!(server = %name, "failed to reload routes: {e}");
+                            result = result.and(Err(e));
+                        }
+                        Some(server)
+                    }
+                    other => {
+                        if let Some(server) = other {
+                            server.stop().await;
+                        }
+                        match RunningServer::start(config, Arc::clone(&invoker), publisher.clone())
+                        {
+                            Ok(server) => Some(server),
+                            Err(e) => {
+                                tracing::error!(server = %name, "failed to restart: {e}");
+                                result = result.and(Err(e));
+                                None
+                            }
+                        }
+                    }
+                };
+                if let Some(server) = current {
+                    running.insert(name, server);
                 }
-            }));
-        }
+            }
+            for (name, server) in previous {
+                tracing::info!(server = %name, "stopping removed HTTP server");
+                server.stop().await;
+            }
 
-        Ok(())
+            *self.running.lock().unwrap() = running;
+            result
+        })
     }
 
     fn shutdown(&self) -> Pin<Box<dyn Future<Output = ()> + Send + '_>> {
         Box::pin(async {
-            let _ = self.shutdown_tx.send(true);
-            let tasks: Vec<_> = {
-                let mut lock = self.tasks.lock().unwrap();
-                std::mem::take(&mut *lock)
+            let running: Vec<_> = {
+                let mut lock = self.running.lock().unwrap();
+                lock.drain().map(|(_, server)| server).collect()
             };
-            for task in tasks {
-                let _ = task.await;
+            for server in &running {
+                let _ = server.shutdown_tx.send(true);
+            }
+            for server in running {
+                let _ = server.task.await;
             }
         })
     }
@@ -0,0 +1,124 @@
+//! On-disk cache of composed component bytes.
+//!
+//! ```toml
+//! [runtime.composition]
+//! cache-dir = "/var/cache/composable/compositions"
+//! ```
+//!
+//! Each composition step is stored under the SHA-256 digest of the step and
+//! all of its inputs (the bytes of every component involved and, for config,
+//! the config table), together with the runtime version. A changed input
+//! yields a new entry, so entries never go stale; old ones are left in place
+//! and can be removed by deleting the directory. The cache is best effort:
+//! an entry that cannot be read or written is composed again, with a
+//! warning.
+
+use anyhow::Result;
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+
+pub(crate) struct CompositionCache {
+    dir: Option<PathBuf>,
+}
+
+impl CompositionCache {
+    /// A cache in `dir`, or a disabled one that always composes.
+    pub(crate) fn new(dir: Option<&Path>) -> Self {
+        Self {
+            dir: dir.map(Path::to_path_buf),
+        }
+    }
+
+    /// The cached output of `step` for `inputs`, or the output of `compose`,
+    /// which is then cached.
+    pub(crate) fn get_or_compose(
+        &self,
+        step: &str,
+        inputs: &[&[u8]],
+        compose: impl FnOnce() -> Result<Vec<u8>>,
+    ) -> Result<Vec<u8>> {
+        let Some(dir) = &self.dir else {
+            return compose();
+        };
+        let path = dir.join(format!("{}.wasm", key(step, inputs)));
+        match std::fs::read(&path) {
+            Ok(bytes) => {
+                tracing::debug!("Reusing cached composition {}", path.display());
+                return Ok(bytes);
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => tracing::warn!("Cannot read cached composition {}: {e}", path.display()),
+        }
+
+        let bytes = compose()?;
+        if let Err(e) = write(dir, &path, &bytes) {
+            tracing::warn!("Cannot cache composition {}: {e}", path.display());
+        }
+        Ok(bytes)
+    }
+}
+
+fn key(step: &str, inputs: &[&[u8]]) -> String {
+    let mut hasher = Sha256::new();
+    // Compositions may change with the tools that produce them.
+    for part in [env!("CARGO_PKG_VERSION").as_bytes(), step.as_bytes()]
+        .into_iter()
+        .chain(inputs.iter().copied())
+    {
+        // Length-prefixed, so inputs cannot run into each other.
+        hasher.update((part.len() as u64).to_le_bytes());
+        hasher.update(part);
+    }
+    format!("{:x}", hasher.finalize())
+}
+
+// Written to a temporary file first, so a concurrent start never reads a
+// partial entry.
+pub(super) fn write(dir: &Path, path: &Path, bytes: &[u8]) -> std::io::Result<()> {
+    std::fs::create_dir_all(dir)?;
+    let temp = dir.join(format!(".{}.tmp", uuid::Uuid::new_v4()));
+    std::fs::write(&temp, bytes)?;
+    std::fs::rename(&temp, path).inspect_err(|_| {
+        let _ = std::fs::remove_file(&temp);
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reuses_output_for_same_inputs() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = CompositionCache::new(Some(dir.path()));
+        let composed = cache
+            .get_or_compose("compose", &[b"socket", b"plug"], || Ok(b"one".to_vec()))
+            .unwrap();
+        assert_eq!(composed, b"one");
+
+        let composed = cache
+            .get_or_compose("compose", &[b"socket", b"plug"], || {
+                panic!("should be cached")
+            })
+            .unwrap();
+        assert_eq!(composed, b"one");
+
+        // Inputs are length-prefixed, so moving bytes between them is a miss.
+        let composed = cache
+            .get_or_compose("compose", &[b"sock", b"etplug"], || Ok(b"two".to_vec()))
+            .unwrap();
+        assert_eq!(composed, b"two");
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 2);
+    }
+
+    #[test]
+    fn failed_compositions_are_not_cached() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = CompositionCache::new(Some(&dir.path().join("nested")));
+        let err = cache
+            .get_or_compose("compose", &[b"socket"], || anyhow::bail!("no plug"))
+            .unwrap_err();
+        assert_eq!(err.to_string(), "no plug");
+        assert!(!dir.path().join("nested").exists());
+    }
+}
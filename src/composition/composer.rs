@@ -42,12 +42,19 @@ impl Composer {
 
 // Generate a wasi:config/store component from key/value configuration
 fn create_config_component(config: &HashMap<String, serde_json::Value>) -> Result<Vec<u8>> {
+    static_config::create_component(config_properties(config)?)
+        .map_err(|e| anyhow::anyhow!("Failed to create config component: {e}"))
+}
+
+/// Flatten a `config` table into the key/value pairs `wasi:config/store` serves.
+pub(crate) fn config_properties(
+    config: &HashMap<String, serde_json::Value>,
+) -> Result<Vec<(String, String)>> {
     let mut config_properties = Vec::new();
     for (key, value) in config {
         flatten_config(key, value, &mut config_properties)?;
     }
-    static_config::create_component(config_properties)
-        .map_err(|e| anyhow::anyhow!("Failed to create config component: {e}"))
+    Ok(config_properties)
 }
 
 /// Recursively flatten nested JSON objects into dot-delimited keys.
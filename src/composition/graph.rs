@@ -3,17 +3,19 @@ use petgraph::graph::{DiGraph, NodeIndex};
 use petgraph::visit::EdgeRef;
 use std::collections::HashMap;
 use std::ops::{Index, IndexMut};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
+use super::lockfile::Lockfile;
 use crate::config::loaders::{TomlLoader, WasmLoader};
 use crate::config::processor::ConfigProcessor;
-use crate::types::{CapabilityDefinition, ComponentDefinition};
+use crate::types::{CapabilityDefinition, ComponentDefinition, RuntimeConfig};
 
 /// Directed graph of component and capability definitions
 /// with dependency and interceptor edges.
 pub struct ComponentGraph {
     graph: DiGraph<Node, Edge>,
     node_map: HashMap<String, NodeIndex>,
+    runtime_config: RuntimeConfig,
 }
 
 impl ComponentGraph {
@@ -53,7 +55,7 @@ impl ComponentGraph {
             if is_template_only {
                 continue;
             }
-            let index = graph.add_node(Node::Component(definition.clone()));
+            let index = graph.add_node(Node::Component(Box::new(definition.clone())));
             node_map.insert(definition.name.clone(), index);
         }
 
@@ -115,8 +117,14 @@ impl ComponentGraph {
 
                 let mut cloned_def = interceptor_def.clone();
                 cloned_def.name = synthetic_name.clone();
+                // Callers reach the component through the outermost
+                // interceptor, so that is where its limits and lifecycle apply.
+                if is_outermost {
+                    cloned_def.limits = definition.limits.clone();
+                    cloned_def.lifecycle = definition.lifecycle;
+                }
 
-                let cloned_index = graph.add_node(Node::Component(cloned_def));
+                let cloned_index = graph.add_node(Node::Component(Box::new(cloned_def)));
                 node_map.insert(synthetic_name, cloned_index);
                 interceptor_clones.insert(cloned_index);
 
@@ -182,7 +190,11 @@ impl ComponentGraph {
             ));
         }
 
-        Ok(Self { graph, node_map })
+        Ok(Self {
+            graph,
+            node_map,
+            runtime_config: RuntimeConfig::default(),
+        })
     }
 
     /// Write the graph to a DOT file.
@@ -193,6 +205,18 @@ impl ComponentGraph {
         Ok(())
     }
 
+    /// Read every component's bytes and precompiled artifact and write their
+    /// digests to a lockfile at `path`. Returns the number of locked uris and
+    /// paths.
+    pub async fn lock(&self, path: impl AsRef<Path>) -> Result<usize> {
+        Lockfile::lock(self, path.as_ref()).await
+    }
+
+    /// Engine settings from the `[runtime.*]` definitions.
+    pub fn runtime_config(&self) -> &RuntimeConfig {
+        &self.runtime_config
+    }
+
     pub fn nodes(&self) -> impl Iterator<Item = &petgraph::graph::Node<Node>> {
         self.graph.raw_nodes().iter()
     }
@@ -318,7 +342,7 @@ impl IndexMut<NodeIndex> for ComponentGraph {
 
 #[derive(Debug, Clone)]
 pub enum Node {
-    Component(ComponentDefinition),
+    Component(Box<ComponentDefinition>),
     Capability(CapabilityDefinition),
 }
 
@@ -335,6 +359,7 @@ pub struct GraphBuilder {
     loaders: Vec<Box<dyn crate::config::types::DefinitionLoader>>,
     handlers: Vec<Box<dyn crate::config::types::ConfigHandler>>,
     use_default_loaders: bool,
+    lockfile: Option<PathBuf>,
 }
 
 impl GraphBuilder {
@@ -344,10 +369,11 @@ impl GraphBuilder {
             loaders: Vec::new(),
             handlers: Vec::new(),
             use_default_loaders: true,
+            lockfile: None,
         }
     }
 
-    /// Add a definition source path (.toml, .wasm, oci://, etc.).
+    /// Add a definition source path (.toml, .wasm, oci://, pkg:, etc.).
     pub fn from_path(mut self, path: impl Into<PathBuf>) -> Self {
         self.paths.push(path.into());
         self
@@ -377,6 +403,13 @@ impl GraphBuilder {
         self
     }
 
+    /// Pin component uris to the digests in the lockfile at `path`, written
+    /// by [`ComponentGraph::lock`].
+    pub fn with_lockfile(mut self, path: impl Into<PathBuf>) -> Self {
+        self.lockfile = Some(path.into());
+        self
+    }
+
     /// Build the ComponentGraph from all loaded definitions.
     pub fn build(self) -> Result<ComponentGraph> {
         let mut processor = ConfigProcessor::new();
@@ -392,7 +425,13 @@ impl GraphBuilder {
             processor.add_handler(handler);
         }
 
-        let (component_definitions, capability_definitions) = processor.process(&self.paths)?;
-        ComponentGraph::build(&component_definitions, &capability_definitions)
+        let runtime_config = processor.runtime_config();
+        let (mut component_definitions, capability_definitions) = processor.process(&self.paths)?;
+        if let Some(path) = &self.lockfile {
+            Lockfile::read(path)?.apply(path, &mut component_definitions)?;
+        }
+        let mut graph = ComponentGraph::build(&component_definitions, &capability_definitions)?;
+        graph.runtime_config = runtime_config.lock().unwrap().clone();
+        Ok(graph)
     }
 }
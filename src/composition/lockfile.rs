@@ -0,0 +1,186 @@
+//! `composable.lock`, written by `composable lock`.
+//!
+//! ```toml
+//! version = 1
+//!
+//! [digests]
+//! "oci://registry.example.com/team/greeter:1.0" = "sha256:9f86d0..."
+//! "pkg:acme:auth@^1.2" = "sha256:fd61a0..."
+//! "greeter.wasm" = "sha256:2c26b4..."
+//! "greeter.cwasm" = "sha256:e3b0c4..."
+//! ```
+//!
+//! Maps each component `uri` to the SHA-256 digest of the bytes it resolved
+//! to, and each `precompiled` path to the digest of its artifact. When a
+//! lockfile is used, every component must have the locked bytes, just as
+//! with its own `digest` property, so a retagged image or a replaced file
+//! fails the build. The same goes for a locked artifact and
+//! `precompiled-digest`. A uri or path that is not in the lockfile fails the
+//! build.
+//!
+//! Files are keyed by their path relative to the lockfile's directory, so a
+//! lockfile matches however the definitions path is spelled.
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::collections::btree_map::Entry;
+use std::path::Path;
+
+use super::graph::{ComponentGraph, Node};
+use super::oci::OciPuller;
+use super::pkg::PkgFetcher;
+use super::registry::read_bytes;
+use crate::types::ComponentDefinition;
+
+/// File name of the lockfile that is used when present.
+pub(crate) const LOCKFILE: &str = "composable.lock";
+
+const VERSION: u32 = 1;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct Lockfile {
+    version: u32,
+    digests: BTreeMap<String, String>,
+}
+
+impl Lockfile {
+    pub(crate) fn read(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| anyhow::anyhow!("Cannot read lockfile {}: {e}", path.display()))?;
+        let lockfile: Self = toml::from_str(&content)
+            .map_err(|e| anyhow::anyhow!("Invalid lockfile {}: {e}", path.display()))?;
+        if lockfile.version != VERSION {
+            return Err(anyhow::anyhow!(
+                "Lockfile {} has unsupported version {}",
+                path.display(),
+                lockfile.version
+            ));
+        }
+        if let Some((uri, digest)) = lockfile
+            .digests
+            .iter()
+            .find(|(_, digest)| !is_sha256_digest(digest))
+        {
+            return Err(anyhow::anyhow!(
+                "Lockfile {}: invalid digest '{digest}' for '{uri}'",
+                path.display()
+            ));
+        }
+        Ok(lockfile)
+    }
+
+    /// Read the bytes of every component in `graph`, and its `precompiled`
+    /// artifact, recording their digests in a lockfile at `path`. Returns
+    /// the number of locked uris and paths.
+    pub(crate) async fn lock(graph: &ComponentGraph, path: &Path) -> Result<usize> {
+        let oci = OciPuller::new(&graph.runtime_config().oci);
+        let pkg = PkgFetcher::new(graph.runtime_config().pkg_config_file.clone());
+        let mut digests = BTreeMap::new();
+        for node in graph.nodes() {
+            let Node::Component(definition) = &node.weight else {
+                continue;
+            };
+            if let Entry::Vacant(entry) = digests.entry(key(path, &definition.uri)) {
+                let bytes = read_bytes(definition, &oci, &pkg).await?;
+                entry.insert(sha256_digest(&bytes));
+            }
+            if let Some(precompiled) = &definition.precompiled
+                && let Entry::Vacant(entry) = digests.entry(key(path, precompiled))
+            {
+                let bytes = std::fs::read(precompiled).map_err(|e| {
+                    anyhow::anyhow!(
+                        "Component '{}': cannot read precompiled artifact '{precompiled}': {e}",
+                        definition.name
+                    )
+                })?;
+                entry.insert(sha256_digest(&bytes));
+            }
+        }
+        let lockfile = Self {
+            version: VERSION,
+            digests,
+        };
+        std::fs::write(path, toml::to_string(&lockfile)?)
+            .map_err(|e| anyhow::anyhow!("Cannot write lockfile {}: {e}", path.display()))?;
+        Ok(lockfile.digests.len())
+    }
+
+    /// Pin each definition's uri, and its `precompiled` artifact, to the
+    /// locked digests. Anything not in the lockfile fails the build.
+    pub(crate) fn apply(&self, path: &Path, definitions: &mut [ComponentDefinition]) -> Result<()> {
+        let locked = |name: &str, uri: &str| {
+            self.digests.get(&key(path, uri)).ok_or_else(|| {
+                anyhow::anyhow!(
+                    "Component '{name}': '{uri}' is not in lockfile {}; run `composable lock` again",
+                    path.display()
+                )
+            })
+        };
+        for definition in definitions {
+            if let Some(precompiled) = &definition.precompiled {
+                let locked = locked(&definition.name, precompiled)?;
+                match &definition.precompiled_digest {
+                    Some(digest) if digest != locked => {
+                        return Err(anyhow::anyhow!(
+                            "Component '{}': precompiled-digest '{digest}' does not match '{locked}' in lockfile {}",
+                            definition.name,
+                            path.display()
+                        ));
+                    }
+                    _ => definition.precompiled_digest = Some(locked.clone()),
+                }
+            }
+            let locked = locked(&definition.name, &definition.uri)?;
+            match &definition.digest {
+                Some(digest) if digest != locked => {
+                    return Err(anyhow::anyhow!(
+                        "Component '{}': digest '{digest}' does not match '{locked}' in lockfile {}",
+                        definition.name,
+                        path.display()
+                    ));
+                }
+                _ => definition.digest = Some(locked.clone()),
+            }
+        }
+        Ok(())
+    }
+}
+
+// The lockfile key of a uri or path. Definitions hold file paths joined to
+// their file's directory, so however the definitions path was spelled, a
+// file is keyed by its canonical path relative to the lockfile's directory
+// (absolute when outside it). Other uris are keyed as written.
+fn key(lockfile: &Path, uri: &str) -> String {
+    if uri.starts_with("oci://") || uri.starts_with("pkg:") {
+        return uri.to_string();
+    }
+    let file = Path::new(uri.strip_prefix("file://").unwrap_or(uri));
+    let Ok(file) = file.canonicalize() else {
+        return uri.to_string();
+    };
+    let dir = match lockfile.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    match dir.canonicalize() {
+        Ok(dir) => file
+            .strip_prefix(&dir)
+            .unwrap_or(&file)
+            .display()
+            .to_string(),
+        Err(_) => file.display().to_string(),
+    }
+}
+
+/// Whether `digest` is written as `sha256:` and 64 lowercase hex digits.
+pub(crate) fn is_sha256_digest(digest: &str) -> bool {
+    digest.strip_prefix("sha256:").is_some_and(|hex| {
+        hex.len() == 64 && hex.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'))
+    })
+}
+
+pub(crate) fn sha256_digest(bytes: &[u8]) -> String {
+    format!("sha256:{:x}", Sha256::digest(bytes))
+}
@@ -1,5 +1,9 @@
 pub mod graph;
 pub mod registry;
 
+pub(crate) mod cache;
 pub(crate) mod composer;
+pub(crate) mod lockfile;
+pub(crate) mod oci;
+pub(crate) mod pkg;
 pub(crate) mod wit;
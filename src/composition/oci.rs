@@ -0,0 +1,307 @@
+//! Pulls `oci://` components.
+//!
+//! ```toml
+//! [runtime.oci]
+//! cache-dir = "/var/cache/composable/oci"
+//!
+//! [component.greeter]
+//! uri = "oci://registry.example.com/team/greeter:1.0"
+//! auth = { username = "ci", password = "${process:env:REGISTRY_PASSWORD}" }
+//! ```
+//!
+//! Credentials come from the component's `auth`, or else from the Docker
+//! config file (`docker-config`, `$DOCKER_CONFIG/config.json` or
+//! `~/.docker/config.json`), including its credential helpers. Without
+//! either, the pull is anonymous.
+//!
+//! The component is the image's one wasm layer, selected by media type. With
+//! a `cache-dir`, layers are stored under their digest, so a layer is only
+//! downloaded once, and the layer each reference last resolved to is
+//! recorded, so `offline = true` can start without a registry.
+
+use anyhow::Result;
+use oci_client::manifest::OciDescriptor;
+use oci_client::secrets::RegistryAuth;
+use oci_client::{Client, Reference};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+
+use super::lockfile::{is_sha256_digest, sha256_digest};
+use crate::types::{OciConfig, RegistryCredentials};
+
+/// Layer media types that hold a wasm component.
+const WASM_MEDIA_TYPES: [&str; 3] = [
+    "application/wasm",
+    "application/vnd.wasm.component",
+    "application/vnd.wasm.content.layer.v1+wasm",
+];
+
+pub(crate) struct OciPuller {
+    client: Client,
+    config: OciConfig,
+}
+
+impl OciPuller {
+    pub(crate) fn new(config: &OciConfig) -> Self {
+        Self {
+            client: Client::new(Default::default()),
+            config: config.clone(),
+        }
+    }
+
+    /// The component bytes of `oci_ref`, a reference without the `oci://`
+    /// scheme.
+    pub(crate) async fn pull(
+        &self,
+        oci_ref: &str,
+        credentials: Option<&RegistryCredentials>,
+        digest: Option<&str>,
+    ) -> Result<Vec<u8>> {
+        let reference: Reference = oci_ref.parse()?;
+        // Pinned bytes can only be those, wherever the reference now points.
+        if let Some(bytes) = digest.and_then(|digest| self.cached_blob(digest)) {
+            return Ok(bytes);
+        }
+        if self.config.offline {
+            return self
+                .cached_reference(&reference)
+                .ok_or_else(|| anyhow::anyhow!("OCI image '{oci_ref}' is not cached (offline)"));
+        }
+
+        let auth = self.auth(&reference, credentials)?;
+        let (manifest, _) = self
+            .client
+            .pull_image_manifest(&reference, &auth)
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to pull OCI image '{oci_ref}': {e}"))?;
+        let layer = select_layer(&manifest.layers)
+            .map_err(|e| anyhow::anyhow!("OCI image '{oci_ref}': {e}"))?;
+
+        let bytes = match self.cached_blob(&layer.digest) {
+            Some(bytes) => bytes,
+            None => {
+                let mut bytes = Vec::with_capacity(usize::try_from(layer.size).unwrap_or(0));
+                // Verifies the layer digest.
+                self.client
+                    .pull_blob(&reference, layer, &mut bytes)
+                    .await
+                    .map_err(|e| anyhow::anyhow!("Failed to pull OCI image '{oci_ref}': {e}"))?;
+                if let Some(path) = blob_path(&layer.digest) {
+                    self.store(&path, &bytes);
+                }
+                bytes
+            }
+        };
+        if let Some(path) = self.reference_path(&reference) {
+            self.store(&path, layer.digest.as_bytes());
+        }
+        Ok(bytes)
+    }
+
+    fn auth(
+        &self,
+        reference: &Reference,
+        credentials: Option<&RegistryCredentials>,
+    ) -> Result<RegistryAuth> {
+        if let Some(credentials) = credentials {
+            return Ok(match credentials {
+                RegistryCredentials::Basic { username, password } => {
+                    RegistryAuth::Basic(username.clone(), password.clone())
+                }
+                RegistryCredentials::Bearer { token } => RegistryAuth::Bearer(token.clone()),
+            });
+        }
+
+        use docker_credential::{CredentialRetrievalError, DockerCredential};
+        let registry = reference.registry();
+        let credential = match &self.config.docker_config {
+            Some(path) => {
+                let file = std::fs::File::open(path).map_err(|e| {
+                    anyhow::anyhow!("Cannot read Docker config {}: {e}", path.display())
+                })?;
+                docker_credential::get_credential_from_reader(file, registry)
+            }
+            None => docker_credential::get_credential(registry),
+        };
+        match credential {
+            Ok(DockerCredential::UsernamePassword(username, password)) => {
+                Ok(RegistryAuth::Basic(username, password))
+            }
+            Ok(DockerCredential::IdentityToken(token)) => Ok(RegistryAuth::Bearer(token)),
+            Err(CredentialRetrievalError::NoCredentialConfigured) => Ok(RegistryAuth::Anonymous),
+            // Without a default config file, pulls are anonymous.
+            Err(
+                CredentialRetrievalError::ConfigNotFound
+                | CredentialRetrievalError::ConfigReadError,
+            ) if self.config.docker_config.is_none() => Ok(RegistryAuth::Anonymous),
+            Err(e) => Err(anyhow::anyhow!(
+                "Cannot get credentials for registry '{registry}': {e}"
+            )),
+        }
+    }
+
+    // The cached bytes of the layer `reference` last resolved to.
+    fn cached_reference(&self, reference: &Reference) -> Option<Vec<u8>> {
+        let path = self
+            .config
+            .cache_dir
+            .as_ref()?
+            .join(self.reference_path(reference)?);
+        let digest = std::fs::read_to_string(path).ok()?;
+        self.cached_blob(digest.trim())
+    }
+
+    // The cached bytes of the layer with `digest`, if they are intact.
+    fn cached_blob(&self, digest: &str) -> Option<Vec<u8>> {
+        let path = self.config.cache_dir.as_ref()?.join(blob_path(digest)?);
+        let bytes = std::fs::read(&path).ok()?;
+        if sha256_digest(&bytes) != digest {
+            tracing::warn!("Ignoring corrupt cached OCI layer {}", path.display());
+            return None;
+        }
+        tracing::debug!("Reusing cached OCI layer {digest}");
+        Some(bytes)
+    }
+
+    fn reference_path(&self, reference: &Reference) -> Option<PathBuf> {
+        self.config.cache_dir.as_ref()?;
+        let key = format!("{:x}", Sha256::digest(reference.whole()));
+        Some(Path::new("refs").join(key))
+    }
+
+    // Best effort: a failed write only means pulling again next time.
+    fn store(&self, relative: &Path, bytes: &[u8]) {
+        let Some(cache_dir) = &self.config.cache_dir else {
+            return;
+        };
+        let path = cache_dir.join(relative);
+        let Some(dir) = path.parent() else {
+            return;
+        };
+        if let Err(e) = super::cache::write(dir, &path, bytes) {
+            tracing::warn!("Cannot write OCI cache entry {}: {e}", path.display());
+        }
+    }
+}
+
+// Registries use sha256 digests in practice; layers with any other digest
+// are not cached.
+fn blob_path(digest: &str) -> Option<PathBuf> {
+    if !is_sha256_digest(digest) {
+        return None;
+    }
+    Some(Path::new("blobs").join(digest.replace(':', "/")))
+}
+
+fn select_layer(layers: &[OciDescriptor]) -> Result<&OciDescriptor, String> {
+    let mut wasm_layers = layers
+        .iter()
+        .filter(|layer| WASM_MEDIA_TYPES.contains(&layer.media_type.as_str()));
+    match (wasm_layers.next(), wasm_layers.next()) {
+        (Some(layer), None) => Ok(layer),
+        (Some(_), Some(_)) => Err("more than one wasm layer".to_string()),
+        (None, _) => {
+            let media_types: Vec<_> = layers.iter().map(|l| l.media_type.as_str()).collect();
+            Err(format!(
+                "no wasm layer, expected one of {WASM_MEDIA_TYPES:?} but found {media_types:?}"
+            ))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn layer(media_type: &str) -> OciDescriptor {
+        OciDescriptor {
+            media_type: media_type.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn selects_the_wasm_layer() {
+        let layers = [
+            layer("application/vnd.oci.image.layer.v1.tar"),
+            layer("application/vnd.wasm.content.layer.v1+wasm"),
+        ];
+        assert_eq!(
+            select_layer(&layers).unwrap().media_type,
+            "application/vnd.wasm.content.layer.v1+wasm"
+        );
+
+        let err = select_layer(&layers[..1]).unwrap_err();
+        assert!(err.contains("no wasm layer"), "{err}");
+        let layers = [layer("application/wasm"), layer("application/wasm")];
+        assert_eq!(
+            select_layer(&layers).unwrap_err(),
+            "more than one wasm layer"
+        );
+    }
+
+    #[tokio::test]
+    async fn offline_pulls_use_the_cache() {
+        let cache_dir = tempfile::tempdir().unwrap();
+        let puller = OciPuller::new(&OciConfig {
+            cache_dir: Some(cache_dir.path().to_path_buf()),
+            offline: true,
+            docker_config: None,
+        });
+        let oci_ref = "registry.example.com/team/greeter:1.0";
+        let err = puller.pull(oci_ref, None, None).await.unwrap_err();
+        assert!(err.to_string().contains("is not cached"), "{err}");
+
+        let bytes = b"\0asm component";
+        let digest = sha256_digest(bytes);
+        puller.store(&blob_path(&digest).unwrap(), bytes);
+        // A pinned digest needs only the layer.
+        let pinned = puller.pull(oci_ref, None, Some(&digest)).await.unwrap();
+        assert_eq!(pinned, bytes);
+        let reference = oci_ref.parse().unwrap();
+        let path = puller.reference_path(&reference).unwrap();
+        puller.store(&path, digest.as_bytes());
+        assert_eq!(puller.pull(oci_ref, None, None).await.unwrap(), bytes);
+
+        // A corrupt layer is not used.
+        let blob = cache_dir.path().join(blob_path(&digest).unwrap());
+        std::fs::write(blob, b"tampered").unwrap();
+        assert!(puller.pull(oci_ref, None, None).await.is_err());
+    }
+
+    #[test]
+    fn credentials_from_auth_or_docker_config() {
+        let dir = tempfile::tempdir().unwrap();
+        let docker_config = dir.path().join("config.json");
+        // "ci:secret"
+        std::fs::write(
+            &docker_config,
+            r#"{"auths": {"registry.example.com": {"auth": "Y2k6c2VjcmV0"}}}"#,
+        )
+        .unwrap();
+        let puller = OciPuller::new(&OciConfig {
+            docker_config: Some(docker_config),
+            ..Default::default()
+        });
+        let reference = |r: &str| r.parse::<Reference>().unwrap();
+
+        let auth = puller
+            .auth(&reference("registry.example.com/team/greeter:1.0"), None)
+            .unwrap();
+        assert!(matches!(auth, RegistryAuth::Basic(u, p) if u == "ci" && p == "secret"));
+        let auth = puller
+            .auth(&reference("other.example.com/greeter:1.0"), None)
+            .unwrap();
+        assert!(matches!(auth, RegistryAuth::Anonymous));
+        let token = RegistryCredentials::Bearer {
+            token: "t0ken".to_string(),
+        };
+        let auth = puller
+            .auth(
+                &reference("registry.example.com/team/greeter:1.0"),
+                Some(&token),
+            )
+            .unwrap();
+        assert!(matches!(auth, RegistryAuth::Bearer(t) if t == "t0ken"));
+    }
+}
@@ -0,0 +1,130 @@
+//! Fetches `pkg:` components from WebAssembly package registries.
+//!
+//! ```toml
+//! [runtime.pkg]
+//! config-file = "/etc/composable/wasm-pkg.toml"
+//!
+//! [component.greeter]
+//! uri = "pkg:acme:greeter@^1.2"
+//! ```
+//!
+//! A uri names a package and, after `@`, an exact version or a semver
+//! requirement; the highest matching release that is not yanked is used.
+//! Without a version, the latest release is. Registries are resolved by
+//! `wasm-pkg-client` from `config-file`, or else from its global config
+//! (`~/.config/wasm-pkg/config.toml`). A `local` registry there serves
+//! releases from a directory, for offline use.
+
+use anyhow::Result;
+use futures_util::TryStreamExt;
+use std::path::PathBuf;
+use tokio::sync::OnceCell;
+use wasm_pkg_client::{Client, Config, PackageRef, Version, VersionReq};
+
+pub(crate) struct PkgFetcher {
+    config_file: Option<PathBuf>,
+    // Created on first use, so definitions without `pkg:` uris never read
+    // the registry config.
+    client: OnceCell<Client>,
+}
+
+impl PkgFetcher {
+    pub(crate) fn new(config_file: Option<PathBuf>) -> Self {
+        Self {
+            config_file,
+            client: OnceCell::new(),
+        }
+    }
+
+    /// The component bytes of `uri`, written without the `pkg:` scheme.
+    pub(crate) async fn fetch(&self, uri: &str) -> Result<Vec<u8>> {
+        let (package, requirement) = parse(uri)?;
+        let client = self.client().await?;
+        let err = |e: wasm_pkg_client::Error| anyhow::anyhow!("Failed to fetch 'pkg:{uri}': {e}");
+
+        let versions = client.list_all_versions(&package).await.map_err(err)?;
+        let version = versions
+            .into_iter()
+            .filter(|info| !info.yanked && requirement.matches(&info.version))
+            .map(|info| info.version)
+            .max()
+            .ok_or_else(|| anyhow::anyhow!("No release of 'pkg:{uri}' matches '{requirement}'"))?;
+        tracing::debug!("Resolved 'pkg:{uri}' to version {version}");
+
+        let release = client.get_release(&package, &version).await.map_err(err)?;
+        // Checked against the release's content digest while streaming.
+        let mut content = client
+            .stream_content(&package, &release)
+            .await
+            .map_err(err)?;
+        let mut bytes = Vec::new();
+        while let Some(chunk) = content.try_next().await.map_err(err)? {
+            bytes.extend_from_slice(&chunk);
+        }
+        Ok(bytes)
+    }
+
+    async fn client(&self) -> Result<&Client> {
+        self.client
+            .get_or_try_init(|| async {
+                let config = match &self.config_file {
+                    Some(path) => {
+                        let mut config = Config::default();
+                        config.merge(Config::from_file(path).await.map_err(|e| {
+                            anyhow::anyhow!("Cannot read registry config {}: {e}", path.display())
+                        })?);
+                        config
+                    }
+                    None => Config::global_defaults().await?,
+                };
+                Ok(Client::new(config))
+            })
+            .await
+    }
+}
+
+// `namespace:name`, then optionally `@` and a version or requirement. An
+// exact version matches only itself, not the compatible versions a bare
+// semver requirement would.
+fn parse(uri: &str) -> Result<(PackageRef, VersionReq)> {
+    let invalid =
+        |e: &dyn std::fmt::Display| anyhow::anyhow!("Invalid package uri 'pkg:{uri}': {e}");
+    let (package, version) = match uri.split_once('@') {
+        Some((package, version)) => (package, Some(version)),
+        None => (uri, None),
+    };
+    let package: PackageRef = package.parse().map_err(|e| invalid(&e))?;
+    let requirement = match version {
+        None => VersionReq::STAR,
+        Some(version) => match Version::parse(version) {
+            Ok(version) => VersionReq::parse(&format!("={version}")).map_err(|e| invalid(&e))?,
+            Err(_) => VersionReq::parse(version).map_err(|e| invalid(&e))?,
+        },
+    };
+    Ok((package, requirement))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_versions_and_requirements() {
+        let matches = |uri: &str, version: &str| {
+            let (package, requirement) = parse(uri).unwrap();
+            assert_eq!(package.to_string(), "acme:greeter");
+            requirement.matches(&Version::parse(version).unwrap())
+        };
+        assert!(matches("acme:greeter", "3.0.0"));
+        assert!(matches("acme:greeter@1.2.0", "1.2.0"));
+        assert!(!matches("acme:greeter@1.2.0", "1.3.0"));
+        assert!(matches("acme:greeter@^1.2", "1.9.1"));
+        assert!(!matches("acme:greeter@^1.2", "2.0.0"));
+        assert!(matches("acme:greeter@>=1.0, <1.5", "1.4.9"));
+
+        for uri in ["greeter@1.0.0", "acme:greeter@one"] {
+            let err = parse(uri).unwrap_err().to_string();
+            assert!(err.contains("Invalid package uri"), "{uri}: {err}");
+        }
+    }
+}
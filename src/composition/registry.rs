@@ -1,17 +1,22 @@
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
 use std::any::{Any, TypeId};
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::marker::PhantomData;
 use std::path::PathBuf;
 use std::sync::Arc;
 use wasmtime::component::{HasData, Linker};
 
-use super::composer::Composer;
+use super::cache::CompositionCache;
+use super::composer::{Composer, config_properties};
 use super::graph::{ComponentGraph, Edge, Node};
+use super::lockfile::sha256_digest;
+use super::oci::OciPuller;
+use super::pkg::PkgFetcher;
 use super::wit::Parser;
 use crate::types::{
-    CapabilityDefinition, ComponentDefinition, ComponentMetadata, ComponentState, Function,
+    CapabilityDefinition, ComponentDefinition, ComponentLimits, ComponentMetadata, ComponentState,
+    ConfigMode, Function, Lifecycle,
 };
 
 /// Trait implemented by host capability instances.
@@ -22,7 +27,8 @@ pub trait HostCapability: Send + Sync {
     /// Fully qualified interfaces this capability provides (namespace:package/interface@version)
     fn interfaces(&self) -> Vec<String>;
 
-    /// Add bindings to the linker. Called once per component instantiation.
+    /// Add bindings to the linker. Called when the runtime is built, once per
+    /// distinct set of capabilities that components are linked against.
     fn link(&self, linker: &mut Linker<ComponentState>) -> wasmtime::Result<()>;
 
     /// Create per-component-instance state. Called once per component instantiation.
@@ -139,6 +145,9 @@ pub struct Capability {
     pub scope: String,
     pub interfaces: Vec<String>,
     pub properties: HashMap<String, serde_json::Value>,
+    /// `properties` as written, before `${...}` placeholders were resolved.
+    #[serde(skip)]
+    pub unresolved_properties: HashMap<String, serde_json::Value>,
     #[serde(skip)]
     pub instance: Option<Box<dyn HostCapability>>,
 }
@@ -169,6 +178,17 @@ pub struct ComponentSpec {
     pub capabilities: Vec<String>,
     pub dependents: Vec<String>,
     pub functions: HashMap<String, Function>,
+    pub limits: ComponentLimits,
+    pub lifecycle: Lifecycle,
+    /// Compiled form of `bytes`, written by `composable compile`.
+    pub precompiled: Option<PathBuf>,
+    /// SHA-256 digest the `precompiled` artifact must have.
+    pub precompiled_digest: Option<String>,
+    /// Flattened `config` table, served by a `wasi:config` capability when
+    /// the component uses `config-mode = "dynamic"`. Empty otherwise.
+    pub config: Arc<HashMap<String, String>>,
+    /// `config` as written, before `${...}` placeholders were resolved.
+    pub unresolved_config: Arc<HashMap<String, String>>,
 }
 
 #[derive(Debug, Clone)]
@@ -272,6 +292,14 @@ pub async fn build_registries(
     let capability_registry = create_capability_registry(capability_definitions, factories)?;
 
     let sorted_indices = component_graph.get_build_order();
+    let cache = CompositionCache::new(
+        component_graph
+            .runtime_config()
+            .composition_cache_dir
+            .as_deref(),
+    );
+    let oci = OciPuller::new(&component_graph.runtime_config().oci);
+    let pkg = PkgFetcher::new(component_graph.runtime_config().pkg_config_file.clone());
 
     let mut built_components = HashMap::new();
 
@@ -286,6 +314,9 @@ pub async fn build_registries(
                 component_graph,
                 &temp_component_registry,
                 &capability_registry,
+                &cache,
+                &oci,
+                &pkg,
             )
             .await?;
 
@@ -356,6 +387,7 @@ fn create_capability_registry(
             scope: def.scope.clone(),
             interfaces,
             properties: def.properties,
+            unresolved_properties: def.unresolved_properties,
             instance: capability_instance,
         };
         capabilities.insert(def.name, capability);
@@ -448,6 +480,7 @@ fn get_interfaces_for_capability(kind: &str) -> Vec<String> {
             "wasi:clocks/monotonic-clock@0.2.12".to_string(),
             "wasi:clocks/wall-clock@0.2.12".to_string(),
         ],
+        ("wasi:config", _) => vec!["wasi:config/store@0.2.0-rc.1".to_string()],
         ("wasi:filesystem", WasiVersion::P3) => vec![
             "wasi:filesystem/preopens@0.3.0".to_string(),
             "wasi:filesystem/types@0.3.0".to_string(),
@@ -476,6 +509,14 @@ fn get_interfaces_for_capability(kind: &str) -> Vec<String> {
             "wasi:io/poll@0.2.12".to_string(),
             "wasi:io/streams@0.2.12".to_string(),
         ],
+        // Not versioned with the other WASI packages, so any kind version
+        // provides the same draft interfaces.
+        ("wasi:keyvalue", _) => vec![
+            "wasi:keyvalue/store@0.2.0-draft".to_string(),
+            "wasi:keyvalue/atomics@0.2.0-draft".to_string(),
+            "wasi:keyvalue/batch@0.2.0-draft".to_string(),
+        ],
+        ("wasi:logging", _) => vec!["wasi:logging/logging@0.1.0-draft".to_string()],
         ("wasi:random", WasiVersion::P3) => vec![
             "wasi:random/random@0.3.0".to_string(),
             "wasi:random/insecure@0.3.0".to_string(),
@@ -551,6 +592,9 @@ async fn process_component(
     component_graph: &ComponentGraph,
     component_registry: &ComponentRegistry,
     capability_registry: &CapabilityRegistry,
+    cache: &CompositionCache,
+    oci: &OciPuller,
+    pkg: &PkgFetcher,
 ) -> Result<ComponentSpec> {
     let definition = if let Node::Component(def) = &component_graph[node_index] {
         def
@@ -560,7 +604,7 @@ async fn process_component(
         ));
     };
 
-    let mut bytes = read_bytes(&definition.uri).await?;
+    let mut bytes = read_bytes(definition, oci, pkg).await?;
 
     let (metadata, mut imports, mut exports, mut functions) =
         Parser::parse(&bytes).map_err(|e| anyhow::anyhow!("Failed to parse component: {e}"))?;
@@ -568,15 +612,26 @@ async fn process_component(
     let imports_config = imports
         .iter()
         .any(|import| import.starts_with("wasi:config/store"));
-
-    if imports_config {
-        bytes = Composer::compose_with_config(&bytes, &definition.config).map_err(|e| {
-            anyhow::anyhow!(
-                "Failed to compose component '{}' with config: {}",
-                definition.name,
-                e
-            )
-        })?;
+    // In dynamic mode the import stays, for a `wasi:config` capability.
+    let compose_config = imports_config && definition.config_mode == ConfigMode::Static;
+    let mut config = HashMap::new();
+    let mut unresolved_config = HashMap::new();
+
+    if compose_config {
+        // Sorted, so the cache key does not depend on the table's order.
+        let config_table =
+            serde_json::to_vec(&definition.config.iter().collect::<BTreeMap<_, _>>())?;
+        bytes = cache
+            .get_or_compose("config", &[&bytes, &config_table], || {
+                Composer::compose_with_config(&bytes, &definition.config)
+            })
+            .map_err(|e| {
+                anyhow::anyhow!(
+                    "Failed to compose component '{}' with config: {}",
+                    definition.name,
+                    e
+                )
+            })?;
 
         let config_keys: Vec<_> = definition.config.keys().collect();
         tracing::info!(
@@ -585,6 +640,16 @@ async fn process_component(
         );
 
         imports.retain(|import| !import.starts_with("wasi:config/store"));
+    } else if imports_config {
+        let flatten = |config| {
+            config_properties(config)
+                .map(|properties| properties.into_iter().collect::<HashMap<_, _>>())
+                .map_err(|e| {
+                    anyhow::anyhow!("Component '{}': invalid config: {e}", definition.name)
+                })
+        };
+        config = flatten(&definition.config)?;
+        unresolved_config = flatten(&definition.unresolved_config)?;
     } else if !definition.config.is_empty() {
         tracing::warn!(
             "Config provided for component '{}' but component doesn't import wasi:config/store",
@@ -617,25 +682,41 @@ async fn process_component(
                 if matches!(edge, Edge::Interceptor(_)) && is_advice_component(&exports) {
                     // Current component is advice; the dependency is the target.
                     // Generate a wrapper from the target, plug in advice + target.
-                    let wrapper_bytes = composable_interceptor::create_from_component(
-                        &component_spec.bytes,
-                        &[],
-                    )
-                    .map_err(|e| {
-                        anyhow::anyhow!(
-                            "Failed to generate interceptor wrapper for '{}' targeting '{}': {e}",
-                            definition.name,
-                            dependency_def.name,
-                        )
-                    })?;
-                    let composed_wrapper = Composer::compose_components(&wrapper_bytes, &bytes)
+                    let wrapper_bytes = cache
+                        .get_or_compose("interceptor", &[&component_spec.bytes], || {
+                            composable_interceptor::create_from_component(
+                                &component_spec.bytes,
+                                &[],
+                            )
+                        })
+                        .map_err(|e| {
+                            anyhow::anyhow!(
+                                "Failed to generate interceptor wrapper for '{}' targeting '{}': {e}",
+                                definition.name,
+                                dependency_def.name,
+                            )
+                        })?;
+                    let composed_wrapper = cache
+                        .get_or_compose("compose", &[&wrapper_bytes, &bytes], || {
+                            Composer::compose_components(&wrapper_bytes, &bytes)
+                        })
                         .map_err(|e| {
                             anyhow::anyhow!(
                                 "Failed composing interceptor wrapper with advice '{}': {e}",
                                 definition.name,
                             )
                         })?;
-                    bytes = Composer::compose_components(&composed_wrapper, &component_spec.bytes)
+                    bytes = cache
+                        .get_or_compose(
+                            "compose",
+                            &[&composed_wrapper, &component_spec.bytes],
+                            || {
+                                Composer::compose_components(
+                                    &composed_wrapper,
+                                    &component_spec.bytes,
+                                )
+                            },
+                        )
                         .map_err(|e| {
                             anyhow::anyhow!(
                                 "Failed composing '{}' with target '{}': {e}",
@@ -656,15 +737,17 @@ async fn process_component(
                         dependency_def.name
                     );
                 } else {
-                    bytes = Composer::compose_components(&bytes, &component_spec.bytes).map_err(
-                        |e| {
+                    bytes = cache
+                        .get_or_compose("compose", &[&bytes, &component_spec.bytes], || {
+                            Composer::compose_components(&bytes, &component_spec.bytes)
+                        })
+                        .map_err(|e| {
                             anyhow::anyhow!(
                                 "Failed composing '{}' with dependency '{}': {e}",
                                 definition.name,
                                 dependency_def.name
                             )
-                        },
-                    )?;
+                        })?;
                     tracing::info!(
                         "Composed component '{}' with dependency '{}'",
                         definition.name,
@@ -684,6 +767,32 @@ async fn process_component(
         }
     }
 
+    // A precompiled artifact is only valid for the bytes it was compiled
+    // from, so it cannot be used once config or dependencies are composed in.
+    let composed = compose_config
+        || dependencies
+            .iter()
+            .any(|(index, _)| matches!(component_graph[*index], Node::Component(_)));
+    if composed && definition.precompiled.is_some() {
+        return Err(anyhow::anyhow!(
+            "Component '{}' is 'precompiled', but is composed with config or dependencies; \
+             precompile the composed component instead",
+            definition.name
+        ));
+    }
+    // Deserializing an artifact runs its machine code, so pinned bytes must
+    // come with a pinned artifact.
+    if definition.digest.is_some()
+        && definition.precompiled.is_some()
+        && definition.precompiled_digest.is_none()
+    {
+        return Err(anyhow::anyhow!(
+            "Component '{}': 'digest' pins its bytes but not its 'precompiled' artifact; \
+             set 'precompiled-digest' or lock the artifact",
+            definition.name
+        ));
+    }
+
     let capability_interfaces: std::collections::HashSet<String> = all_capabilities
         .iter()
         .filter_map(|name| capability_registry.get_capability(name))
@@ -716,6 +825,12 @@ async fn process_component(
         capabilities: all_capabilities.into_iter().collect(),
         dependents: Vec::new(),
         functions,
+        limits: definition.limits.clone(),
+        lifecycle: definition.lifecycle,
+        precompiled: definition.precompiled.as_ref().map(PathBuf::from),
+        precompiled_digest: definition.precompiled_digest.clone(),
+        config: Arc::new(config),
+        unresolved_config: Arc::new(unresolved_config),
     })
 }
 
@@ -725,21 +840,18 @@ fn is_advice_component(exports: &[String]) -> bool {
         .any(|e| e.starts_with("modulewise:interceptor/advice"))
 }
 
-async fn read_bytes(uri: &str) -> Result<Vec<u8>> {
-    if let Some(oci_ref) = uri.strip_prefix("oci://") {
-        let client = wasm_pkg_client::oci::client::Client::new(Default::default());
-        let image_ref = oci_ref.parse()?;
-        let auth = oci_client::secrets::RegistryAuth::Anonymous;
-        let media_types = vec!["application/wasm", "application/vnd.wasm.component"];
-
-        let image_data = client.pull(&image_ref, &auth, media_types).await?;
-
-        // Get the component bytes from the first layer
-        if let Some(layer) = image_data.layers.first() {
-            Ok(layer.data.to_vec())
-        } else {
-            Err(anyhow::anyhow!("No layers found in OCI image: {oci_ref}"))
-        }
+/// The bytes at the definition's `uri`, verified against its `digest`.
+pub(crate) async fn read_bytes(
+    definition: &ComponentDefinition,
+    oci: &OciPuller,
+    pkg: &PkgFetcher,
+) -> Result<Vec<u8>> {
+    let uri = definition.uri.as_str();
+    let digest = definition.digest.as_deref();
+    let bytes = if let Some(oci_ref) = uri.strip_prefix("oci://") {
+        oci.pull(oci_ref, definition.auth.as_ref(), digest).await?
+    } else if let Some(package) = uri.strip_prefix("pkg:") {
+        pkg.fetch(package).await?
     } else {
         // Handle both file:// and plain paths
         let path = if let Some(path_str) = uri.strip_prefix("file://") {
@@ -747,6 +859,16 @@ async fn read_bytes(uri: &str) -> Result<Vec<u8>> {
         } else {
             PathBuf::from(uri)
         };
-        Ok(std::fs::read(path)?)
+        std::fs::read(path)?
+    };
+    if let Some(digest) = digest {
+        let actual = sha256_digest(&bytes);
+        if actual != digest {
+            return Err(anyhow::anyhow!(
+                "Component '{}': '{uri}' has digest '{actual}', expected '{digest}'",
+                definition.name
+            ));
+        }
     }
+    Ok(bytes)
 }
@@ -1,8 +1,13 @@
 use anyhow::Result;
 use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
 
 use super::types::{CategoryClaim, ConfigHandler, PropertyMap};
-use crate::types::{CapabilityDefinition, ComponentDefinition, default_scope};
+use crate::composition::lockfile::is_sha256_digest;
+use crate::types::{
+    CapabilityDefinition, ComponentDefinition, ComponentLimits, ConfigMode, Lifecycle, OciConfig,
+    OptLevel, PoolingConfig, RegistryCredentials, RuntimeConfig, default_scope,
+};
 
 /// Handles `[component.*]` definitions.
 pub struct ComponentConfigHandler {
@@ -31,7 +36,23 @@ impl ConfigHandler for ComponentConfigHandler {
                 "imports",
                 "interceptors",
                 "config",
+                "config-mode",
                 "labels",
+                "timeout-ms",
+                "fuel",
+                "max-memory-bytes",
+                "max-tables",
+                "max-table-elements",
+                "max-instances",
+                "max-concurrency",
+                "max-queue",
+                "lifecycle",
+                "pool-size",
+                "max-calls",
+                "precompiled",
+                "precompiled-digest",
+                "auth",
+                "digest",
             ]
             .as_slice(),
         )])
@@ -56,7 +77,84 @@ impl ConfigHandler for ComponentConfigHandler {
         let imports = take_string_array(&mut properties, "imports").map_err(ctx)?;
         let interceptors = take_string_array(&mut properties, "interceptors").map_err(ctx)?;
         let config = take_object(&mut properties, "config").map_err(ctx)?;
+        let config_mode = match take_optional_string(&mut properties, "config-mode")
+            .map_err(ctx)?
+            .as_deref()
+        {
+            None | Some("static") => ConfigMode::Static,
+            Some("dynamic") => ConfigMode::Dynamic,
+            Some(other) => {
+                return Err(anyhow::anyhow!(
+                    "Component '{name}': invalid config-mode '{other}'. Must be one of: static, dynamic"
+                ));
+            }
+        };
         let labels = take_string_map(&mut properties, "labels").map_err(ctx)?;
+        let limits = ComponentLimits {
+            timeout_ms: take_optional_u64(&mut properties, "timeout-ms").map_err(ctx)?,
+            fuel: take_optional_u64(&mut properties, "fuel").map_err(ctx)?,
+            max_memory_bytes: take_optional_u64(&mut properties, "max-memory-bytes")
+                .map_err(ctx)?,
+            max_tables: take_optional_u32(&mut properties, "max-tables").map_err(ctx)?,
+            max_table_elements: take_optional_u64(&mut properties, "max-table-elements")
+                .map_err(ctx)?,
+            max_instances: take_optional_u32(&mut properties, "max-instances").map_err(ctx)?,
+            max_concurrency: take_optional_u32(&mut properties, "max-concurrency").map_err(ctx)?,
+            max_queue: take_optional_u32(&mut properties, "max-queue").map_err(ctx)?,
+        };
+        for (key, value) in [
+            ("timeout-ms", limits.timeout_ms),
+            ("fuel", limits.fuel),
+            ("max-concurrency", limits.max_concurrency.map(u64::from)),
+        ] {
+            if value == Some(0) {
+                return Err(anyhow::anyhow!(
+                    "Component '{name}': '{key}' must be positive"
+                ));
+            }
+        }
+        if limits.max_queue.is_some() && limits.max_concurrency.is_none() {
+            return Err(anyhow::anyhow!(
+                "Component '{name}': 'max-queue' requires 'max-concurrency'"
+            ));
+        }
+        let lifecycle = take_lifecycle(&mut properties, name)?;
+        let precompiled = take_optional_string(&mut properties, "precompiled").map_err(ctx)?;
+        // An intercepted component is composed into its interceptor chain, so
+        // its own compiled artifact is never run.
+        if precompiled.is_some() && !interceptors.is_empty() {
+            return Err(anyhow::anyhow!(
+                "Component '{name}': 'precompiled' cannot be combined with 'interceptors'"
+            ));
+        }
+        let digest = take_optional_string(&mut properties, "digest").map_err(ctx)?;
+        if let Some(digest) = &digest
+            && !is_sha256_digest(digest)
+        {
+            return Err(anyhow::anyhow!(
+                "Component '{name}': invalid digest '{digest}'. Must be 'sha256:' followed by 64 lowercase hex digits"
+            ));
+        }
+        let precompiled_digest =
+            take_optional_string(&mut properties, "precompiled-digest").map_err(ctx)?;
+        if let Some(digest) = &precompiled_digest {
+            if precompiled.is_none() {
+                return Err(anyhow::anyhow!(
+                    "Component '{name}': 'precompiled-digest' requires 'precompiled'"
+                ));
+            }
+            if !is_sha256_digest(digest) {
+                return Err(anyhow::anyhow!(
+                    "Component '{name}': invalid precompiled-digest '{digest}'. Must be 'sha256:' followed by 64 lowercase hex digits"
+                ));
+            }
+        }
+        let auth = take_auth(&mut properties, name)?;
+        if auth.is_some() && !uri.starts_with("oci://") {
+            return Err(anyhow::anyhow!(
+                "Component '{name}': 'auth' requires an oci:// uri"
+            ));
+        }
 
         if !properties.is_empty() {
             let unknown: Vec<_> = properties.keys().collect();
@@ -72,7 +170,16 @@ impl ConfigHandler for ComponentConfigHandler {
             imports,
             interceptors,
             config,
+            // Filled in when placeholders are resolved.
+            unresolved_config: HashMap::new(),
             labels,
+            limits,
+            lifecycle,
+            precompiled,
+            precompiled_digest,
+            config_mode,
+            auth,
+            digest,
         });
         Ok(())
     }
@@ -82,6 +189,84 @@ impl ConfigHandler for ComponentConfigHandler {
     }
 }
 
+// Parse `lifecycle` with its `pool-size` and `max-calls` settings:
+//
+//     lifecycle = "pool"      # "per-invocation" (default) | "singleton" | "pool"
+//     pool-size = 4           # required for "pool"
+//     max-calls = 1000        # recycle an instance after this many calls
+fn take_lifecycle(properties: &mut PropertyMap, name: &str) -> Result<Lifecycle> {
+    let ctx = |e: PropertyError| e.with_context("component", name);
+    let lifecycle = take_optional_string(properties, "lifecycle").map_err(ctx)?;
+    let pool_size = take_optional_u32(properties, "pool-size").map_err(ctx)?;
+    let max_calls = take_optional_u64(properties, "max-calls").map_err(ctx)?;
+
+    let lifecycle = match lifecycle.as_deref().unwrap_or("per-invocation") {
+        "per-invocation" => {
+            if max_calls.is_some() {
+                return Err(anyhow::anyhow!(
+                    "Component '{name}': 'max-calls' requires lifecycle = \"singleton\" or \"pool\""
+                ));
+            }
+            Lifecycle::PerInvocation
+        }
+        "singleton" => Lifecycle::Singleton { max_calls },
+        "pool" => match pool_size {
+            Some(size) if size > 0 => Lifecycle::Pool { size, max_calls },
+            _ => {
+                return Err(anyhow::anyhow!(
+                    "Component '{name}': lifecycle = \"pool\" requires a positive 'pool-size'"
+                ));
+            }
+        },
+        other => {
+            return Err(anyhow::anyhow!(
+                "Component '{name}': invalid lifecycle '{other}'. Must be one of: per-invocation, singleton, pool"
+            ));
+        }
+    };
+    if pool_size.is_some() && !matches!(lifecycle, Lifecycle::Pool { .. }) {
+        return Err(anyhow::anyhow!(
+            "Component '{name}': 'pool-size' requires lifecycle = \"pool\""
+        ));
+    }
+    if max_calls == Some(0) {
+        return Err(anyhow::anyhow!(
+            "Component '{name}': 'max-calls' must be positive"
+        ));
+    }
+    Ok(lifecycle)
+}
+
+// Parse `auth`, the registry credentials for an `oci://` uri:
+//
+//     auth = { username = "ci", password = "${process:env:REGISTRY_PASSWORD}" }
+//     auth = { token = "${process:env:REGISTRY_TOKEN}" }
+fn take_auth(properties: &mut PropertyMap, name: &str) -> Result<Option<RegistryCredentials>> {
+    let ctx = |e: PropertyError| e.with_context("component", name);
+    if !properties.contains_key("auth") {
+        return Ok(None);
+    }
+    let mut auth = take_object(properties, "auth").map_err(ctx)?;
+    let username = take_optional_string(&mut auth, "username").map_err(ctx)?;
+    let password = take_optional_string(&mut auth, "password").map_err(ctx)?;
+    let token = take_optional_string(&mut auth, "token").map_err(ctx)?;
+    if !auth.is_empty() {
+        let unknown: Vec<_> = auth.keys().collect();
+        return Err(anyhow::anyhow!(
+            "Component '{name}': 'auth' has unknown properties: {unknown:?}"
+        ));
+    }
+    match (username, password, token) {
+        (Some(username), Some(password), None) => {
+            Ok(Some(RegistryCredentials::Basic { username, password }))
+        }
+        (None, None, Some(token)) => Ok(Some(RegistryCredentials::Bearer { token })),
+        _ => Err(anyhow::anyhow!(
+            "Component '{name}': 'auth' must have either 'username' and 'password', or 'token'"
+        )),
+    }
+}
+
 /// Handles `[capability.*]` definitions.
 pub struct CapabilityConfigHandler {
     definitions: Vec<CapabilityDefinition>,
@@ -136,6 +321,8 @@ impl ConfigHandler for CapabilityConfigHandler {
             kind,
             scope,
             properties: remaining,
+            // Filled in when placeholders are resolved.
+            unresolved_properties: HashMap::new(),
         });
         Ok(())
     }
@@ -145,6 +332,200 @@ impl ConfigHandler for CapabilityConfigHandler {
     }
 }
 
+/// Handles `[runtime.*]` definitions, which configure the wasmtime engine.
+///
+/// ```toml
+/// [runtime.engine]
+/// cache-dir = "/var/cache/composable"
+/// debug-info = false
+/// opt-level = "speed"            # "none" | "speed" | "speed-and-size"
+///
+/// [runtime.allocator]
+/// strategy = "pooling"           # "on-demand" (default) | "pooling"
+/// total-component-instances = 1000
+/// total-memories = 1000
+/// total-tables = 1000
+///
+/// [runtime.composition]
+/// cache-dir = "/var/cache/composable/compositions"
+///
+/// [runtime.oci]
+/// cache-dir = "/var/cache/composable/oci"
+/// offline = false                # only use cache-dir, never a registry
+/// docker-config = "/etc/composable/docker.json"
+///
+/// [runtime.pkg]
+/// config-file = "/etc/composable/wasm-pkg.toml"
+/// ```
+pub struct RuntimeConfigHandler {
+    config: Arc<Mutex<RuntimeConfig>>,
+    seen: Vec<String>,
+}
+
+impl RuntimeConfigHandler {
+    pub fn new(config: Arc<Mutex<RuntimeConfig>>) -> Self {
+        Self {
+            config,
+            seen: Vec::new(),
+        }
+    }
+
+    fn handle_engine(&mut self, mut properties: PropertyMap) -> Result<()> {
+        let ctx = |e: PropertyError| e.with_context("runtime", "engine");
+        let cache_dir = take_optional_string(&mut properties, "cache-dir").map_err(ctx)?;
+        let debug_info = take_optional_bool(&mut properties, "debug-info").map_err(ctx)?;
+        let opt_level = take_optional_string(&mut properties, "opt-level")
+            .map_err(ctx)?
+            .map(|level| match level.as_str() {
+                "none" => Ok(OptLevel::None),
+                "speed" => Ok(OptLevel::Speed),
+                "speed-and-size" => Ok(OptLevel::SpeedAndSize),
+                other => Err(anyhow::anyhow!(
+                    "runtime 'engine': invalid opt-level '{other}'. Must be one of: none, speed, speed-and-size"
+                )),
+            })
+            .transpose()?;
+        reject_unknown_properties("engine", &properties)?;
+
+        let mut config = self.config.lock().unwrap();
+        config.cache_dir = cache_dir.map(Into::into);
+        config.debug_info = debug_info.unwrap_or(false);
+        config.opt_level = opt_level;
+        Ok(())
+    }
+
+    fn handle_composition(&mut self, mut properties: PropertyMap) -> Result<()> {
+        let ctx = |e: PropertyError| e.with_context("runtime", "composition");
+        let cache_dir = take_optional_string(&mut properties, "cache-dir").map_err(ctx)?;
+        reject_unknown_properties("composition", &properties)?;
+
+        self.config.lock().unwrap().composition_cache_dir = cache_dir.map(Into::into);
+        Ok(())
+    }
+
+    fn handle_oci(&mut self, mut properties: PropertyMap) -> Result<()> {
+        let ctx = |e: PropertyError| e.with_context("runtime", "oci");
+        let cache_dir = take_optional_string(&mut properties, "cache-dir").map_err(ctx)?;
+        let offline = take_optional_bool(&mut properties, "offline").map_err(ctx)?;
+        let docker_config = take_optional_string(&mut properties, "docker-config").map_err(ctx)?;
+        reject_unknown_properties("oci", &properties)?;
+
+        let offline = offline.unwrap_or(false);
+        if offline && cache_dir.is_none() {
+            return Err(anyhow::anyhow!(
+                "runtime 'oci': 'offline' requires 'cache-dir'"
+            ));
+        }
+        self.config.lock().unwrap().oci = OciConfig {
+            cache_dir: cache_dir.map(Into::into),
+            offline,
+            docker_config: docker_config.map(Into::into),
+        };
+        Ok(())
+    }
+
+    fn handle_pkg(&mut self, mut properties: PropertyMap) -> Result<()> {
+        let ctx = |e: PropertyError| e.with_context("runtime", "pkg");
+        let config_file = take_optional_string(&mut properties, "config-file").map_err(ctx)?;
+        reject_unknown_properties("pkg", &properties)?;
+
+        self.config.lock().unwrap().pkg_config_file = config_file.map(Into::into);
+        Ok(())
+    }
+
+    fn handle_allocator(&mut self, mut properties: PropertyMap) -> Result<()> {
+        let ctx = |e: PropertyError| e.with_context("runtime", "allocator");
+        let strategy = take_optional_string(&mut properties, "strategy").map_err(ctx)?;
+        let limits = PoolingConfig {
+            total_component_instances: take_optional_u32(
+                &mut properties,
+                "total-component-instances",
+            )
+            .map_err(ctx)?,
+            total_memories: take_optional_u32(&mut properties, "total-memories").map_err(ctx)?,
+            total_tables: take_optional_u32(&mut properties, "total-tables").map_err(ctx)?,
+        };
+        reject_unknown_properties("allocator", &properties)?;
+
+        let pooling = match strategy.as_deref().unwrap_or("on-demand") {
+            "pooling" => Some(limits),
+            "on-demand" => {
+                if limits != PoolingConfig::default() {
+                    return Err(anyhow::anyhow!(
+                        "runtime 'allocator': pool limits require strategy = \"pooling\""
+                    ));
+                }
+                None
+            }
+            other => {
+                return Err(anyhow::anyhow!(
+                    "runtime 'allocator': invalid strategy '{other}'. Must be one of: on-demand, pooling"
+                ));
+            }
+        };
+        self.config.lock().unwrap().pooling = pooling;
+        Ok(())
+    }
+}
+
+impl ConfigHandler for RuntimeConfigHandler {
+    fn claimed_categories(&self) -> Vec<CategoryClaim> {
+        vec![CategoryClaim::all("runtime")]
+    }
+
+    fn claimed_properties(&self) -> HashMap<&str, &[&str]> {
+        HashMap::new()
+    }
+
+    fn accepts_unclaimed_properties(&self, category: &str) -> bool {
+        // Properties differ per definition, so they are validated in
+        // handle_category instead.
+        category == "runtime"
+    }
+
+    fn handle_category(
+        &mut self,
+        category: &str,
+        name: &str,
+        properties: PropertyMap,
+    ) -> Result<()> {
+        if category != "runtime" {
+            return Err(anyhow::anyhow!(
+                "RuntimeConfigHandler received unexpected category '{category}'"
+            ));
+        }
+        if self.seen.iter().any(|n| n == name) {
+            return Err(anyhow::anyhow!(
+                "runtime '{name}' is defined more than once"
+            ));
+        }
+        match name {
+            "engine" => self.handle_engine(properties)?,
+            "allocator" => self.handle_allocator(properties)?,
+            "composition" => self.handle_composition(properties)?,
+            "oci" => self.handle_oci(properties)?,
+            "pkg" => self.handle_pkg(properties)?,
+            _ => {
+                return Err(anyhow::anyhow!(
+                    "Unknown runtime definition '{name}'. Must be one of: engine, allocator, composition, oci, pkg"
+                ));
+            }
+        }
+        self.seen.push(name.to_string());
+        Ok(())
+    }
+}
+
+fn reject_unknown_properties(name: &str, properties: &PropertyMap) -> Result<()> {
+    if !properties.is_empty() {
+        let unknown: Vec<_> = properties.keys().collect();
+        return Err(anyhow::anyhow!(
+            "runtime '{name}' has unknown properties: {unknown:?}"
+        ));
+    }
+    Ok(())
+}
+
 // --- Property extractors ---
 
 enum PropertyError {
@@ -198,6 +579,55 @@ fn take_optional_string(
     }
 }
 
+fn take_optional_bool(
+    properties: &mut PropertyMap,
+    key: &str,
+) -> Result<Option<bool>, PropertyError> {
+    match properties.remove(key) {
+        Some(serde_json::Value::Bool(b)) => Ok(Some(b)),
+        Some(got) => Err(PropertyError::TypeMismatch {
+            key: key.into(),
+            expected: "a boolean",
+            got,
+        }),
+        None => Ok(None),
+    }
+}
+
+fn take_optional_u64(
+    properties: &mut PropertyMap,
+    key: &str,
+) -> Result<Option<u64>, PropertyError> {
+    match properties.remove(key) {
+        Some(got) => match got.as_u64() {
+            Some(v) => Ok(Some(v)),
+            None => Err(PropertyError::TypeMismatch {
+                key: key.into(),
+                expected: "a non-negative integer",
+                got,
+            }),
+        },
+        None => Ok(None),
+    }
+}
+
+fn take_optional_u32(
+    properties: &mut PropertyMap,
+    key: &str,
+) -> Result<Option<u32>, PropertyError> {
+    match properties.remove(key) {
+        Some(got) => match got.as_u64().and_then(|v| u32::try_from(v).ok()) {
+            Some(v) => Ok(Some(v)),
+            None => Err(PropertyError::TypeMismatch {
+                key: key.into(),
+                expected: "a non-negative 32-bit integer",
+                got,
+            }),
+        },
+        None => Ok(None),
+    }
+}
+
 fn take_string_array(
     properties: &mut PropertyMap,
     key: &str,
@@ -40,6 +40,7 @@ fn load_toml_file(path: &Path) -> Result<Vec<GenericDefinition>> {
     let toml_doc: toml::Value = toml::from_str(&content)?;
 
     let mut definitions = Vec::new();
+    let dir = path.parent().unwrap_or(Path::new(""));
 
     let toml::Value::Table(table) = toml_doc else {
         return Err(anyhow::anyhow!(
@@ -59,7 +60,12 @@ fn load_toml_file(path: &Path) -> Result<Vec<GenericDefinition>> {
                 ));
             };
 
-            let properties = convert_toml_table_to_property_map(&def_table)?;
+            let mut properties = convert_toml_table_to_property_map(&def_table)?;
+            if category == "component" {
+                for key in ["uri", "precompiled"] {
+                    resolve_relative_path(&mut properties, key, dir);
+                }
+            }
             definitions.push(GenericDefinition {
                 category: category.clone(),
                 name,
@@ -71,7 +77,26 @@ fn load_toml_file(path: &Path) -> Result<Vec<GenericDefinition>> {
     Ok(definitions)
 }
 
-/// Loads definitions from .wasm file paths and OCI URIs.
+// A relative file path in a component's `uri` or `precompiled` is relative to
+// the definitions file, so a directory written by `composable compile` can be
+// moved as a whole.
+fn resolve_relative_path(properties: &mut PropertyMap, key: &str, dir: &Path) {
+    let Some(serde_json::Value::String(value)) = properties.get_mut(key) else {
+        return;
+    };
+    if value.starts_with("oci://") || value.starts_with("pkg:") {
+        return;
+    }
+    let (scheme, path) = match value.strip_prefix("file://") {
+        Some(path) => ("file://", path),
+        None => ("", value.as_str()),
+    };
+    if Path::new(path).is_relative() {
+        *value = format!("{scheme}{}", dir.join(path).display());
+    }
+}
+
+/// Loads definitions from .wasm file paths, OCI URIs and package URIs.
 pub struct WasmLoader {
     paths: Vec<PathBuf>,
 }
@@ -86,6 +111,7 @@ impl DefinitionLoader for WasmLoader {
     fn claim(&mut self, path: &Path) -> bool {
         let path_str = path.to_string_lossy();
         if path_str.starts_with("oci://")
+            || path_str.starts_with("pkg:")
             || path.extension().and_then(|s| s.to_str()) == Some("wasm")
         {
             self.paths.push(path.to_path_buf());
@@ -116,7 +142,16 @@ impl DefinitionLoader for WasmLoader {
 }
 
 fn extract_component_name(path_str: &str, path: &Path) -> Result<String> {
-    if path_str.starts_with("oci://") {
+    if let Some(package) = path_str.strip_prefix("pkg:") {
+        // `namespace:name@version`
+        let package = package
+            .split_once('@')
+            .map_or(package, |(package, _)| package);
+        match package.split_once(':') {
+            Some((_namespace, name)) if !name.is_empty() => Ok(name.to_string()),
+            _ => Err(anyhow::anyhow!("Invalid package URI format: {path_str}")),
+        }
+    } else if path_str.starts_with("oci://") {
         let oci_ref = path_str.strip_prefix("oci://").unwrap();
         if let Some((pkg_part, _version)) = oci_ref.rsplit_once(':') {
             if let Some((_prefix, name)) = pkg_part.rsplit_once('/') {
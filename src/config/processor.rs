@@ -1,14 +1,16 @@
 use anyhow::Result;
 use std::collections::{HashMap, HashSet};
 use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
 
-use super::handlers::{CapabilityConfigHandler, ComponentConfigHandler};
+use super::handlers::{CapabilityConfigHandler, ComponentConfigHandler, RuntimeConfigHandler};
 use super::types::{ConfigHandler, DefinitionLoader, GenericDefinition, PropertyMap};
-use crate::types::{CapabilityDefinition, ComponentDefinition};
+use crate::types::{CapabilityDefinition, ComponentDefinition, RegistryCredentials, RuntimeConfig};
 
 pub struct ConfigProcessor {
     loaders: Vec<Box<dyn DefinitionLoader>>,
     handlers: Vec<Box<dyn ConfigHandler>>,
+    runtime_config: Arc<Mutex<RuntimeConfig>>,
 }
 
 impl ConfigProcessor {
@@ -16,6 +18,7 @@ impl ConfigProcessor {
         Self {
             loaders: Vec::new(),
             handlers: Vec::new(),
+            runtime_config: Arc::new(Mutex::new(RuntimeConfig::default())),
         }
     }
 
@@ -27,6 +30,12 @@ impl ConfigProcessor {
         self.handlers.push(handler);
     }
 
+    /// Runtime settings, filled in from `[runtime.*]` definitions by
+    /// [`process`](Self::process).
+    pub fn runtime_config(&self) -> Arc<Mutex<RuntimeConfig>> {
+        self.runtime_config.clone()
+    }
+
     /// Route paths to loaders via claim, then run the full config pipeline.
     pub fn process(
         mut self,
@@ -67,6 +76,9 @@ impl ConfigProcessor {
         let mut all_handlers: Vec<Box<dyn ConfigHandler>> = Vec::new();
         all_handlers.push(Box::new(ComponentConfigHandler::new()));
         all_handlers.push(Box::new(CapabilityConfigHandler::new()));
+        all_handlers.push(Box::new(RuntimeConfigHandler::new(
+            self.runtime_config.clone(),
+        )));
         all_handlers.extend(self.handlers);
 
         dispatch(&mut definitions, &mut all_handlers)?;
@@ -129,7 +141,15 @@ fn dispatch(
         }
     }
 
-    // Build claimed properties map: (category, property) => handler index
+    // Build claimed properties map: (category, property) => handler index.
+    // Handlers that share a category through selectors each own the
+    // definitions they match, so they may claim the same properties on it.
+    // Such properties stay with whichever handler owns the definition.
+    let owns = |idx: usize, category: &str| {
+        category_claims
+            .get(category)
+            .is_some_and(|claims| claims.iter().any(|c| c.handler_idx == idx))
+    };
     let mut property_claims: HashMap<(String, String), usize> = HashMap::new();
     for (idx, handler) in handlers.iter().enumerate() {
         for (category, properties) in handler.claimed_properties() {
@@ -138,6 +158,9 @@ fn dispatch(
                 if let Some(&existing_idx) = property_claims.get(&key)
                     && existing_idx != idx
                 {
+                    if owns(idx, category) && owns(existing_idx, category) {
+                        continue;
+                    }
                     return Err(anyhow::anyhow!(
                         "Property '{prop}' on category '{category}' claimed by multiple handlers"
                     ));
@@ -158,8 +181,13 @@ fn dispatch(
 
         let owner_idx = resolve_owner(claims, &def)?;
 
-        let (core_properties, claimed_by_handler) =
-            split_properties(def.properties, &def.category, owner_idx, &property_claims);
+        let (core_properties, claimed_by_handler) = split_properties(
+            def.properties,
+            &def.category,
+            owner_idx,
+            &property_claims,
+            |idx| owns(idx, &def.category),
+        );
 
         // Reject any top-level property not claimed by any registered handler,
         // unless the owner handler accepts unclaimed properties as pass-through
@@ -263,11 +291,13 @@ fn flatten_recursive(
     }
 }
 
+// Split off the properties claimed by handlers that do not own the category.
 fn split_properties(
     mut properties: PropertyMap,
     category: &str,
     owner_idx: usize,
     property_claims: &HashMap<(String, String), usize>,
+    owns_category: impl Fn(usize) -> bool,
 ) -> (PropertyMap, HashMap<usize, PropertyMap>) {
     let mut claimed: HashMap<usize, PropertyMap> = HashMap::new();
 
@@ -276,6 +306,7 @@ fn split_properties(
         let lookup = (category.to_string(), key.clone());
         if let Some(&handler_idx) = property_claims.get(&lookup)
             && handler_idx != owner_idx
+            && !owns_category(handler_idx)
             && let Some(value) = properties.remove(&key)
         {
             claimed.entry(handler_idx).or_default().insert(key, value);
@@ -289,13 +320,23 @@ fn split_properties(
 
 fn resolve_placeholders_in_components(definitions: &mut [ComponentDefinition]) -> Result<()> {
     for def in definitions {
+        def.unresolved_config = def.config.clone();
         resolve_placeholders_in_map(&mut def.config)?;
+        match &mut def.auth {
+            Some(RegistryCredentials::Basic { username, password }) => {
+                resolve_placeholders_in_string(username)?;
+                resolve_placeholders_in_string(password)?;
+            }
+            Some(RegistryCredentials::Bearer { token }) => resolve_placeholders_in_string(token)?,
+            None => {}
+        }
     }
     Ok(())
 }
 
 fn resolve_placeholders_in_capabilities(definitions: &mut [CapabilityDefinition]) -> Result<()> {
     for def in definitions {
+        def.unresolved_properties = def.properties.clone();
         resolve_placeholders_in_map(&mut def.properties)?;
     }
     Ok(())
@@ -310,11 +351,7 @@ fn resolve_placeholders_in_map(map: &mut HashMap<String, serde_json::Value>) ->
 
 fn resolve_placeholders_in_value(value: &mut serde_json::Value) -> Result<()> {
     match value {
-        serde_json::Value::String(s) => {
-            if let Some(resolved) = resolve_placeholder(s)? {
-                *s = resolved;
-            }
-        }
+        serde_json::Value::String(s) => resolve_placeholders_in_string(s)?,
         serde_json::Value::Array(arr) => {
             for item in arr {
                 resolve_placeholders_in_value(item)?;
@@ -330,6 +367,13 @@ fn resolve_placeholders_in_value(value: &mut serde_json::Value) -> Result<()> {
     Ok(())
 }
 
+fn resolve_placeholders_in_string(s: &mut String) -> Result<()> {
+    if let Some(resolved) = resolve_placeholder(s)? {
+        *s = resolved;
+    }
+    Ok(())
+}
+
 fn resolve_placeholder(s: &str) -> Result<Option<String>> {
     if !s.starts_with("${") || !s.ends_with('}') {
         return Ok(None);
@@ -18,8 +18,10 @@ pub use message::{
 pub use runtime::{ComponentInstance, ComponentResource, Runtime, RuntimeBuilder, Val};
 pub use service::Service;
 pub use types::{
-    CapabilityDefinition, Component, ComponentDefinition, ComponentInvoker, ComponentMetadata,
-    ComponentState, Function, FunctionParam, PROPAGATED_HEADERS,
+    CapabilityDefinition, Component, ComponentBusy, ComponentDefinition, ComponentInvoker,
+    ComponentLimits, ComponentMetadata, ComponentState, ConfigMode, ExecutionLimitExceeded,
+    Function, FunctionParam, InvocationError, Lifecycle, PROPAGATED_HEADERS, RegistryCredentials,
+    ResourceLimitExceeded,
 };
 
 // exposed for testing, hidden from docs
@@ -34,6 +36,7 @@ pub mod mapping;
 pub(crate) mod message;
 #[cfg(feature = "messaging")]
 mod messaging;
+mod metrics;
 mod runtime;
 pub mod schema;
 pub(crate) mod service;
@@ -1,8 +1,8 @@
 use anyhow::Result;
 use clap::{Parser, Subcommand};
 use composable_runtime::{
-    Component, ComponentGraph, FunctionParam, MessageBuilder, MessageHeaders, PROPAGATION_CONTEXT,
-    PropagationContext, Runtime, Selector,
+    Component, ComponentGraph, FunctionParam, InvocationError, MessageBuilder, MessageHeaders,
+    PROPAGATION_CONTEXT, PropagationContext, Runtime, Selector,
 };
 use rustyline::Editor;
 use rustyline::error::ReadlineError;
@@ -45,6 +45,10 @@ enum Command {
         /// Component definition files (.toml) and standalone .wasm files
         #[arg(required = true)]
         definitions: Vec<PathBuf>,
+
+        /// Reload when a definition file or a local .wasm it refers to changes
+        #[arg(long)]
+        watch: bool,
     },
     /// Interactive shell for dev and debugging
     Shell {
@@ -82,6 +86,45 @@ enum Command {
         #[arg(long)]
         reply_timeout: Option<u64>,
     },
+    /// Compose and compile components ahead of time
+    Compile {
+        /// Component definition files (.toml) and standalone .wasm files
+        #[arg(required = true)]
+        definitions: Vec<PathBuf>,
+
+        /// Output directory for the compiled components and their definitions
+        #[arg(long, short)]
+        output: PathBuf,
+    },
+    /// Write fully composed components for other component hosts
+    Compose {
+        /// Component definition files (.toml) and standalone .wasm files
+        #[arg(required = true)]
+        definitions: Vec<PathBuf>,
+
+        /// Component to compose
+        #[arg(long, required_unless_present = "all", conflicts_with = "all")]
+        component: Option<String>,
+
+        /// Compose every component
+        #[arg(long)]
+        all: bool,
+
+        /// Output .wasm file, or output directory with --all. Each manifest
+        /// is written next to its component, with a .toml extension.
+        #[arg(long, short)]
+        output: PathBuf,
+    },
+    /// Pin the digest of each component uri in a lockfile
+    Lock {
+        /// Component definition files (.toml) and standalone .wasm files
+        #[arg(required = true)]
+        definitions: Vec<PathBuf>,
+
+        /// Lockfile to write, used by later runs from the same directory
+        #[arg(long, short, default_value = "composable.lock")]
+        output: PathBuf,
+    },
     /// Inspect the dependency graph
     Graph {
         /// Component definition files (.toml) and standalone .wasm files
@@ -179,11 +222,64 @@ async fn main() -> Result<()> {
 
             runtime.shutdown().await;
         }
-        Command::Run { definitions } => {
-            tracing_subscriber::fmt()
-                .with_env_filter(EnvFilter::from_default_env())
-                .init();
+        Command::Compile {
+            definitions,
+            output,
+        } => {
+            let runtime = Runtime::builder().from_paths(&definitions).build().await?;
+            let definitions_file = runtime.compile_to(&output)?;
+            println!(
+                "Compiled {} components to {}",
+                runtime.list_components(None).len(),
+                output.display()
+            );
+            println!("Run with: composable run {}", definitions_file.display());
+        }
+        Command::Compose {
+            definitions,
+            component,
+            all: _,
+            output,
+        } => {
             let runtime = Runtime::builder().from_paths(&definitions).build().await?;
+            let components = match component {
+                Some(name) => vec![(name, output)],
+                None => runtime
+                    .list_components(None)
+                    .into_iter()
+                    .map(|c| {
+                        let path = output.join(format!("{}.wasm", c.metadata.name));
+                        (c.metadata.name, path)
+                    })
+                    .collect(),
+            };
+            for (name, path) in components {
+                let manifest = runtime.compose_to(&name, &path)?;
+                println!(
+                    "Composed {name} to {} (manifest: {})",
+                    path.display(),
+                    manifest.display()
+                );
+            }
+        }
+        Command::Lock {
+            definitions,
+            output,
+        } => {
+            let graph = build_graph(&definitions)?;
+            let count = graph.lock(&output).await?;
+            println!("Locked {count} component digests in {}", output.display());
+        }
+        Command::Run { definitions, watch } => {
+            // Without RUST_LOG, still show what components log at info and above.
+            let filter = EnvFilter::try_from_default_env()
+                .unwrap_or_else(|_| EnvFilter::new("error,composable_runtime::guest=info"));
+            tracing_subscriber::fmt().with_env_filter(filter).init();
+            let mut builder = Runtime::builder().from_paths(&definitions);
+            if watch {
+                builder = builder.watch();
+            }
+            let runtime = builder.build().await?;
             runtime.run().await?;
         }
     }
@@ -239,7 +335,8 @@ async fn run_invoke(
             PROPAGATION_CONTEXT.scope(Some(ctx), invoke_fut).await
         }
         _ => invoke_fut.await,
-    }?;
+    }
+    .inspect_err(print_backtrace)?;
     // Write a bytes result to stdout, rather than render as a JSON array.
     if returns_bytes(function.result()) {
         let bytes: Vec<u8> = result
@@ -265,6 +362,17 @@ async fn run_invoke(
     Ok(())
 }
 
+// The error message leaves out a trap's Wasm backtrace, which can be long.
+fn print_backtrace(error: &InvocationError) {
+    if let InvocationError::Trap {
+        backtrace: Some(backtrace),
+        ..
+    } = error
+    {
+        eprintln!("{backtrace}");
+    }
+}
+
 // Consider the return value bytes if the function's result is a `list<u8>`.
 // The generated schema for such a result will be an array of numbers that
 // includes `minimum: 0` and `maximum: 255`.
@@ -343,7 +451,7 @@ async fn run_shell(
 async fn handle_command(
     line: String,
     runtime: &Runtime,
-    components: &[&Component],
+    components: &[Component],
     env: Option<&HashMap<String, String>>,
 ) -> Result<(), ()> {
     let parts = parse_quoted_args(&line);
@@ -472,7 +580,10 @@ async fn handle_command(
                                                     serde_json::to_string_pretty(&result).unwrap()
                                                 );
                                             }
-                                            Err(e) => eprintln!("Error: {e}"),
+                                            Err(e) => {
+                                                eprintln!("Error: {e}");
+                                                print_backtrace(&e);
+                                            }
                                         }
                                     }
                                     Err(e) => eprintln!("Error: {e}"),
@@ -547,8 +658,8 @@ fn parse_invoke_args(
     Ok(final_args)
 }
 
-fn find_component<'a>(components: &[&'a Component], name: &str) -> Option<&'a Component> {
-    components.iter().find(|c| c.metadata.name == name).copied()
+fn find_component<'a>(components: &'a [Component], name: &str) -> Option<&'a Component> {
+    components.iter().find(|c| c.metadata.name == name)
 }
 
 fn parse_quoted_args(line: &str) -> Vec<String> {
@@ -1,4 +1,5 @@
 use std::collections::HashMap;
+use std::fmt;
 use std::future::Future;
 use std::sync::Arc;
 
@@ -6,7 +7,7 @@ use crate::context::{PROPAGATION_CONTEXT, PropagationContext};
 #[cfg(test)]
 use crate::mapping::ParamMapping;
 use crate::mapping::{MappingConfig, MessageMapper};
-use crate::types::{Component, ComponentInvoker, PROPAGATED_HEADERS};
+use crate::types::{Component, ComponentInvoker, InvocationError, PROPAGATED_HEADERS};
 
 use super::channel::ReplyPublisher;
 use crate::message::{Message, MessageHeaders};
@@ -18,7 +19,53 @@ use crate::message::{Message, MessageHeaders};
 /// from messages by the activator. The dispatcher delivers messages through
 /// this trait without knowing which kind of handler it is.
 pub trait Handler: Send + Sync {
-    fn handle(&self, msg: Message) -> impl Future<Output = Result<(), String>> + Send;
+    fn handle(&self, msg: Message) -> impl Future<Output = Result<(), HandlerError>> + Send;
+}
+
+/// Why a handler did not process a message.
+///
+/// The dispatcher nacks a retryable failure so the message can be redelivered,
+/// and rejects any other, since redelivering a message that cannot be
+/// processed would only fail the same way again. A failed message is never
+/// acked.
+#[derive(Debug)]
+pub struct HandlerError {
+    pub message: String,
+    pub retryable: bool,
+}
+
+impl HandlerError {
+    pub fn permanent(message: impl Into<String>) -> Self {
+        Self {
+            message: message.into(),
+            retryable: false,
+        }
+    }
+}
+
+// Failures not otherwise classified are assumed to be retryable.
+impl From<String> for HandlerError {
+    fn from(message: String) -> Self {
+        Self {
+            message,
+            retryable: true,
+        }
+    }
+}
+
+impl From<InvocationError> for HandlerError {
+    fn from(error: InvocationError) -> Self {
+        Self {
+            retryable: error.is_transient(),
+            message: error.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for HandlerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
 }
 
 /// Handler that invokes a Wasm component per message.
@@ -55,12 +102,12 @@ impl Activator {
             .get_component(component_name)
             .ok_or_else(|| format!("component '{component_name}' not found"))?;
 
-        let mode = if Self::exports_handler_interface(component) {
+        let mode = if Self::exports_handler_interface(&component) {
             InvocationMode::Direct
         } else {
             InvocationMode::Mapped {
                 mapper: Box::new(MessageMapper::from_component(
-                    component,
+                    &component,
                     function_key,
                     config,
                 )?),
@@ -84,11 +131,15 @@ impl Activator {
 }
 
 impl Handler for Activator {
-    async fn handle(&self, msg: Message) -> Result<(), String> {
+    async fn handle(&self, msg: Message) -> Result<(), HandlerError> {
         match &self.mode {
-            InvocationMode::Direct => Err("direct handler mode not yet implemented".to_string()),
+            InvocationMode::Direct => Err(HandlerError::permanent(
+                "direct handler mode not yet implemented",
+            )),
             InvocationMode::Mapped { mapper } => {
-                let invocation = mapper.to_invocation(&msg)?;
+                let invocation = mapper
+                    .to_invocation(&msg)
+                    .map_err(HandlerError::permanent)?;
 
                 // Collect propagated headers from the inbound message.
                 let mut propagated: HashMap<String, String> = HashMap::new();
@@ -114,12 +165,13 @@ impl Handler for Activator {
                         entries: propagated.clone(),
                     };
                     PROPAGATION_CONTEXT.scope(Some(ctx), invoke_fut).await
-                }
-                .map_err(|e| e.to_string())?;
+                }?;
 
                 if let Some(reply_to) = msg.headers().reply_to() {
                     let publisher = self.reply_publisher.as_ref().ok_or_else(|| {
-                        format!("reply-to '{reply_to}' requested but no reply publisher available")
+                        HandlerError::permanent(format!(
+                            "reply-to '{reply_to}' requested but no reply publisher available"
+                        ))
                     })?;
 
                     // Reply propagation: the propagated context plus content-type
@@ -134,7 +186,9 @@ impl Handler for Activator {
                         );
                     }
 
-                    let reply = mapper.from_invocation_result(&result, propagated)?;
+                    let reply = mapper
+                        .from_invocation_result(&result, propagated)
+                        .map_err(HandlerError::permanent)?;
                     publisher
                         .publish(reply_to, reply)
                         .await
@@ -586,9 +640,10 @@ mod tests {
         assert!(result.is_err());
         let err = result.unwrap_err();
         assert!(
-            err.contains("missing required arg 'b'"),
+            err.message.contains("missing required arg 'b'"),
             "unexpected error: {err}"
         );
+        assert!(!err.retryable);
     }
 
     #[tokio::test]
@@ -660,7 +715,8 @@ mod tests {
         assert!(result.is_err());
         let err = result.unwrap_err();
         assert!(
-            err.contains("non-object body cannot be mapped to 2 parameters"),
+            err.message
+                .contains("non-object body cannot be mapped to 2 parameters"),
             "unexpected error: {err}"
         );
     }
@@ -811,7 +867,10 @@ mod tests {
         let result = activator.handle(msg).await;
         assert!(result.is_err());
         let err = result.unwrap_err();
-        assert!(err.contains("unknown path"), "unexpected error: {err}");
+        assert!(
+            err.message.contains("unknown path"),
+            "unexpected error: {err}"
+        );
     }
 
     // When a mapping references a path that doesn't exist in the message body,
@@ -853,7 +912,7 @@ mod tests {
         assert!(result.is_err());
         let err = result.unwrap_err();
         assert!(
-            err.contains("body.nope"),
+            err.message.contains("body.nope"),
             "expected error to mention missing path 'body.nope', got: {err}"
         );
     }
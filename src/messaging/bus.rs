@@ -6,6 +6,7 @@ use std::time::Duration;
 use anyhow::Result;
 use tokio_util::sync::CancellationToken;
 
+use crate::metrics::Metrics;
 use crate::types::ComponentInvoker;
 
 use super::activator::Activator;
@@ -32,6 +33,11 @@ pub(crate) trait Bus: Send + Sync {
 
     fn start(&self) -> Result<()>;
 
+    // Replace the running dispatchers with ones for the pending
+    // subscriptions. The current dispatchers finish their in-flight messages
+    // first; messages published meanwhile wait in their channels.
+    fn restart(&self) -> Pin<Box<dyn Future<Output = Result<()>> + Send + '_>>;
+
     fn shutdown(&self) -> Pin<Box<dyn Future<Output = ()> + Send + '_>>;
 }
 
@@ -66,25 +72,97 @@ pub(crate) struct GenericBus<C: Channel, F: ChannelFactory<C>> {
     subscriptions: Mutex<Vec<SubscriptionConfig>>,
     invoker: Mutex<Option<Arc<dyn ComponentInvoker>>>,
     shared_reply_publisher: Mutex<Option<Arc<dyn ReplyPublisher>>>,
+    reply_publisher: Mutex<Option<Arc<dyn ReplyPublisher>>>,
     cancel: CancellationToken,
+    // Cancels the dispatchers of the current subscriptions only.
+    generation: Mutex<CancellationToken>,
     handles: Mutex<Vec<tokio::task::JoinHandle<()>>>,
+    metrics: Arc<Metrics>,
 }
 
 impl<C: Channel + 'static, F: ChannelFactory<C>> GenericBus<C, F>
 where
     C::ConsumeReceipt: 'static,
 {
-    pub(crate) fn new(factory: F) -> Self {
+    pub(crate) fn new(factory: F, metrics: Arc<Metrics>) -> Self {
+        let cancel = CancellationToken::new();
         Self {
             factory,
             registry: Arc::new(ChannelRegistry::new()),
             subscriptions: Mutex::new(Vec::new()),
             invoker: Mutex::new(None),
             shared_reply_publisher: Mutex::new(None),
-            cancel: CancellationToken::new(),
+            reply_publisher: Mutex::new(None),
+            generation: Mutex::new(cancel.child_token()),
+            cancel,
             handles: Mutex::new(Vec::new()),
+            metrics,
+        }
+    }
+
+    // Resolve the pending subscriptions to channels and activators without
+    // starting anything, so a failure leaves the running dispatchers as is.
+    fn activate(&self) -> Result<Vec<Activated<C>>> {
+        let invoker = self
+            .invoker
+            .lock()
+            .unwrap()
+            .clone()
+            .ok_or_else(|| anyhow::anyhow!("Bus: invoker not set before start()"))?;
+        let reply_publisher = self.reply_publisher.lock().unwrap().clone();
+        let subscriptions: Vec<_> = self.subscriptions.lock().unwrap().drain(..).collect();
+
+        let mut activated = Vec::new();
+        for sub in subscriptions {
+            let channel = self.registry.lookup(&sub.channel_name).unwrap_or_else(|| {
+                let ch = self.factory.create(&sub.channel_name);
+                self.registry.register(&sub.channel_name, Arc::clone(&ch));
+                ch
+            });
+            self.factory.init(&channel, &sub.component_name);
+
+            let activator = Activator::new(
+                Arc::clone(&invoker),
+                &sub.component_name,
+                sub.function_key,
+                sub.mapping,
+                reply_publisher.clone(),
+            )
+            .map_err(|e| anyhow::anyhow!(e))?;
+            activated.push(Activated {
+                channel,
+                channel_name: sub.channel_name,
+                group: sub.component_name,
+                activator,
+            });
         }
+        Ok(activated)
     }
+
+    fn spawn(&self, activated: Vec<Activated<C>>, cancel: &CancellationToken) {
+        let mut handles = self.handles.lock().unwrap();
+        for activated in activated {
+            let dispatcher = Dispatcher::new(
+                activated.channel,
+                activated.group,
+                Arc::new(activated.activator),
+                1,
+                cancel.child_token(),
+            )
+            .with_metrics(Arc::clone(&self.metrics), activated.channel_name);
+            handles.push(tokio::spawn(async move {
+                dispatcher.run().await;
+            }));
+        }
+    }
+}
+
+// A subscription resolved to its channel and activator.
+struct Activated<C> {
+    channel: Arc<C>,
+    channel_name: String,
+    group: String,
+    activator: Activator,
 }
 
 impl<C: Channel + 'static, F: ChannelFactory<C> + 'static> Bus for GenericBus<C, F>
@@ -119,13 +197,6 @@ where
     }
 
     fn start(&self) -> Result<()> {
-        let invoker = self
-            .invoker
-            .lock()
-            .unwrap()
-            .clone()
-            .ok_or_else(|| anyhow::anyhow!("Bus: invoker not set before start()"))?;
-
         // Compose the shared reply publisher with this bus's own registry.
         let shared = self.shared_reply_publisher.lock().unwrap().clone();
         let bus_registry = Arc::clone(&self.registry) as Arc<dyn ReplyPublisher>;
@@ -136,42 +207,30 @@ where
             }),
             None => bus_registry,
         };
-        let subscriptions: Vec<_> = self.subscriptions.lock().unwrap().drain(..).collect();
-
-        for sub in subscriptions {
-            let channel = self.registry.lookup(&sub.channel_name).unwrap_or_else(|| {
-                let ch = self.factory.create(&sub.channel_name);
-                self.registry.register(&sub.channel_name, Arc::clone(&ch));
-                ch
-            });
-            self.factory.init(&channel, &sub.component_name);
-
-            let activator = Activator::new(
-                Arc::clone(&invoker),
-                &sub.component_name,
-                sub.function_key,
-                sub.mapping,
-                Some(Arc::clone(&reply_publisher)),
-            )
-            .map_err(|e| anyhow::anyhow!(e))?;
-
-            let dispatcher = Dispatcher::new(
-                channel,
-                sub.component_name.clone(),
-                Arc::new(activator),
-                1,
-                self.cancel.child_token(),
-            );
-
-            let handle = tokio::spawn(async move {
-                dispatcher.run().await;
-            });
-            self.handles.lock().unwrap().push(handle);
-        }
+        *self.reply_publisher.lock().unwrap() = Some(reply_publisher);
 
+        let activated = self.activate()?;
+        let generation = self.generation.lock().unwrap().clone();
+        self.spawn(activated, &generation);
         Ok(())
     }
 
+    fn restart(&self) -> Pin<Box<dyn Future<Output = Result<()>> + Send + '_>> {
+        Box::pin(async {
+            let activated = self.activate()?;
+            let generation = self.cancel.child_token();
+            let previous =
+                std::mem::replace(&mut *self.generation.lock().unwrap(), generation.clone());
+            previous.cancel();
+            let handles: Vec<_> = self.handles.lock().unwrap().drain(..).collect();
+            for handle in handles {
+                let _ = handle.await;
+            }
+            self.spawn(activated, &generation);
+            Ok(())
+        })
+    }
+
     fn shutdown(&self) -> Pin<Box<dyn Future<Output = ()> + Send + '_>> {
         for channel in self.registry.list() {
             self.factory.close(&channel);
@@ -80,9 +80,16 @@ impl std::error::Error for ReceiptError {}
 /// Receipt from a `consume()` call, so a consumer may optionally acknowledge
 /// or reject the message. Behavior is specific to each channel implementation.
 /// Each receipt is single-use (consumes `self`).
-pub trait ConsumeReceipt: Send {
+pub trait ConsumeReceipt: Send + Sized {
     fn ack(self) -> impl Future<Output = Result<(), ReceiptError>> + Send;
     fn nack(self) -> impl Future<Output = Result<(), ReceiptError>> + Send;
+
+    /// Reject a message that cannot be processed, so it is not redelivered
+    /// as-is. A channel with a dead-letter destination routes it there; by
+    /// default it is nacked, leaving redelivery policy to the channel.
+    fn reject(self) -> impl Future<Output = Result<(), ReceiptError>> + Send {
+        self.nack()
+    }
 }
 
 /// Receipt from a `publish()` call, so a publisher may optionally wait for
@@ -136,6 +143,12 @@ pub trait Channel: Send + Sync {
         &self,
         group: &str,
     ) -> impl Future<Output = Result<(Message, Self::ConsumeReceipt), ConsumeError>> + Send;
+
+    /// Number of messages waiting for the given consumer group, if the
+    /// channel can tell.
+    fn depth(&self, _group: &str) -> Option<usize> {
+        None
+    }
 }
 
 /// In-memory channel backed by tokio primitives.
@@ -428,6 +441,21 @@ impl Channel for LocalChannel {
             }
         }
     }
+
+    fn depth(&self, group: &str) -> Option<usize> {
+        match &self.inner {
+            ChannelInner::Block { senders, .. } => {
+                let senders = senders.lock().unwrap();
+                let sender = senders.get(group)?;
+                Some(sender.max_capacity() - sender.capacity())
+            }
+            ChannelInner::DropOldest { receivers, .. } => {
+                let receiver = Arc::clone(receivers.lock().unwrap().get(group)?);
+                let len = receiver.try_lock().ok()?.len();
+                Some(len)
+            }
+        }
+    }
 }
 
 // Publish to a channel by name. Used by the activator with reply-to.
@@ -1,10 +1,12 @@
 use std::sync::Arc;
+use std::time::Instant;
 
 use tokio::task::JoinSet;
 use tokio_util::sync::CancellationToken;
 
 use super::activator::Handler;
 use super::channel::{Channel, ConsumeError, ConsumeReceipt};
+use crate::metrics::Metrics;
 
 /// Per-subscription consumer that connects a channel to a handler.
 ///
@@ -17,6 +19,7 @@ pub struct Dispatcher<C: Channel, H: Handler> {
     handler: Arc<H>,
     concurrency: usize,
     cancel: CancellationToken,
+    metrics: Option<(Arc<Metrics>, String)>,
 }
 
 impl<C: Channel + 'static, H: Handler + 'static> Dispatcher<C, H>
@@ -36,9 +39,16 @@ where
             handler,
             concurrency,
             cancel,
+            metrics: None,
         }
     }
 
+    /// Record queue depth and dispatch latency under the given channel name.
+    pub(crate) fn with_metrics(mut self, metrics: Arc<Metrics>, channel_name: String) -> Self {
+        self.metrics = Some((metrics, channel_name));
+        self
+    }
+
     /// Run the dispatch loop. Returns when cancelled or channel is closed.
     pub async fn run(&self) {
         let mut tasks = JoinSet::new();
@@ -54,9 +64,11 @@ where
                 result = self.channel.consume(&self.group) => {
                     match result {
                         Ok((msg, receipt)) => {
+                            self.record_queue_depth();
                             self.dispatch_message(&mut tasks, msg, receipt);
                         }
                         Err(ConsumeError::Timeout(_)) => {
+                            self.record_queue_depth();
                             tracing::debug!(group = %self.group, "no message, looping");
                             continue;
                         }
@@ -74,6 +86,14 @@ where
         while tasks.join_next().await.is_some() {}
     }
 
+    fn record_queue_depth(&self) {
+        if let Some((metrics, channel_name)) = &self.metrics
+            && let Some(depth) = self.channel.depth(&self.group)
+        {
+            metrics.set_queue_depth(channel_name, &self.group, depth);
+        }
+    }
+
     fn dispatch_message(
         &self,
         tasks: &mut JoinSet<()>,
@@ -81,19 +101,32 @@ where
         receipt: C::ConsumeReceipt,
     ) {
         let handler = Arc::clone(&self.handler);
+        let metrics = self.metrics.clone();
+        let group = self.group.clone();
         tasks.spawn(async move {
-            match handler.handle(msg).await {
+            let start = Instant::now();
+            let result = handler.handle(msg).await;
+            if let Some((metrics, channel_name)) = metrics {
+                metrics.record_dispatch(&channel_name, &group, start.elapsed());
+            }
+            match result {
                 Ok(()) => {
                     if let Err(e) = receipt.ack().await {
                         tracing::error!(error = %e, "ack failed");
                     }
                 }
-                Err(e) => {
+                Err(e) if e.retryable => {
                     tracing::error!(error = %e, "handler error");
                     if let Err(e) = receipt.nack().await {
                         tracing::error!(error = %e, "nack failed");
                     }
                 }
+                Err(e) => {
+                    tracing::error!(error = %e, "handler error, not retrying");
+                    if let Err(e) = receipt.reject().await {
+                        tracing::error!(error = %e, "reject failed");
+                    }
+                }
             }
         });
     }
@@ -108,6 +141,7 @@ mod tests {
 
     use super::*;
     use crate::message::{Message, MessageBuilder};
+    use crate::messaging::activator::HandlerError;
     use crate::messaging::channel::{
         ConsumeError, LocalChannel, Overflow, PublishError, ReceiptError,
     };
@@ -166,7 +200,7 @@ mod tests {
     }
 
     impl Handler for StubHandler {
-        async fn handle(&self, msg: Message) -> Result<(), String> {
+        async fn handle(&self, msg: Message) -> Result<(), HandlerError> {
             self.received.lock().await.push(msg);
             self.counter.increment();
             Ok(())
@@ -176,20 +210,32 @@ mod tests {
     // Handler that always returns an error.
     struct ErrorHandler {
         counter: HandleCounter,
+        retryable: bool,
     }
 
     impl ErrorHandler {
         fn new() -> Self {
             Self {
                 counter: HandleCounter::new(),
+                retryable: true,
+            }
+        }
+
+        fn permanent() -> Self {
+            Self {
+                retryable: false,
+                ..Self::new()
             }
         }
     }
 
     impl Handler for ErrorHandler {
-        async fn handle(&self, _msg: Message) -> Result<(), String> {
+        async fn handle(&self, _msg: Message) -> Result<(), HandlerError> {
             self.counter.increment();
-            Err("test error".to_string())
+            Err(HandlerError {
+                message: "test error".to_string(),
+                retryable: self.retryable,
+            })
         }
     }
 
@@ -213,7 +259,7 @@ mod tests {
     }
 
     impl Handler for BlockingHandler {
-        async fn handle(&self, _msg: Message) -> Result<(), String> {
+        async fn handle(&self, _msg: Message) -> Result<(), HandlerError> {
             let current = self.active.fetch_add(1, Ordering::SeqCst) + 1;
             self.max_active.fetch_max(current, Ordering::SeqCst);
             self.counter.increment();
@@ -228,6 +274,7 @@ mod tests {
         inner: LocalChannel,
         acks: Arc<AtomicUsize>,
         nacks: Arc<AtomicUsize>,
+        rejects: Arc<AtomicUsize>,
     }
 
     impl TrackingChannel {
@@ -236,6 +283,7 @@ mod tests {
                 inner: LocalChannel::new(256, Overflow::Block, -1, 50),
                 acks: Arc::new(AtomicUsize::new(0)),
                 nacks: Arc::new(AtomicUsize::new(0)),
+                rejects: Arc::new(AtomicUsize::new(0)),
             }
         }
     }
@@ -243,6 +291,7 @@ mod tests {
     struct TrackingReceipt {
         acks: Arc<AtomicUsize>,
         nacks: Arc<AtomicUsize>,
+        rejects: Arc<AtomicUsize>,
     }
 
     impl ConsumeReceipt for TrackingReceipt {
@@ -254,6 +303,10 @@ mod tests {
             self.nacks.fetch_add(1, Ordering::SeqCst);
             Ok(())
         }
+        async fn reject(self) -> Result<(), ReceiptError> {
+            self.rejects.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        }
     }
 
     impl Channel for TrackingChannel {
@@ -269,6 +322,7 @@ mod tests {
             let receipt = TrackingReceipt {
                 acks: Arc::clone(&self.acks),
                 nacks: Arc::clone(&self.nacks),
+                rejects: Arc::clone(&self.rejects),
             };
             Ok((msg, receipt))
         }
@@ -330,6 +384,35 @@ mod tests {
         assert_eq!(channel.nacks.load(Ordering::SeqCst), 1);
     }
 
+    #[tokio::test]
+    async fn reject_called_on_permanent_handler_error() {
+        let channel = Arc::new(TrackingChannel::new());
+        let handler = Arc::new(ErrorHandler::permanent());
+        let cancel = CancellationToken::new();
+
+        let dispatcher = Dispatcher::new(
+            Arc::clone(&channel),
+            "test".to_string(),
+            Arc::clone(&handler),
+            1,
+            cancel.clone(),
+        );
+
+        let dispatch_handle = tokio::spawn(async move { dispatcher.run().await });
+        tokio::task::yield_now().await;
+
+        let msg = MessageBuilder::new(b"fail".to_vec()).build();
+        channel.publish(msg).await.unwrap();
+
+        handler.counter.wait_for(1).await;
+        cancel.cancel();
+        dispatch_handle.await.unwrap();
+
+        assert_eq!(channel.acks.load(Ordering::SeqCst), 0);
+        assert_eq!(channel.nacks.load(Ordering::SeqCst), 0);
+        assert_eq!(channel.rejects.load(Ordering::SeqCst), 1);
+    }
+
     #[tokio::test]
     async fn single_message_dispatched() {
         let channel = Arc::new(LocalChannel::new(256, Overflow::Block, -1, 50));
@@ -360,6 +443,44 @@ mod tests {
         assert_eq!(received[0].body(), b"hello");
     }
 
+    #[tokio::test]
+    async fn records_queue_depth_and_dispatch_latency() {
+        let channel = Arc::new(LocalChannel::new(256, Overflow::Block, -1, 50));
+        let handler = Arc::new(StubHandler::new());
+        let cancel = CancellationToken::new();
+        let metrics = Arc::new(Metrics::default());
+
+        // Queue messages before the dispatcher starts consuming.
+        channel.init_group("test");
+        for _ in 0..3 {
+            let msg = MessageBuilder::new(b"hello".to_vec()).build();
+            channel.publish(msg).await.unwrap();
+        }
+        assert_eq!(channel.depth("test"), Some(3));
+
+        let dispatcher = Dispatcher::new(
+            Arc::clone(&channel),
+            "test".to_string(),
+            Arc::clone(&handler),
+            1,
+            cancel.clone(),
+        )
+        .with_metrics(Arc::clone(&metrics), "events".to_string());
+        let dispatch_handle = tokio::spawn(async move { dispatcher.run().await });
+
+        handler.counter.wait_for(3).await;
+        cancel.cancel();
+        dispatch_handle.await.unwrap();
+
+        let text = metrics.render();
+        assert!(
+            text.contains("composable_channel_queue_depth{channel=\"events\",group=\"test\"} 0\n")
+        );
+        assert!(text.contains(
+            "composable_dispatch_duration_seconds_count{channel=\"events\",group=\"test\"} 3\n"
+        ));
+    }
+
     #[tokio::test]
     async fn concurrency_limit_respected() {
         let channel = Arc::new(LocalChannel::new(256, Overflow::Block, -1, 50));
@@ -7,6 +7,7 @@ use anyhow::Result;
 
 use crate::config::types::{CategoryClaim, ConfigHandler, PropertyMap};
 use crate::message::{Message, MessageBuilder, MessageHeaders, MessagePublisher};
+use crate::metrics::Metrics;
 use crate::service::Service;
 use crate::types::ComponentInvoker;
 
@@ -138,7 +139,9 @@ impl ConfigHandler for MessagingConfigHandler {
 }
 
 pub(crate) struct MessagingService {
-    subscriptions: Arc<Mutex<Vec<SubscriptionConfig>>>,
+    // Written by the config handler, and adopted by `commit_config`.
+    parsed: Arc<Mutex<Vec<SubscriptionConfig>>>,
+    subscriptions: Mutex<Vec<SubscriptionConfig>>,
     // Currently one default bus using LocalChannel.
     // Future: HashMap<String, Arc<dyn Bus>> populated from [bus.*] config.
     bus: Arc<dyn Bus>,
@@ -146,10 +149,11 @@ pub(crate) struct MessagingService {
 }
 
 impl MessagingService {
-    pub(crate) fn new() -> Self {
+    pub(crate) fn new(metrics: Arc<Metrics>) -> Self {
         Self {
-            subscriptions: Arc::new(Mutex::new(Vec::new())),
-            bus: Arc::new(LocalBus::new(LocalChannelFactory)),
+            parsed: Arc::new(Mutex::new(Vec::new())),
+            subscriptions: Mutex::new(Vec::new()),
+            bus: Arc::new(LocalBus::new(LocalChannelFactory, metrics)),
             reply_handler: ReplyHandler::new(),
         }
     }
@@ -164,11 +168,16 @@ impl MessagingService {
 
 impl Service for MessagingService {
     fn config_handler(&self) -> Option<Box<dyn ConfigHandler>> {
+        self.parsed.lock().unwrap().clear();
         Some(Box::new(MessagingConfigHandler {
-            subscriptions: Arc::clone(&self.subscriptions),
+            subscriptions: Arc::clone(&self.parsed),
         }))
     }
 
+    fn commit_config(&self) {
+        *self.subscriptions.lock().unwrap() = std::mem::take(&mut *self.parsed.lock().unwrap());
+    }
+
     fn set_invoker(&self, invoker: Arc<dyn ComponentInvoker>) {
         self.bus.set_invoker(invoker);
     }
@@ -182,6 +191,14 @@ impl Service for MessagingService {
         self.bus.start()
     }
 
+    fn reload(&self) -> Pin<Box<dyn Future<Output = Result<()>> + Send + '_>> {
+        let subscriptions: Vec<_> = self.subscriptions.lock().unwrap().drain(..).collect();
+        for sub in subscriptions {
+            self.bus.add_subscription(sub);
+        }
+        self.bus.restart()
+    }
+
     fn shutdown(&self) -> Pin<Box<dyn Future<Output = ()> + Send + '_>> {
         self.bus.shutdown()
     }
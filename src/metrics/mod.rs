@@ -0,0 +1,344 @@
+//! Built-in runtime metrics.
+//!
+//! The component host records invocation counts and latencies, and the
+//! messaging dispatchers record queue depths and dispatch latencies. The
+//! metrics service serves them in OpenMetrics text format.
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::RwLock;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use crate::types::{ExecutionLimitExceeded, InvocationError};
+
+mod service;
+
+pub(crate) use service::MetricsService;
+
+/// Media type of [`Metrics::render`] output.
+pub(crate) const CONTENT_TYPE: &str = "application/openmetrics-text; version=1.0.0; charset=utf-8";
+
+// Upper bounds, in seconds, of the latency histogram buckets.
+const BUCKETS: [f64; 14] = [
+    0.0005, 0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+/// Metrics shared by every part of a runtime. They outlive reloads.
+#[derive(Default)]
+pub(crate) struct Metrics {
+    // (component, function, outcome)
+    invocations: Family<3, AtomicU64>,
+    // (component, function)
+    invocation_duration: Family<2, Histogram>,
+    // (component)
+    instantiation_duration: Family<1, Histogram>,
+    // (channel, group)
+    queue_depth: Family<2, AtomicU64>,
+    // (channel, group)
+    dispatch_duration: Family<2, Histogram>,
+}
+
+// The series of one metric, by label values. Each series is updated
+// atomically, so recording only takes the write lock to add a new series.
+struct Family<const N: usize, T> {
+    series: RwLock<BTreeMap<[String; N], T>>,
+}
+
+impl<const N: usize, T> Default for Family<N, T> {
+    fn default() -> Self {
+        Self {
+            series: RwLock::new(BTreeMap::new()),
+        }
+    }
+}
+
+impl<const N: usize, T: Default> Family<N, T> {
+    fn update(&self, labels: [&str; N], f: impl FnOnce(&T)) {
+        let labels = labels.map(String::from);
+        if let Some(series) = self.series.read().unwrap().get(&labels) {
+            return f(series);
+        }
+        f(self.series.write().unwrap().entry(labels).or_default());
+    }
+}
+
+impl Metrics {
+    pub(crate) fn record_invocation(
+        &self,
+        component: &str,
+        function: &str,
+        result: &Result<serde_json::Value, InvocationError>,
+        elapsed: Duration,
+    ) {
+        let outcome = match result {
+            Ok(_) => "ok",
+            Err(e) => match outcome(e) {
+                Some(outcome) => outcome,
+                None => return,
+            },
+        };
+        self.invocations
+            .update([component, function, outcome], |count| {
+                count.fetch_add(1, Ordering::Relaxed);
+            });
+        self.invocation_duration
+            .update([component, function], |histogram| {
+                histogram.observe(elapsed)
+            });
+    }
+
+    pub(crate) fn record_instantiation(&self, component: &str, elapsed: Duration) {
+        self.instantiation_duration
+            .update([component], |histogram| histogram.observe(elapsed));
+    }
+
+    #[cfg(feature = "messaging")]
+    pub(crate) fn set_queue_depth(&self, channel: &str, group: &str, depth: usize) {
+        self.queue_depth.update([channel, group], |gauge| {
+            gauge.store(depth as u64, Ordering::Relaxed);
+        });
+    }
+
+    #[cfg(feature = "messaging")]
+    pub(crate) fn record_dispatch(&self, channel: &str, group: &str, elapsed: Duration) {
+        self.dispatch_duration
+            .update([channel, group], |histogram| histogram.observe(elapsed));
+    }
+
+    /// Render every metric in OpenMetrics text format.
+    pub(crate) fn render(&self) -> String {
+        let mut out = String::new();
+        write_counters(
+            &mut out,
+            "composable_invocations",
+            "Component function invocations, by outcome.",
+            ["component", "function", "outcome"],
+            &self.invocations.series.read().unwrap(),
+        );
+        write_histograms(
+            &mut out,
+            "composable_invocation_duration_seconds",
+            "Time taken by component function invocations.",
+            ["component", "function"],
+            &self.invocation_duration.series.read().unwrap(),
+        );
+        write_histograms(
+            &mut out,
+            "composable_instantiation_duration_seconds",
+            "Time taken to instantiate components.",
+            ["component"],
+            &self.instantiation_duration.series.read().unwrap(),
+        );
+        write_gauges(
+            &mut out,
+            "composable_channel_queue_depth",
+            "Messages waiting in a channel for a consumer group.",
+            ["channel", "group"],
+            &self.queue_depth.series.read().unwrap(),
+        );
+        write_histograms(
+            &mut out,
+            "composable_dispatch_duration_seconds",
+            "Time taken to handle messages consumed from a channel.",
+            ["channel", "group"],
+            &self.dispatch_duration.series.read().unwrap(),
+        );
+        out.push_str("# EOF\n");
+        out
+    }
+}
+
+// Unknown names come from callers, so counting them could add series
+// without bound.
+fn outcome(error: &InvocationError) -> Option<&'static str> {
+    Some(match error {
+        InvocationError::ComponentNotFound { .. } | InvocationError::FunctionNotFound { .. } => {
+            return None;
+        }
+        InvocationError::InvalidArguments { .. } => "invalid_arguments",
+        InvocationError::Trap { .. } => "trap",
+        InvocationError::ErrorResult { .. } => "error_result",
+        InvocationError::Capability { .. } => "capability_error",
+        InvocationError::Exit { .. } => "exit",
+        InvocationError::LimitExceeded(ExecutionLimitExceeded::Timeout { .. }) => "timeout",
+        InvocationError::LimitExceeded(ExecutionLimitExceeded::OutOfFuel { .. }) => "out_of_fuel",
+        InvocationError::ResourceLimitExceeded(_) => "resource_limit_exceeded",
+        InvocationError::Busy(_) => "busy",
+        InvocationError::Other { .. } => "failed",
+    })
+}
+
+#[derive(Default)]
+struct Histogram {
+    // Non-cumulative count per bucket; rendering accumulates them.
+    buckets: [AtomicU64; BUCKETS.len()],
+    count: AtomicU64,
+    sum_nanos: AtomicU64,
+}
+
+impl Histogram {
+    fn observe(&self, elapsed: Duration) {
+        let seconds = elapsed.as_secs_f64();
+        if let Some(i) = BUCKETS.iter().position(|&bound| seconds <= bound) {
+            self.buckets[i].fetch_add(1, Ordering::Relaxed);
+        }
+        self.count.fetch_add(1, Ordering::Relaxed);
+        let nanos = u64::try_from(elapsed.as_nanos()).unwrap_or(u64::MAX);
+        self.sum_nanos.fetch_add(nanos, Ordering::Relaxed);
+    }
+}
+
+fn write_header(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# TYPE {name} {kind}");
+    if name.ends_with("_seconds") {
+        let _ = writeln!(out, "# UNIT {name} seconds");
+    }
+    let _ = writeln!(out, "# HELP {name} {help}");
+}
+
+fn write_counters<const N: usize>(
+    out: &mut String,
+    name: &str,
+    help: &str,
+    labels: [&str; N],
+    values: &BTreeMap<[String; N], AtomicU64>,
+) {
+    write_header(out, name, "counter", help);
+    for (key, value) in values {
+        let value = value.load(Ordering::Relaxed);
+        let _ = writeln!(out, "{name}_total{} {value}", label_set(&labels, key, None));
+    }
+}
+
+fn write_gauges<const N: usize>(
+    out: &mut String,
+    name: &str,
+    help: &str,
+    labels: [&str; N],
+    values: &BTreeMap<[String; N], AtomicU64>,
+) {
+    write_header(out, name, "gauge", help);
+    for (key, value) in values {
+        let value = value.load(Ordering::Relaxed);
+        let _ = writeln!(out, "{name}{} {value}", label_set(&labels, key, None));
+    }
+}
+
+fn write_histograms<const N: usize>(
+    out: &mut String,
+    name: &str,
+    help: &str,
+    labels: [&str; N],
+    values: &BTreeMap<[String; N], Histogram>,
+) {
+    write_header(out, name, "histogram", help);
+    for (key, histogram) in values {
+        // Loaded before the buckets, so a concurrent observation cannot
+        // leave a bucket above the +Inf count.
+        let count = histogram.count.load(Ordering::Relaxed);
+        let sum = histogram.sum_nanos.load(Ordering::Relaxed) as f64 / 1e9;
+        let mut cumulative = 0;
+        for (bound, bucket) in BUCKETS.iter().zip(&histogram.buckets) {
+            cumulative = (cumulative + bucket.load(Ordering::Relaxed)).min(count);
+            let le = label_set(&labels, key, Some(&format!("{bound:?}")));
+            let _ = writeln!(out, "{name}_bucket{le} {cumulative}");
+        }
+        let le = label_set(&labels, key, Some("+Inf"));
+        let _ = writeln!(out, "{name}_bucket{le} {count}");
+        let labels = label_set(&labels, key, None);
+        let _ = writeln!(out, "{name}_sum{labels} {sum}");
+        let _ = writeln!(out, "{name}_count{labels} {count}");
+    }
+}
+
+// `{name="value",...}`, with an optional trailing `le` bucket label.
+fn label_set(names: &[&str], values: &[String], le: Option<&str>) -> String {
+    let mut pairs: Vec<String> = names
+        .iter()
+        .zip(values)
+        .map(|(name, value)| format!("{name}=\"{}\"", escape(value)))
+        .collect();
+    if let Some(le) = le {
+        pairs.push(format!("le=\"{le}\""));
+    }
+    format!("{{{}}}", pairs.join(","))
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_counters_and_histograms() {
+        let metrics = Metrics::default();
+        let ok = Ok(serde_json::Value::Null);
+        metrics.record_invocation("greeter", "greet", &ok, Duration::from_millis(3));
+        metrics.record_invocation("greeter", "greet", &ok, Duration::from_secs(20));
+
+        let text = metrics.render();
+        assert!(text.contains("# TYPE composable_invocations counter\n"));
+        assert!(text.contains(
+            "composable_invocations_total{component=\"greeter\",function=\"greet\",outcome=\"ok\"} 2\n"
+        ));
+        assert!(text.contains("# UNIT composable_invocation_duration_seconds seconds\n"));
+        assert!(text.contains(
+            "composable_invocation_duration_seconds_bucket{component=\"greeter\",function=\"greet\",le=\"0.0025\"} 0\n"
+        ));
+        assert!(text.contains(
+            "composable_invocation_duration_seconds_bucket{component=\"greeter\",function=\"greet\",le=\"0.005\"} 1\n"
+        ));
+        assert!(text.contains(
+            "composable_invocation_duration_seconds_bucket{component=\"greeter\",function=\"greet\",le=\"+Inf\"} 2\n"
+        ));
+        assert!(text.contains(
+            "composable_invocation_duration_seconds_count{component=\"greeter\",function=\"greet\"} 2\n"
+        ));
+        assert!(text.ends_with("# EOF\n"));
+    }
+
+    #[test]
+    fn concurrent_invocations_are_all_counted() {
+        let metrics = Metrics::default();
+        let ok = Ok(serde_json::Value::Null);
+        std::thread::scope(|scope| {
+            for _ in 0..8 {
+                scope.spawn(|| {
+                    for _ in 0..1000 {
+                        metrics.record_invocation("c", "f", &ok, Duration::from_millis(1));
+                    }
+                });
+            }
+        });
+        let text = metrics.render();
+        assert!(text.contains("outcome=\"ok\"} 8000\n"), "{text}");
+        assert!(text.contains(
+            "composable_invocation_duration_seconds_sum{component=\"c\",function=\"f\"} 8\n"
+        ));
+    }
+
+    #[test]
+    fn unknown_names_are_not_counted() {
+        let metrics = Metrics::default();
+        let err = Err(InvocationError::ComponentNotFound {
+            component: "missing".to_string(),
+        });
+        metrics.record_invocation("missing", "f", &err, Duration::ZERO);
+        assert!(!metrics.render().contains("missing"));
+    }
+
+    #[test]
+    fn escapes_label_values() {
+        assert_eq!(
+            label_set(&["channel"], &["a\"b\\c\n".to_string()], None),
+            "{channel=\"a\\\"b\\\\c\\n\"}"
+        );
+    }
+}
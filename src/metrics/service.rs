@@ -0,0 +1,268 @@
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::future::Future;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+
+use anyhow::Result;
+use bytes::Bytes;
+use http_body_util::Full;
+use hyper::server::conn::http1;
+use hyper::service::service_fn;
+use hyper::{Method, Request, Response, StatusCode, header};
+use hyper_util::rt::TokioIo;
+use tokio::net::TcpListener;
+use tokio::task::{JoinHandle, JoinSet};
+
+use super::{CONTENT_TYPE, Metrics};
+use crate::config::types::{
+    CategoryClaim, Condition, ConfigHandler, Operator, PropertyMap, Selector,
+};
+use crate::service::Service;
+
+// Claims `[server.*]` definitions where `type = "metrics"`. Each one serves
+// `/metrics` on its `port`, bound to its `address` (loopback by default, so
+// metrics are not exposed beyond the host unless asked for).
+struct MetricsConfigHandler {
+    servers: Arc<Mutex<HashMap<String, SocketAddr>>>,
+}
+
+impl ConfigHandler for MetricsConfigHandler {
+    fn claimed_categories(&self) -> Vec<CategoryClaim> {
+        vec![CategoryClaim::with_selector(
+            "server",
+            Selector {
+                conditions: vec![Condition {
+                    key: "type".to_string(),
+                    operator: Operator::Equals("metrics".to_string()),
+                }],
+            },
+        )]
+    }
+
+    fn claimed_properties(&self) -> HashMap<&str, &[&str]> {
+        HashMap::from([("server", ["type", "port", "address"].as_slice())])
+    }
+
+    fn handle_category(
+        &mut self,
+        category: &str,
+        name: &str,
+        mut properties: PropertyMap,
+    ) -> Result<()> {
+        if category != "server" {
+            anyhow::bail!("MetricsConfigHandler received unexpected category '{category}'");
+        }
+
+        // type is only used by the selector
+        properties.remove("type");
+
+        let port = match properties.remove("port") {
+            Some(serde_json::Value::Number(n)) => n
+                .as_u64()
+                .and_then(|p| u16::try_from(p).ok())
+                .ok_or_else(|| {
+                    anyhow::anyhow!("Server '{name}': 'port' must be a valid port number")
+                })?,
+            Some(other) => {
+                anyhow::bail!("Server '{name}': 'port' must be a number, got {other}");
+            }
+            None => anyhow::bail!("Server '{name}' missing required 'port' field"),
+        };
+
+        let address = match properties.remove("address") {
+            Some(serde_json::Value::String(s)) => s.parse::<IpAddr>().map_err(|_| {
+                anyhow::anyhow!("Server '{name}': 'address' must be an IP address, got '{s}'")
+            })?,
+            Some(other) => {
+                anyhow::bail!("Server '{name}': 'address' must be a string, got {other}");
+            }
+            None => IpAddr::V4(Ipv4Addr::LOCALHOST),
+        };
+
+        // Properties other server types claim on `[server.*]` reach this
+        // handler too, so unknown ones are rejected here.
+        if !properties.is_empty() {
+            let unknown: Vec<_> = properties.keys().collect();
+            anyhow::bail!("Server '{name}' has unknown properties: {unknown:?}");
+        }
+
+        self.servers
+            .lock()
+            .unwrap()
+            .insert(name.to_string(), SocketAddr::new(address, port));
+        Ok(())
+    }
+}
+
+/// Serves the runtime's metrics in OpenMetrics text format.
+///
+/// Registered by the runtime builder. Starts one listener per
+/// `[server.*]` definition with `type = "metrics"`.
+pub(crate) struct MetricsService {
+    metrics: Arc<Metrics>,
+    // Written by the config handler, and adopted by `commit_config`.
+    parsed: Arc<Mutex<HashMap<String, SocketAddr>>>,
+    servers: Mutex<HashMap<String, SocketAddr>>,
+    running: Mutex<HashMap<String, RunningServer>>,
+}
+
+struct RunningServer {
+    addr: SocketAddr,
+    task: JoinHandle<()>,
+}
+
+impl RunningServer {
+    fn start(name: &str, addr: SocketAddr, metrics: Arc<Metrics>) -> Result<Self> {
+        let listener = std::net::TcpListener::bind(addr)
+            .map_err(|e| anyhow::anyhow!("Server '{name}': cannot listen on {addr}: {e}"))?;
+        listener.set_nonblocking(true)?;
+        let listener = TcpListener::from_std(listener)?;
+        tracing::info!(server = %name, %addr, "metrics server listening");
+        Ok(Self {
+            addr,
+            task: tokio::spawn(serve(listener, metrics)),
+        })
+    }
+
+    // Aborting the accept loop drops its connections with it.
+    async fn stop(self) {
+        self.task.abort();
+        let _ = self.task.await;
+    }
+}
+
+impl MetricsService {
+    pub(crate) fn new(metrics: Arc<Metrics>) -> Self {
+        Self {
+            metrics,
+            parsed: Arc::new(Mutex::new(HashMap::new())),
+            servers: Mutex::new(HashMap::new()),
+            running: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn take_servers(&self) -> HashMap<String, SocketAddr> {
+        std::mem::take(&mut *self.servers.lock().unwrap())
+    }
+}
+
+impl Service for MetricsService {
+    fn config_handler(&self) -> Option<Box<dyn ConfigHandler>> {
+        self.parsed.lock().unwrap().clear();
+        Some(Box::new(MetricsConfigHandler {
+            servers: Arc::clone(&self.parsed),
+        }))
+    }
+
+    fn commit_config(&self) {
+        *self.servers.lock().unwrap() = std::mem::take(&mut *self.parsed.lock().unwrap());
+    }
+
+    fn start(&self) -> Result<()> {
+        let mut running = self.running.lock().unwrap();
+        for (name, addr) in self.take_servers() {
+            let server = RunningServer::start(&name, addr, Arc::clone(&self.metrics))?;
+            running.insert(name, server);
+        }
+        Ok(())
+    }
+
+    // Servers that keep their address keep running; the others are restarted
+    // or, if no longer defined, stopped.
+    fn reload(&self) -> Pin<Box<dyn Future<Output = Result<()>> + Send + '_>> {
+        Box::pin(async {
+            let servers = self.take_servers();
+            let mut previous = std::mem::take(&mut *self.running.lock().unwrap());
+
+            let mut result = Ok(());
+            let mut running = HashMap::new();
+            for (name, addr) in servers {
+                match previous.remove(&name) {
+                    Some(server) if server.addr == addr => {
+                        running.insert(name, server);
+                        continue;
+                    }
+                    Some(server) => server.stop().await,
+                    None => {}
+                }
+                match RunningServer::start(&name, addr, Arc::clone(&self.metrics)) {
+                    Ok(server) => {
+                        running.insert(name, server);
+                    }
+                    Err(e) => {
+                        tracing::error!(server = %name, "failed to restart: {e}");
+                        result = result.and(Err(e));
+                    }
+                }
+            }
+            for (_, server) in previous {
+                server.stop().await;
+            }
+
+            *self.running.lock().unwrap() = running;
+            result
+        })
+    }
+
+    fn shutdown(&self) -> Pin<Box<dyn Future<Output = ()> + Send + '_>> {
+        Box::pin(async {
+            let running: Vec<_> = {
+                let mut lock = self.running.lock().unwrap();
+                lock.drain().map(|(_, server)| server).collect()
+            };
+            for server in running {
+                server.stop().await;
+            }
+        })
+    }
+}
+
+async fn serve(listener: TcpListener, metrics: Arc<Metrics>) {
+    let mut connections = JoinSet::new();
+    loop {
+        while connections.try_join_next().is_some() {}
+        let stream = match listener.accept().await {
+            Ok((stream, _addr)) => stream,
+            Err(e) => {
+                tracing::error!("metrics server accept error: {e}");
+                continue;
+            }
+        };
+        let metrics = Arc::clone(&metrics);
+        connections.spawn(async move {
+            let service = service_fn(move |req| {
+                let response = respond(&req, &metrics);
+                async move { Ok::<_, Infallible>(response) }
+            });
+            if let Err(e) = http1::Builder::new()
+                .serve_connection(TokioIo::new(stream), service)
+                .await
+            {
+                tracing::debug!("metrics connection error: {e}");
+            }
+        });
+    }
+}
+
+fn respond<B>(req: &Request<B>, metrics: &Metrics) -> Response<Full<Bytes>> {
+    let (status, content_type, body) = match (req.method(), req.uri().path()) {
+        (&Method::GET, "/metrics") => (StatusCode::OK, CONTENT_TYPE, metrics.render()),
+        (_, "/metrics") => (
+            StatusCode::METHOD_NOT_ALLOWED,
+            "text/plain",
+            "method not allowed\n".to_string(),
+        ),
+        _ => (
+            StatusCode::NOT_FOUND,
+            "text/plain",
+            "not found\n".to_string(),
+        ),
+    };
+    Response::builder()
+        .status(status)
+        .header(header::CONTENT_TYPE, content_type)
+        .body(Full::new(Bytes::from(body)))
+        .expect("static response parts are valid")
+}
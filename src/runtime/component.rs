@@ -5,11 +5,24 @@
 //! an instance requires `&mut store`. Dropping the handle drops the store.
 
 use anyhow::Result;
-use wasmtime::Store;
+use std::time::Duration;
+use tokio::sync::OwnedSemaphorePermit;
 use wasmtime::component::{ComponentExportIndex, Instance, ResourceAny, Val as WasmtimeVal};
+use wasmtime::{Store, Trap, WasmBacktrace};
 
 use crate::runtime::conversion::{json_to_val, val_to_json};
-use crate::types::{ComponentState, Function};
+use crate::types::{
+    ComponentLimits, ComponentState, ExecutionLimitExceeded, Function, InvocationError,
+    ResourceLimitExceeded,
+};
+
+// Wall-clock resolution of `timeout-ms`: the host advances the engine epoch
+// once per tick, and running guest code traps once its deadline has passed.
+pub(crate) const EPOCH_TICK: Duration = Duration::from_millis(10);
+
+// Epoch deadline for calls without `timeout-ms`. Large enough to never be
+// reached, small enough that adding the current epoch cannot overflow.
+const NO_EPOCH_DEADLINE: u64 = u64::MAX / 2;
 
 /// A value crossing the call boundary, either as JSON (passed by value) or as
 /// a Component-owned Resource (passed by reference).
@@ -67,11 +80,31 @@ pub struct ComponentResource {
 pub struct ComponentInstance {
     store: Store<ComponentState>,
     instance: Instance,
+    component_name: String,
+    limits: ComponentLimits,
+    // Counts this instance against the component's `max-concurrency`.
+    _permit: Option<OwnedSemaphorePermit>,
 }
 
 impl ComponentInstance {
-    pub(crate) fn new(store: Store<ComponentState>, instance: Instance) -> Self {
-        Self { store, instance }
+    pub(crate) fn new(
+        store: Store<ComponentState>,
+        instance: Instance,
+        component_name: &str,
+        limits: &ComponentLimits,
+    ) -> Self {
+        Self {
+            store,
+            instance,
+            component_name: component_name.to_string(),
+            limits: limits.clone(),
+            _permit: None,
+        }
+    }
+
+    pub(crate) fn with_permit(mut self, permit: OwnedSemaphorePermit) -> Self {
+        self._permit = Some(permit);
+        self
     }
 
     /// Call an exported function on this instance.
@@ -83,12 +116,19 @@ impl ComponentInstance {
     /// This covers every component model export shape. A resource method is
     /// represented as a function whose first parameter is the receiver, so can
     /// be called by passing the resource handle as that arg.
-    pub async fn call(&mut self, function: &Function, args: Vec<Val>) -> Result<Option<Val>> {
-        let export = self.resolve_function(function)?;
-        let results = self
-            .call_export(export, args, function.function_name())
-            .await?;
-        convert_results(results)
+    pub async fn call(
+        &mut self,
+        function: &Function,
+        args: Vec<Val>,
+    ) -> Result<Option<Val>, InvocationError> {
+        let name = function.function_name();
+        let result = async {
+            let export = self.resolve_function(function)?;
+            let results = self.call_export(export, args, name).await?;
+            self.convert_results(results, name)
+        }
+        .await;
+        result.map_err(|e| InvocationError::from_error(&self.component_name, name, e))
     }
 
     // Resolve an exported function on an interface or directly at world-level.
@@ -100,25 +140,30 @@ impl ComponentInstance {
                 let interface_export = self
                     .instance
                     .get_export(&mut self.store, None, interface_name)
-                    .ok_or_else(|| anyhow::anyhow!("Interface '{interface_name}' not found"))?;
+                    .ok_or_else(|| self.function_not_found(function))?;
                 self.instance
                     .get_export(&mut self.store, Some(&interface_export.1), name)
-                    .ok_or_else(|| {
-                        anyhow::anyhow!(
-                            "Function '{name}' not found in interface '{interface_name}'"
-                        )
-                    })?
+                    .ok_or_else(|| self.function_not_found(function))?
             }
             None => self
                 .instance
                 .get_export(&mut self.store, None, name)
-                .ok_or_else(|| {
-                    anyhow::anyhow!("Function '{name}' not found in component exports")
-                })?,
+                .ok_or_else(|| self.function_not_found(function))?,
         };
         Ok(export.1)
     }
 
+    fn function_not_found(&self, function: &Function) -> InvocationError {
+        let function = match function.interface() {
+            Some(interface) => format!("{}.{}", interface.as_str(), function.function_name()),
+            None => function.function_name().to_string(),
+        };
+        InvocationError::FunctionNotFound {
+            component: self.component_name.clone(),
+            function,
+        }
+    }
+
     // Shared call path: convert args based on declared param types and run.
     async fn call_export(
         &mut self,
@@ -134,19 +179,25 @@ impl ComponentInstance {
         let func_ty = func.ty(&self.store);
         let params: Vec<_> = func_ty.params().collect();
         if args.len() != params.len() {
-            anyhow::bail!(
-                "Wrong number of args for '{name}': expected {}, got {}",
-                params.len(),
-                args.len()
-            );
+            return Err(self
+                .invalid_arguments(
+                    name,
+                    format!(
+                        "wrong number of args: expected {}, got {}",
+                        params.len(),
+                        args.len()
+                    ),
+                )
+                .into());
         }
 
         let mut arg_vals: Vec<WasmtimeVal> = Vec::with_capacity(args.len());
         for (index, arg) in args.into_iter().enumerate() {
             let val = match arg {
                 Val::Resource(resource) => WasmtimeVal::Resource(resource.resource),
-                Val::Json(json) => json_to_val(&json, &params[index].1)
-                    .map_err(|e| anyhow::anyhow!("Error converting parameter {index}: {e}"))?,
+                Val::Json(json) => json_to_val(&json, &params[index].1).map_err(|e| {
+                    self.invalid_arguments(name, format!("error converting parameter {index}: {e}"))
+                })?,
             };
             arg_vals.push(val);
         }
@@ -156,55 +207,161 @@ impl ComponentInstance {
         // `run_concurrent` keeps the async executor active so an async-typed
         // export can block on async imports (e.g. wasi:http) and be driven to
         // completion; `call_async` would trap if the call idles awaiting I/O.
-        let call_result = self
-            .store
-            .run_concurrent(async |accessor| {
-                func.call_concurrent(accessor, &arg_vals, &mut results)
-                    .await
-            })
-            .await?;
+        //
+        // Each call starts with a fresh deadline and fuel budget. The epoch
+        // deadline stops a guest spinning in Wasm; the outer timeout stops
+        // one that is blocked awaiting a host import.
+        reset_execution_limits(&mut self.store, &self.limits)?;
+        let run = self.store.run_concurrent(async |accessor| {
+            func.call_concurrent(accessor, &arg_vals, &mut results)
+                .await
+        });
+        let run_result = match self.limits.timeout_ms {
+            Some(timeout_ms) => {
+                match tokio::time::timeout(Duration::from_millis(timeout_ms), run).await {
+                    Ok(run_result) => run_result,
+                    Err(_) => return Err(self.timeout_error(timeout_ms)),
+                }
+            }
+            None => run.await,
+        };
+        let call_result = run_result.map_err(|e| self.call_error(e, name))?;
 
         // A guest calling `wasi:cli/exit` surfaces as an `I32Exit` error.
         if let Err(e) = call_result {
             return match e.downcast_ref::<wasmtime_wasi::I32Exit>() {
                 Some(wasmtime_wasi::I32Exit(0)) => Ok(Vec::new()),
-                Some(wasmtime_wasi::I32Exit(code)) => {
-                    Err(anyhow::anyhow!("component exited with code {code}"))
+                Some(wasmtime_wasi::I32Exit(code)) => Err(InvocationError::Exit {
+                    component: self.component_name.clone(),
+                    function: name.to_string(),
+                    code: *code,
                 }
-                None => Err(e.into()),
+                .into()),
+                None => Err(self.call_error(e, name)),
             };
         }
 
         Ok(results)
     }
-}
 
-// Convert a call's wasmtime results into [`Val`]s.
-//
-// A WIT `result<T, E>` maps onto Rust's `Result`: an `err` becomes an `Err`
-// here, so callers handle failure before ever checking for an `ok` value. A
-// resource remains a handle; everything else converts to JSON.
-fn convert_results(results: Vec<WasmtimeVal>) -> Result<Option<Val>> {
-    if results.len() > 1 {
-        anyhow::bail!(
-            "got {} results; a WIT function declares at most one",
-            results.len()
-        );
+    fn invalid_arguments(&self, function: &str, message: String) -> InvocationError {
+        InvocationError::InvalidArguments {
+            component: self.component_name.clone(),
+            function: function.to_string(),
+            message,
+        }
     }
-    let Some(result) = results.first() else {
-        return Ok(None);
-    };
-    match result {
-        WasmtimeVal::Result(Ok(Some(ok_val))) => Ok(Some(convert_value(ok_val)?)),
-        WasmtimeVal::Result(Ok(None)) => Ok(None),
-        WasmtimeVal::Result(Err(Some(error_val))) => {
-            let error_json =
-                val_to_json(error_val).map_or_else(|e| format!("<{e}>"), |json| json.to_string());
-            anyhow::bail!("Component returned error: {error_json}")
+
+    // Classify a failed call: an exceeded limit, a trap in the guest, an I/O
+    // error a host import raised on the guest's behalf, or any other failure.
+    fn call_error(&self, error: wasmtime::Error, function: &str) -> anyhow::Error {
+        match (error.downcast_ref::<Trap>(), &self.limits) {
+            (
+                Some(Trap::Interrupt),
+                ComponentLimits {
+                    timeout_ms: Some(timeout_ms),
+                    ..
+                },
+            ) => self.timeout_error(*timeout_ms),
+            (
+                Some(Trap::OutOfFuel),
+                ComponentLimits {
+                    fuel: Some(fuel), ..
+                },
+            ) => ExecutionLimitExceeded::OutOfFuel {
+                component: self.component_name.clone(),
+                fuel: *fuel,
+            }
+            .into(),
+            (Some(trap), _) => InvocationError::Trap {
+                component: self.component_name.clone(),
+                function: function.to_string(),
+                message: trap.to_string(),
+                backtrace: error
+                    .downcast_ref::<WasmBacktrace>()
+                    .map(ToString::to_string),
+            }
+            .into(),
+            (None, _) if error.is::<ResourceLimitExceeded>() => error.into(),
+            // Only a host import failing on I/O may succeed when retried;
+            // anything else would fail the same way again.
+            (None, _) if error.chain().any(|e| e.is::<std::io::Error>()) => {
+                InvocationError::Capability {
+                    component: self.component_name.clone(),
+                    function: function.to_string(),
+                    message: format!("{error:#}"),
+                }
+                .into()
+            }
+            (None, _) => InvocationError::Other {
+                component: self.component_name.clone(),
+                function: function.to_string(),
+                source: error.into(),
+            }
+            .into(),
+        }
+    }
+
+    // Convert a call's wasmtime results into [`Val`]s.
+    //
+    // A WIT `result<T, E>` maps onto Rust's `Result`: an `err` becomes an
+    // `ErrorResult` here, so callers handle failure before ever checking for an
+    // `ok` value. A resource remains a handle; everything else converts to JSON.
+    fn convert_results(&self, results: Vec<WasmtimeVal>, function: &str) -> Result<Option<Val>> {
+        if results.len() > 1 {
+            anyhow::bail!(
+                "got {} results; a WIT function declares at most one",
+                results.len()
+            );
         }
-        WasmtimeVal::Result(Err(None)) => anyhow::bail!("Component returned error"),
-        value => Ok(Some(convert_value(value)?)),
+        let Some(result) = results.first() else {
+            return Ok(None);
+        };
+        match result {
+            WasmtimeVal::Result(Ok(Some(ok_val))) => Ok(Some(convert_value(ok_val)?)),
+            WasmtimeVal::Result(Ok(None)) => Ok(None),
+            WasmtimeVal::Result(Err(error_val)) => {
+                let error = match error_val {
+                    Some(error_val) => val_to_json(error_val)
+                        .unwrap_or_else(|e| serde_json::Value::String(format!("<{e}>"))),
+                    None => serde_json::Value::Null,
+                };
+                Err(InvocationError::ErrorResult {
+                    component: self.component_name.clone(),
+                    function: function.to_string(),
+                    error,
+                }
+                .into())
+            }
+            value => Ok(Some(convert_value(value)?)),
+        }
+    }
+
+    fn timeout_error(&self, timeout_ms: u64) -> anyhow::Error {
+        ExecutionLimitExceeded::Timeout {
+            component: self.component_name.clone(),
+            timeout_ms,
+        }
+        .into()
+    }
+}
+
+// Arm the store's epoch deadline and fuel budget from the component's limits.
+// Fuel can only be set when the engine consumes fuel, which it does if any
+// component has a `fuel` budget; other components then get an unlimited one.
+pub(crate) fn reset_execution_limits(
+    store: &mut Store<ComponentState>,
+    limits: &ComponentLimits,
+) -> Result<()> {
+    let deadline = limits.timeout_ms.map_or(NO_EPOCH_DEADLINE, |timeout_ms| {
+        // Rounded up, plus one tick because the current tick is partly over.
+        timeout_ms.div_ceil(EPOCH_TICK.as_millis() as u64) + 1
+    });
+    store.set_epoch_deadline(deadline);
+    if store.get_fuel().is_ok() {
+        store.set_fuel(limits.fuel.unwrap_or(u64::MAX))?;
     }
+    Ok(())
 }
 
 fn convert_value(val: &WasmtimeVal) -> Result<Val> {
@@ -0,0 +1,121 @@
+//! Bounds on the simultaneous use of a component (`max-concurrency`), and on
+//! how many callers may wait for it (`max-queue`).
+
+use anyhow::Result;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU32, Ordering};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+use crate::types::{ComponentBusy, ComponentLimits};
+
+/// Admits at most `max-concurrency` invocations or live instances of one
+/// component. Further callers wait in line, and are turned away with a
+/// [`ComponentBusy`] error once `max-queue` callers are already waiting.
+pub(crate) struct ConcurrencyGate {
+    component: String,
+    permits: Arc<Semaphore>,
+    waiting: AtomicU32,
+    max_concurrency: u32,
+    max_queue: Option<u32>,
+}
+
+impl ConcurrencyGate {
+    /// Returns `None` when the component has no `max-concurrency`.
+    pub(crate) fn new(component: &str, limits: &ComponentLimits) -> Option<Self> {
+        let max_concurrency = limits.max_concurrency?;
+        Some(Self {
+            component: component.to_string(),
+            permits: Arc::new(Semaphore::new(max_concurrency as usize)),
+            waiting: AtomicU32::new(0),
+            max_concurrency,
+            max_queue: limits.max_queue,
+        })
+    }
+
+    /// Wait for a slot. It is released when the returned permit is dropped.
+    pub(crate) async fn acquire(&self) -> Result<OwnedSemaphorePermit> {
+        if let Ok(permit) = Arc::clone(&self.permits).try_acquire_owned() {
+            return Ok(permit);
+        }
+
+        // Only a caller that got a place in the queue waits, so the queue
+        // never holds more than `max-queue` callers.
+        let queued = self
+            .waiting
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |waiting| {
+                match self.max_queue {
+                    Some(max_queue) if waiting >= max_queue => None,
+                    _ => Some(waiting + 1),
+                }
+            });
+        if queued.is_err() {
+            // A slot may have been released since the first attempt.
+            return Arc::clone(&self.permits)
+                .try_acquire_owned()
+                .map_err(|_| self.busy().into());
+        }
+        let _slot = QueueSlot(&self.waiting);
+        Ok(Arc::clone(&self.permits).acquire_owned().await?)
+    }
+
+    fn busy(&self) -> ComponentBusy {
+        ComponentBusy {
+            component: self.component.clone(),
+            max_concurrency: self.max_concurrency,
+            max_queue: self.max_queue,
+        }
+    }
+}
+
+// A place in the queue, given up when the caller gets a permit, is turned
+// away, or is cancelled while waiting.
+struct QueueSlot<'a>(&'a AtomicU32);
+
+impl Drop for QueueSlot<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn queue_never_exceeds_max_queue() {
+        let limits = ComponentLimits {
+            max_concurrency: Some(1),
+            max_queue: Some(2),
+            ..Default::default()
+        };
+        let gate = Arc::new(ConcurrencyGate::new("guest", &limits).unwrap());
+        let held = gate.acquire().await.unwrap();
+
+        let callers: Vec<_> = (0..16)
+            .map(|_| {
+                let gate = Arc::clone(&gate);
+                tokio::spawn(async move {
+                    tokio::time::timeout(std::time::Duration::from_millis(200), gate.acquire())
+                        .await
+                })
+            })
+            .collect();
+        let mut busy = 0;
+        let mut waited = 0;
+        for caller in callers {
+            match caller.await.unwrap() {
+                Ok(Err(e)) => {
+                    assert!(e.downcast_ref::<ComponentBusy>().is_some(), "{e}");
+                    busy += 1;
+                }
+                Err(_elapsed) => waited += 1,
+                Ok(Ok(_)) => panic!("no slot was released"),
+            }
+        }
+        assert_eq!((busy, waited), (14, 2));
+        assert_eq!(gate.waiting.load(Ordering::SeqCst), 0);
+
+        drop(held);
+        let _permit = gate.acquire().await.unwrap();
+    }
+}
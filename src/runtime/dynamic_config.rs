@@ -0,0 +1,292 @@
+//! Built-in `wasi:config` capability, for components with
+//! `config-mode = "dynamic"`.
+//!
+//! Values are looked up at call time, so they can change without
+//! recomposing the component:
+//!
+//! ```toml
+//! [component.greeter]
+//! uri = "./greeter.wasm"
+//! imports = ["config"]
+//! config-mode = "dynamic"
+//! config.greeting = "Hello"
+//!
+//! [capability.config]
+//! type = "wasi:config"
+//! env-prefix = "GREETER_"     # optional: GREETER_GREETING overrides `greeting`
+//! file = "./greeter.toml"     # optional: re-read whenever it changes
+//! ```
+//!
+//! The file is checked for changes once a second in the background, so a
+//! lookup never waits on the file system.
+//!
+//! Environment variables take precedence over the file, and the file over
+//! the component's `config` table. A key maps to the variable named by the
+//! prefix followed by the key in upper case, with `.` and `-` replaced by `_`.
+//! That mapping cannot be reversed, so a variable only overrides a key the
+//! file or the table defines; it never adds one.
+
+use anyhow::Result;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+use wasmtime::component::{HasData, Linker};
+
+use crate::composition::composer::config_properties;
+use crate::composition::registry::{CapabilityRegistry, split_wasi_kind};
+use crate::types::ComponentState;
+
+mod generated {
+    wasmtime::component::bindgen!({
+        path: "wit/deps/wasi-config-0.2.0-rc.1",
+        world: "wasi:config/imports",
+        imports: { default: trappable },
+    });
+}
+
+use generated::wasi::config::store::{self, Error};
+
+const FILE_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Where a `wasi:config` capability looks up values besides the component's
+/// `config` table.
+pub(crate) struct ConfigSource {
+    env_prefix: Option<String>,
+    // Looks up an environment variable; tests swap in a fixed set.
+    env: fn(&str) -> Option<String>,
+    file: Option<Arc<WatchedFile>>,
+}
+
+// A TOML file, flattened like a `config` table. A background thread re-reads
+// it when its modification time or length changes.
+struct WatchedFile {
+    path: PathBuf,
+    state: Mutex<FileState>,
+}
+
+// The modification time and length the file had when it was last read, and
+// the values read then, or why they could not be.
+struct FileState {
+    stamp: Option<(SystemTime, u64)>,
+    values: FileValues,
+}
+
+type FileValues = std::result::Result<Arc<HashMap<String, String>>, String>;
+
+impl WatchedFile {
+    // Read the file, and keep it up to date for as long as it is in use.
+    fn watch(path: PathBuf) -> Result<Arc<Self>> {
+        let file = Arc::new(Self {
+            path,
+            state: Mutex::new(FileState {
+                stamp: None,
+                values: Err("not read yet".to_string()),
+            }),
+        });
+        file.refresh();
+        let weak = Arc::downgrade(&file);
+        std::thread::Builder::new()
+            .name("composable-config-watcher".to_string())
+            .spawn(move || {
+                loop {
+                    std::thread::sleep(FILE_POLL_INTERVAL);
+                    let Some(file) = weak.upgrade() else {
+                        break;
+                    };
+                    file.refresh();
+                }
+            })?;
+        Ok(file)
+    }
+
+    fn values(&self) -> FileValues {
+        self.state.lock().unwrap().values.clone()
+    }
+
+    // Re-read the file if it changed since it was last read.
+    fn refresh(&self) {
+        let path = self.path.display();
+        let stamp = match std::fs::metadata(&self.path).and_then(|m| Ok((m.modified()?, m.len()))) {
+            Ok(stamp) => stamp,
+            Err(e) => {
+                *self.state.lock().unwrap() = FileState {
+                    stamp: None,
+                    values: Err(format!("'{path}': {e}")),
+                };
+                return;
+            }
+        };
+        if self.state.lock().unwrap().stamp == Some(stamp) {
+            return;
+        }
+        let values = self.read().map_err(|e| format!("'{path}': {e}"));
+        *self.state.lock().unwrap() = FileState {
+            stamp: Some(stamp),
+            values,
+        };
+    }
+
+    fn read(&self) -> Result<Arc<HashMap<String, String>>> {
+        let content = std::fs::read_to_string(&self.path)?;
+        let table: HashMap<String, serde_json::Value> = toml::from_str(&content)?;
+        Ok(Arc::new(config_properties(&table)?.into_iter().collect()))
+    }
+}
+
+/// Validate every `wasi:config` capability and return its source, by
+/// capability name.
+pub(crate) fn sources(
+    capability_registry: &CapabilityRegistry,
+) -> Result<HashMap<String, Arc<ConfigSource>>> {
+    let mut sources = HashMap::new();
+    for (name, capability) in capability_registry.capabilities.iter() {
+        if split_wasi_kind(&capability.kind).0 != "wasi:config" {
+            continue;
+        }
+        let string = |key: &str| match capability.properties.get(key) {
+            None => Ok(None),
+            Some(serde_json::Value::String(s)) => Ok(Some(s.clone())),
+            Some(other) => Err(anyhow::anyhow!(
+                "Capability '{name}': '{key}' must be a string, got {other}"
+            )),
+        };
+        let file = string("file")?
+            .map(|path| WatchedFile::watch(PathBuf::from(path)))
+            .transpose()?;
+        // Fail the build, rather than every call, on a file that is missing
+        // or invalid from the start.
+        if let Some(file) = &file {
+            file.values()
+                .map_err(|e| anyhow::anyhow!("Capability '{name}': invalid config file {e}"))?;
+        }
+        let source = ConfigSource {
+            env_prefix: string("env-prefix")?,
+            env: |name| std::env::var(name).ok(),
+            file,
+        };
+        sources.insert(name.clone(), Arc::new(source));
+    }
+    Ok(sources)
+}
+
+/// A component's dynamic config: its own `config` table and the source of
+/// its `wasi:config` capability.
+#[derive(Clone)]
+pub(crate) struct DynamicConfig {
+    pub(crate) table: Arc<HashMap<String, String>>,
+    pub(crate) source: Arc<ConfigSource>,
+}
+
+impl DynamicConfig {
+    fn get(&self, key: &str) -> std::result::Result<Option<String>, Error> {
+        let value = match &self.source.file {
+            Some(file) => file.values().map_err(Error::Io)?.get(key).cloned(),
+            None => None,
+        }
+        .or_else(|| self.table.get(key).cloned());
+        if value.is_some()
+            && let Some(value) = self.env_value(key)
+        {
+            return Ok(Some(value));
+        }
+        Ok(value)
+    }
+
+    fn get_all(&self) -> std::result::Result<Vec<(String, String)>, Error> {
+        let mut values = (*self.table).clone();
+        if let Some(file) = &self.source.file {
+            let file_values = file.values().map_err(Error::Io)?;
+            values.extend(file_values.iter().map(|(k, v)| (k.clone(), v.clone())));
+        }
+        for (key, value) in values.iter_mut() {
+            if let Some(env_value) = self.env_value(key) {
+                *value = env_value;
+            }
+        }
+        let mut values: Vec<_> = values.into_iter().collect();
+        values.sort();
+        Ok(values)
+    }
+
+    fn env_value(&self, key: &str) -> Option<String> {
+        let prefix = self.source.env_prefix.as_ref()?;
+        (self.source.env)(&env_var(prefix, key))
+    }
+}
+
+fn env_var(prefix: &str, key: &str) -> String {
+    let key: String = key
+        .chars()
+        .map(|c| match c {
+            '.' | '-' => '_',
+            c => c.to_ascii_uppercase(),
+        })
+        .collect();
+    format!("{prefix}{key}")
+}
+
+/// Link `wasi:config/store` against the component's [`DynamicConfig`].
+pub(crate) fn add_to_linker(linker: &mut Linker<ComponentState>) -> Result<()> {
+    store::add_to_linker::<_, HasDynamicConfig>(linker, |state| {
+        state.config.as_ref().expect(
+            "Component requires 'wasi:config' capability, so its config should be available",
+        )
+    })?;
+    Ok(())
+}
+
+struct HasDynamicConfig;
+
+impl HasData for HasDynamicConfig {
+    type Data<'a> = &'a DynamicConfig;
+}
+
+impl store::Host for &DynamicConfig {
+    fn get(&mut self, key: String) -> wasmtime::Result<std::result::Result<Option<String>, Error>> {
+        Ok(DynamicConfig::get(self, &key))
+    }
+
+    fn get_all(&mut self) -> wasmtime::Result<std::result::Result<Vec<(String, String)>, Error>> {
+        Ok(DynamicConfig::get_all(self))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn env_var_names() {
+        assert_eq!(env_var("APP_", "db.url"), "APP_DB_URL");
+        assert_eq!(env_var("APP_", "max-conns"), "APP_MAX_CONNS");
+    }
+
+    #[test]
+    fn get_all_only_overrides_known_keys() {
+        let config = DynamicConfig {
+            table: Arc::new(HashMap::from([
+                ("db.url".to_string(), "postgres://table".to_string()),
+                ("name".to_string(), "world".to_string()),
+            ])),
+            source: Arc::new(ConfigSource {
+                env_prefix: Some("APP_".to_string()),
+                env: |name| match name {
+                    "APP_DB_URL" => Some("postgres://env".to_string()),
+                    "APP_OTHER_KEY" => Some("unknown".to_string()),
+                    _ => None,
+                },
+                file: None,
+            }),
+        };
+        let values = config.get_all().ok().unwrap();
+        assert_eq!(
+            values,
+            vec![
+                ("db.url".to_string(), "postgres://env".to_string()),
+                ("name".to_string(), "world".to_string()),
+            ]
+        );
+        assert_eq!(config.get("other.key").ok(), Some(None));
+    }
+}
@@ -1,9 +1,14 @@
 use anyhow::Result;
-use std::collections::HashMap;
 use std::collections::hash_map::Entry;
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+use tokio::sync::OwnedSemaphorePermit;
 use wasmtime::{
-    Cache, Config, Engine, Store,
-    component::{Component as WasmComponent, Linker},
+    Cache, CacheConfig, Config, Engine, InstanceAllocationStrategy, PoolingAllocationConfig,
+    Precompiled, Store,
+    component::{Component as WasmComponent, InstancePre, Linker},
 };
 use wasmtime_wasi::cli::{WasiCli, WasiCliView};
 use wasmtime_wasi::clocks::{WasiClocks, WasiClocksView};
@@ -15,31 +20,171 @@ use wasmtime_wasi_http::WasiHttpCtx;
 use wasmtime_wasi_http::{p2 as http_p2, p3 as http_p3};
 use wasmtime_wasi_io::IoView;
 
+use crate::composition::lockfile::sha256_digest;
 use crate::composition::registry::{
-    CapabilityRegistry, ComponentRegistry, WasiVersion, split_wasi_kind,
+    CapabilityRegistry, ComponentRegistry, ComponentSpec, WasiVersion, split_wasi_kind,
 };
 use crate::context::PROPAGATION_CONTEXT;
-use crate::runtime::component::{ComponentInstance, Val};
+use crate::metrics::Metrics;
+use crate::runtime::component::{ComponentInstance, EPOCH_TICK, Val, reset_execution_limits};
+use crate::runtime::concurrency::ConcurrencyGate;
+use crate::runtime::dynamic_config::{self, ConfigSource, DynamicConfig};
+use crate::runtime::host_overrides::{self, HostOverrides};
+use crate::runtime::http_client::{BoxError, ConnectError, MIN_TIMEOUT, SendError, TimeoutBody};
+use crate::runtime::http_policy::{LimitedBody, PolicyViolation};
+use crate::runtime::keyvalue::KeyValueCtx;
+use crate::runtime::lifecycle::InstancePool;
+use crate::runtime::limiter::{ComponentLimiter, InstanceCounts};
+use crate::runtime::socket_policy::SocketPolicy;
 use crate::types::{
     Component, ComponentInvoker, ComponentMetadata, ComponentState, Function, HttpHooks,
-    PROPAGATED_HEADERS,
+    InvocationError, OptLevel, PROPAGATED_HEADERS, RuntimeConfig,
 };
 
 // Component host: wasmtime engine + registries, provides instantiation + invocation.
+//
+// Clones share one host. Its state is replaced as a whole when the runtime
+// reloads, while invocations already in flight keep the state they started
+// with until they finish.
 #[derive(Clone)]
 pub(crate) struct ComponentHost {
+    state: Arc<RwLock<Arc<HostState>>>,
+}
+
+pub(crate) struct HostState {
     invoker: Invoker,
     components: HashMap<String, Component>,
+    prepared: HashMap<String, PreparedComponent>,
+    pools: HashMap<String, InstancePool>,
+    gates: HashMap<String, ConcurrencyGate>,
+    contexts: CapabilityContexts,
+    metrics: Arc<Metrics>,
+    pub(crate) runtime_config: RuntimeConfig,
     pub(crate) component_registry: ComponentRegistry,
     pub(crate) capability_registry: CapabilityRegistry,
 }
 
+// What the built-in capabilities hand each instance, parsed from their
+// properties once per host.
+struct CapabilityContexts {
+    // `wasi:keyvalue` stores, by capability name.
+    keyvalue: HashMap<String, KeyValueCtx>,
+    // `wasi:cli` environment variables, by capability name.
+    cli_envs: HashMap<String, Vec<(String, String)>>,
+    // `wasi:config` sources, by capability name.
+    config_sources: HashMap<String, Arc<ConfigSource>>,
+    // `wasi:http` hooks, by capability name.
+    http_hooks: HashMap<String, HttpHooks>,
+    // `wasi:sockets` host overrides, by capability name.
+    socket_overrides: HashMap<String, Arc<HostOverrides>>,
+    // `wasi:sockets` address allow-lists, by capability name.
+    socket_policies: HashMap<String, Arc<SocketPolicy>>,
+}
+
+// A component compiled and pre-linked at build time. A linking failure (e.g.
+// a host import no capability provides) is kept and reported when the
+// component is instantiated, so it only fails the invocations that need it.
+type PreparedComponent = std::result::Result<Prepared, String>;
+
+struct Prepared {
+    instance_pre: InstancePre<ComponentState>,
+    counts: InstanceCounts,
+}
+
 impl ComponentHost {
     pub(crate) fn new(
+        runtime_config: RuntimeConfig,
+        component_registry: ComponentRegistry,
+        capability_registry: CapabilityRegistry,
+        keyvalue: HashMap<String, KeyValueCtx>,
+        metrics: Arc<Metrics>,
+    ) -> Result<Self> {
+        let state = HostState::new(
+            runtime_config,
+            component_registry,
+            capability_registry,
+            keyvalue,
+            metrics,
+        )?;
+        Ok(Self {
+            state: Arc::new(RwLock::new(Arc::new(state))),
+        })
+    }
+
+    // The current state. Holding it keeps its components alive across a reload.
+    pub(crate) fn state(&self) -> Arc<HostState> {
+        Arc::clone(&self.state.read().unwrap())
+    }
+
+    // Switch every clone of this host over to the state of `other`.
+    pub(crate) fn replace(&self, other: ComponentHost) {
+        let state = other.state();
+        *self.state.write().unwrap() = state;
+    }
+
+    pub(crate) async fn instantiate(
+        &self,
+        component_name: &str,
+        env_vars: &[(String, String)],
+    ) -> Result<ComponentInstance> {
+        self.state().instantiate(component_name, env_vars).await
+    }
+}
+
+impl HostState {
+    fn new(
+        runtime_config: RuntimeConfig,
         component_registry: ComponentRegistry,
         capability_registry: CapabilityRegistry,
+        keyvalue: HashMap<String, KeyValueCtx>,
+        metrics: Arc<Metrics>,
     ) -> Result<Self> {
-        let invoker = Invoker::new()?;
+        let specs = || component_registry.get_components();
+        let timeouts = specs().any(|spec| spec.limits.timeout_ms.is_some());
+        let fuel = specs().any(|spec| spec.limits.fuel.is_some());
+        let invoker = Invoker::new(&runtime_config, timeouts, fuel)?;
+        if timeouts {
+            invoker.start_epoch_ticker()?;
+        }
+        let prepared = invoker.prepare_components(&component_registry, &capability_registry)?;
+        let cli_envs = capability_registry
+            .capabilities
+            .iter()
+            .filter(|(_, cap)| split_wasi_kind(&cap.kind).0 == "wasi:cli")
+            .map(|(name, cap)| Ok((name.clone(), cli_env(&cap.properties, name)?)))
+            .collect::<Result<_>>()?;
+        let config_sources = dynamic_config::sources(&capability_registry)?;
+        let http_hooks = capability_registry
+            .capabilities
+            .iter()
+            .filter(|(_, cap)| split_wasi_kind(&cap.kind).0 == "wasi:http")
+            .map(|(name, cap)| {
+                Ok((
+                    name.clone(),
+                    HttpHooks::from_properties(name, &cap.properties)?,
+                ))
+            })
+            .collect::<Result<_>>()?;
+        let socket_overrides = capability_registry
+            .capabilities
+            .iter()
+            .filter(|(_, cap)| split_wasi_kind(&cap.kind).0 == "wasi:sockets")
+            .filter_map(|(name, cap)| {
+                HostOverrides::from_properties(name, &cap.properties, false)
+                    .map(|overrides| Some((name.clone(), Arc::new(overrides?))))
+                    .transpose()
+            })
+            .collect::<Result<_>>()?;
+        let socket_policies = capability_registry
+            .capabilities
+            .iter()
+            .filter(|(_, cap)| split_wasi_kind(&cap.kind).0 == "wasi:sockets")
+            .filter_map(|(name, cap)| {
+                SocketPolicy::from_properties(name, &cap.properties)
+                    .map(|policy| Some((name.clone(), Arc::new(policy?))))
+                    .transpose()
+            })
+            .collect::<Result<_>>()?;
         let components = component_registry
             .get_components()
             .map(|spec| {
@@ -57,15 +202,39 @@ impl ComponentHost {
                 (spec.name.clone(), component)
             })
             .collect();
+        let pools = component_registry
+            .get_components()
+            .filter_map(|spec| Some((spec.name.clone(), InstancePool::new(spec.lifecycle)?)))
+            .collect();
+        let gates = component_registry
+            .get_components()
+            .filter_map(|spec| {
+                let gate = ConcurrencyGate::new(&spec.name, &spec.limits)?;
+                Some((spec.name.clone(), gate))
+            })
+            .collect();
         Ok(Self {
             invoker,
             components,
+            prepared,
+            pools,
+            gates,
+            contexts: CapabilityContexts {
+                keyvalue,
+                cli_envs,
+                config_sources,
+                http_hooks,
+                socket_overrides,
+                socket_policies,
+            },
+            metrics,
+            runtime_config,
             component_registry,
             capability_registry,
         })
     }
 
-    pub(crate) async fn invoke(
+    async fn invoke(
         &self,
         component_name: &str,
         function_name: &str,
@@ -75,25 +244,76 @@ impl ComponentHost {
         let spec = self
             .component_registry
             .get_component(component_name)
-            .ok_or_else(|| anyhow::anyhow!("Component '{component_name}' not found"))?;
+            .ok_or_else(|| InvocationError::ComponentNotFound {
+                component: component_name.to_string(),
+            })?;
 
-        let function = spec.functions.get(function_name).ok_or_else(|| {
-            anyhow::anyhow!("Function '{function_name}' not found in component '{component_name}'")
-        })?;
+        let function =
+            spec.functions
+                .get(function_name)
+                .ok_or_else(|| InvocationError::FunctionNotFound {
+                    component: component_name.to_string(),
+                    function: function_name.to_string(),
+                })?;
 
-        self.invoker
-            .invoke(
-                &spec.bytes,
-                &spec.capabilities,
-                &self.capability_registry,
-                function.clone(),
-                args,
-                env_vars,
-            )
-            .await
+        // A component that failed to link lacks a capability it imports, and
+        // will until the runtime is reloaded.
+        self.prepared(component_name)
+            .map_err(|e| InvocationError::Other {
+                component: component_name.to_string(),
+                function: function_name.to_string(),
+                source: e,
+            })?;
+
+        let _permit = self.acquire(component_name).await?;
+        let Some(pool) = self.pools.get(component_name) else {
+            // Single-use invocation: instantiate, call, drop.
+            let mut instance = self.new_instance(component_name, env_vars).await?;
+            let args = args.into_iter().map(Val::Json).collect();
+            let results = instance.call(function, args).await?;
+            return results_to_json(results, function);
+        };
+
+        // A reused instance keeps the environment it was instantiated with.
+        if !env_vars.is_empty() {
+            return Err(InvocationError::InvalidArguments {
+                component: component_name.to_string(),
+                function: function_name.to_string(),
+                message: "the component reuses instances across invocations and does not accept per-invocation env vars".to_string(),
+            }
+            .into());
+        }
+        let args = args.into_iter().map(Val::Json).collect();
+        let results = pool
+            .call(|| self.new_instance(component_name, &[]), function, args)
+            .await?;
+        results_to_json(results, function)
     }
 
-    pub(crate) async fn instantiate(
+    async fn instantiate(
+        &self,
+        component_name: &str,
+        env_vars: &[(String, String)],
+    ) -> Result<ComponentInstance> {
+        let permit = self.acquire(component_name).await?;
+        let instance = self.new_instance(component_name, env_vars).await?;
+        Ok(match permit {
+            Some(permit) => instance.with_permit(permit),
+            None => instance,
+        })
+    }
+
+    // Wait for a `max-concurrency` slot, if the component has a limit.
+    async fn acquire(&self, component_name: &str) -> Result<Option<OwnedSemaphorePermit>> {
+        match self.gates.get(component_name) {
+            Some(gate) => Ok(Some(gate.acquire().await?)),
+            None => Ok(None),
+        }
+    }
+
+    // Instantiate without taking a concurrency slot: pooled instances are
+    // only counted while serving an invocation.
+    async fn new_instance(
         &self,
         component_name: &str,
         env_vars: &[(String, String)],
@@ -103,33 +323,53 @@ impl ComponentHost {
             .get_component(component_name)
             .ok_or_else(|| anyhow::anyhow!("Component '{component_name}' not found"))?;
 
-        self.invoker
-            .instantiate_from_bytes(
-                &spec.bytes,
-                &spec.capabilities,
+        let start = Instant::now();
+        let instance = self
+            .invoker
+            .instantiate(
+                self.prepared(component_name)?,
+                spec,
                 &self.capability_registry,
+                &self.contexts,
                 env_vars,
             )
-            .await
+            .await?;
+        self.metrics
+            .record_instantiation(component_name, start.elapsed());
+        Ok(instance)
+    }
+
+    // The compiled component, for serializing ahead of time.
+    pub(crate) fn compiled_component(&self, component_name: &str) -> Result<&WasmComponent> {
+        Ok(self.prepared(component_name)?.instance_pre.component())
+    }
+
+    fn prepared(&self, component_name: &str) -> Result<&Prepared> {
+        match self.prepared.get(component_name) {
+            Some(Ok(prepared)) => Ok(prepared),
+            Some(Err(e)) => Err(anyhow::anyhow!(
+                "Component '{component_name}' cannot be linked: {e}"
+            )),
+            None => Err(anyhow::anyhow!("Component '{component_name}' not found")),
+        }
     }
 }
 
 impl ComponentInvoker for ComponentHost {
-    fn get_component(&self, name: &str) -> Option<&Component> {
-        self.components.get(name)
+    fn get_component(&self, name: &str) -> Option<Component> {
+        self.state().components.get(name).cloned()
     }
 
-    fn list_components(
-        &self,
-        selector: Option<&crate::config::types::Selector>,
-    ) -> Vec<&Component> {
+    fn list_components(&self, selector: Option<&crate::config::types::Selector>) -> Vec<Component> {
+        let state = self.state();
         match selector {
-            Some(selector) => self
+            Some(selector) => state
                 .components
                 .values()
                 .filter(|c| selector.matches(&c.metadata.to_selectable()))
+                .cloned()
                 .collect(),
-            None => self.components.values().collect(),
+            None => state.components.values().cloned().collect(),
         }
     }
 
@@ -140,13 +380,29 @@ impl ComponentInvoker for ComponentHost {
         args: Vec<serde_json::Value>,
         env: Option<HashMap<String, String>>,
     ) -> std::pin::Pin<
-        Box<dyn std::future::Future<Output = anyhow::Result<serde_json::Value>> + Send + 'a>,
+        Box<
+            dyn std::future::Future<
+                    Output = std::result::Result<serde_json::Value, InvocationError>,
+                > + Send
+                + 'a,
+        >,
     > {
         Box::pin(async move {
             let env_pairs: Vec<(String, String)> =
                 env.map(|m| m.into_iter().collect()).unwrap_or_default();
-            self.invoke(component_name, function_name, args, &env_pairs)
+            let state = self.state();
+            let start = Instant::now();
+            let result = state
+                .invoke(component_name, function_name, args, &env_pairs)
                 .await
+                .map_err(|e| InvocationError::from_error(component_name, function_name, e));
+            state.metrics.record_invocation(
+                component_name,
+                function_name,
+                &result,
+                start.elapsed(),
+            );
+            result
         })
     }
 }
@@ -198,7 +454,10 @@ impl HttpHooks {
 impl http_p2::WasiHttpView for ComponentState {
     fn http(&mut self) -> http_p2::WasiHttpCtxView<'_> {
         http_p2::WasiHttpCtxView {
-            hooks: &mut self.http_hooks,
+            hooks: self
+                .http_hooks
+                .as_mut()
+                .expect("Component requires 'http' capability, so HTTP hooks should be available"),
             table: &mut self.resource_table,
             ctx: self.wasi_http_ctx.as_mut().expect(
                 "Component requires 'http' capability, so HTTP context should be available",
@@ -210,7 +469,10 @@ impl http_p2::WasiHttpView for ComponentState {
 impl http_p3::WasiHttpView for ComponentState {
     fn http(&mut self) -> http_p3::WasiHttpCtxView<'_> {
         http_p3::WasiHttpCtxView {
-            hooks: &mut self.http_hooks,
+            hooks: self
+                .http_hooks
+                .as_mut()
+                .expect("Component requires 'http' capability, so HTTP hooks should be available"),
             table: &mut self.resource_table,
             ctx: self.wasi_http_ctx.as_mut().expect(
                 "Component requires 'http' capability, so HTTP context should be available",
@@ -226,15 +488,75 @@ impl http_p2::WasiHttpHooks for HttpHooks {
         config: http_p2::types::OutgoingRequestConfig,
     ) -> http_p2::HttpResult<http_p2::types::HostFutureIncomingResponse> {
         Self::propagate_headers(&mut request);
+        self.policy.check_request(&request).map_err(p2_error)?;
+        if let Some(limit) = self.policy.max_request_body_bytes {
+            request = request.map(|body| {
+                use http_body_util::BodyExt;
+                LimitedBody::new(body, limit, P2ErrorCode::HttpRequestBodySize(None)).boxed_unsync()
+            });
+        }
+
+        let grpc = self.client.grpc_enabled() && Self::is_grpc(&request);
+        let client = self.client.clone();
+        let handle = wasmtime_wasi::runtime::spawn(async move {
+            Ok(async {
+                use http_body_util::BodyExt;
 
-        if self.h2c_for_grpc && Self::is_grpc(&request) {
-            if request.uri().scheme_str() == Some("https") {
-                tracing::error!("h2c-for-grpc does not support TLS (https)");
-                return Err(http_p2::bindings::http::types::ErrorCode::HttpProtocolError.into());
+                let request = request.map(|body| body.map_err(BoxError::from).boxed_unsync());
+                let response = client
+                    .send(
+                        request,
+                        grpc,
+                        config.connect_timeout,
+                        config.first_byte_timeout,
+                    )
+                    .await
+                    .map_err(p2_send_error)?;
+                Ok(http_p2::types::IncomingResponse {
+                    resp: response
+                        .map(|body| body.map_err(http_p2::hyper_response_error).boxed_unsync()),
+                    worker: None,
+                    between_bytes_timeout: config
+                        .between_bytes_timeout
+                        .min(client.between_bytes_timeout)
+                        .max(MIN_TIMEOUT),
+                })
             }
-            Ok(super::grpc::send_grpc_request_p2(request, config))
-        } else {
-            Ok(http_p2::default_send_request(request, config))
+            .await)
+        });
+        Ok(http_p2::types::HostFutureIncomingResponse::pending(handle))
+    }
+}
+
+type P2ErrorCode = http_p2::bindings::http::types::ErrorCode;
+
+fn p2_error(violation: PolicyViolation) -> P2ErrorCode {
+    match violation {
+        PolicyViolation::Denied => P2ErrorCode::HttpRequestDenied,
+        PolicyViolation::BodyTooLarge(length) => P2ErrorCode::HttpRequestBodySize(length),
+    }
+}
+
+fn p2_send_error(err: SendError<P2ErrorCode>) -> P2ErrorCode {
+    match err {
+        SendError::Connect(ConnectError::InvalidUri) => P2ErrorCode::HttpRequestUriInvalid,
+        SendError::Connect(ConnectError::Timeout) => P2ErrorCode::ConnectionTimeout,
+        SendError::Connect(ConnectError::Refused) => P2ErrorCode::ConnectionRefused,
+        SendError::Connect(ConnectError::Dns) => {
+            P2ErrorCode::DnsError(http_p2::bindings::http::types::DnsErrorPayload {
+                rcode: Some("address not available".to_string()),
+                info_code: Some(0),
+            })
+        }
+        SendError::Connect(ConnectError::Denied) => P2ErrorCode::HttpRequestDenied,
+        SendError::Connect(ConnectError::Tls) => P2ErrorCode::TlsProtocolError,
+        SendError::Connect(ConnectError::NoH2) => P2ErrorCode::HttpProtocolError,
+        SendError::FirstByteTimeout => P2ErrorCode::ConnectionReadTimeout,
+        SendError::Closed => P2ErrorCode::ConnectionTerminated,
+        SendError::Body(code) => code,
+        SendError::Protocol(e) => {
+            tracing::warn!("HTTP request error: {e}");
+            P2ErrorCode::HttpProtocolError
         }
     }
 }
@@ -257,25 +579,99 @@ impl http_p3::WasiHttpHooks for HttpHooks {
                 >,
             > + Send,
     > {
+        use http_body_util::BodyExt;
+
         Self::propagate_headers(&mut request);
+        if let Err(violation) = self.policy.check_request(&request) {
+            let err = p3_error(violation);
+            return Box::new(async move { Err(err.into()) });
+        }
+        if let Some(limit) = self.policy.max_request_body_bytes {
+            request = request.map(|body| {
+                LimitedBody::new(body, limit, P3ErrorCode::HttpRequestBodySize(None)).boxed_unsync()
+            });
+        }
 
-        if self.h2c_for_grpc && Self::is_grpc(&request) {
-            if request.uri().scheme_str() == Some("https") {
-                tracing::error!("h2c-for-grpc does not support TLS (https)");
-                let err = P3ErrorCode::HttpProtocolError;
-                return Box::new(async move { Err(err.into()) });
-            }
-            super::grpc::send_grpc_request_p3(request, options)
-        } else {
-            // `fut` is the guest-side request-error channel,
-            // unused by `default_send_request`.
-            let _ = fut;
-            Box::new(async move {
-                use http_body_util::BodyExt;
-                let (res, io) = http_p3::default_send_request(request, options).await?;
-                Ok((res.map(BodyExt::boxed_unsync), Box::new(io) as P3IoFuture))
+        let grpc = self.client.grpc_enabled() && Self::is_grpc(&request);
+        // `fut` is the guest-side request-error channel. Request body errors
+        // surface as the send error instead.
+        let _ = fut;
+        let client = self.client.clone();
+        Box::new(async move {
+            let options = options.unwrap_or_default();
+            let request = request.map(|body| body.map_err(BoxError::from).boxed_unsync());
+            let response = client
+                .send(
+                    request,
+                    grpc,
+                    options.connect_timeout.unwrap_or(Duration::MAX),
+                    options.first_byte_timeout.unwrap_or(Duration::MAX),
+                )
+                .await
+                .map_err(p3_send_error)?;
+            let between_bytes_timeout = options
+                .between_bytes_timeout
+                .unwrap_or(Duration::MAX)
+                .min(client.between_bytes_timeout);
+            let response = response.map(|incoming| {
+                TimeoutBody::new(
+                    incoming,
+                    between_bytes_timeout,
+                    p3_response_error,
+                    P3ErrorCode::ConnectionReadTimeout,
+                )
+                .boxed_unsync()
+            });
+            let io: P3IoFuture = Box::new(async { Ok(()) });
+            Ok((response, io))
+        })
+    }
+}
+
+fn p3_send_error(err: SendError<P3ErrorCode>) -> P3ErrorCode {
+    match err {
+        SendError::Connect(ConnectError::InvalidUri) => P3ErrorCode::HttpRequestUriInvalid,
+        SendError::Connect(ConnectError::Timeout) => P3ErrorCode::ConnectionTimeout,
+        SendError::Connect(ConnectError::Refused) => P3ErrorCode::ConnectionRefused,
+        SendError::Connect(ConnectError::Dns) => {
+            P3ErrorCode::DnsError(http_p3::bindings::http::types::DnsErrorPayload {
+                rcode: Some("address not available".to_string()),
+                info_code: Some(0),
             })
         }
+        SendError::Connect(ConnectError::Denied) => P3ErrorCode::HttpRequestDenied,
+        SendError::Connect(ConnectError::Tls) => P3ErrorCode::TlsProtocolError,
+        SendError::Connect(ConnectError::NoH2) => P3ErrorCode::HttpProtocolError,
+        SendError::FirstByteTimeout => P3ErrorCode::ConnectionReadTimeout,
+        SendError::Closed => P3ErrorCode::ConnectionTerminated,
+        SendError::Body(code) => code,
+        SendError::Protocol(e) => {
+            tracing::warn!("HTTP request error: {e}");
+            P3ErrorCode::HttpProtocolError
+        }
+    }
+}
+
+// Map a hyper error from the response phase to a wasi-http `ErrorCode`.
+// Mirrors wasmtime's `from_hyper_response_error`, which is not public.
+fn p3_response_error(err: hyper::Error) -> P3ErrorCode {
+    use std::error::Error as _;
+    if err.is_timeout() {
+        return P3ErrorCode::HttpResponseTimeout;
+    }
+    if let Some(cause) = err.source()
+        && let Some(code) = cause.downcast_ref::<P3ErrorCode>()
+    {
+        return code.clone();
+    }
+    tracing::warn!("hyper response error: {err:?}");
+    P3ErrorCode::HttpProtocolError
+}
+
+fn p3_error(violation: PolicyViolation) -> P3ErrorCode {
+    match violation {
+        PolicyViolation::Denied => P3ErrorCode::HttpRequestDenied,
+        PolicyViolation::BodyTooLarge(length) => P3ErrorCode::HttpRequestBodySize(length),
     }
 }
 
@@ -285,9 +681,46 @@ struct Invoker {
 }
 
 impl Invoker {
-    pub fn new() -> Result<Self> {
+    // `epoch_interruption` and `consume_fuel` instrument all compiled code, so
+    // they are only enabled when some component sets `timeout-ms` or `fuel`.
+    pub fn new(
+        runtime_config: &RuntimeConfig,
+        epoch_interruption: bool,
+        consume_fuel: bool,
+    ) -> Result<Self> {
         let mut config = Config::new();
-        config.cache(Some(Cache::from_file(None)?));
+        let cache = match &runtime_config.cache_dir {
+            Some(dir) => {
+                // The cache requires an absolute directory.
+                let dir = std::path::absolute(dir)?;
+                let mut cache_config = CacheConfig::new();
+                cache_config.with_directory(dir);
+                Cache::new(cache_config)?
+            }
+            None => Cache::from_file(None)?,
+        };
+        config.cache(Some(cache));
+        config.debug_info(runtime_config.debug_info);
+        if let Some(opt_level) = runtime_config.opt_level {
+            config.cranelift_opt_level(match opt_level {
+                OptLevel::None => wasmtime::OptLevel::None,
+                OptLevel::Speed => wasmtime::OptLevel::Speed,
+                OptLevel::SpeedAndSize => wasmtime::OptLevel::SpeedAndSize,
+            });
+        }
+        if let Some(pooling) = &runtime_config.pooling {
+            let mut pool = PoolingAllocationConfig::default();
+            if let Some(n) = pooling.total_component_instances {
+                pool.total_component_instances(n);
+            }
+            if let Some(n) = pooling.total_memories {
+                pool.total_memories(n);
+            }
+            if let Some(n) = pooling.total_tables {
+                pool.total_tables(n);
+            }
+            config.allocation_strategy(InstanceAllocationStrategy::Pooling(pool));
+        }
         config.parallel_compilation(true);
         config.wasm_component_model_async(true);
         // Synchronous stream.read/future.read builtins.
@@ -299,10 +732,118 @@ impl Invoker {
         config.wasm_gc(true);
         config.wasm_exceptions(true);
         config.wasm_function_references(true);
+        config.epoch_interruption(epoch_interruption);
+        config.consume_fuel(consume_fuel);
         let engine = Engine::new(&config)?;
         Ok(Self { engine })
     }
 
+    // Advance the engine epoch every tick, so calls past their deadline trap.
+    // A thread rather than a task: a guest spinning in Wasm never yields, and
+    // would starve a ticker sharing its runtime thread. Stops once the engine
+    // is dropped.
+    fn start_epoch_ticker(&self) -> Result<()> {
+        let engine = self.engine.weak();
+        std::thread::Builder::new()
+            .name("composable-epoch-ticker".to_string())
+            .spawn(move || {
+                while let Some(engine) = engine.upgrade() {
+                    engine.increment_epoch();
+                    drop(engine);
+                    std::thread::sleep(EPOCH_TICK);
+                }
+            })?;
+        Ok(())
+    }
+
+    // Compile each component once and pre-link it, so that an invocation is
+    // only store creation plus instantiation. Components with the same set of
+    // capabilities share one linker.
+    fn prepare_components(
+        &self,
+        component_registry: &ComponentRegistry,
+        capability_registry: &CapabilityRegistry,
+    ) -> Result<HashMap<String, PreparedComponent>> {
+        let mut linkers: HashMap<Vec<String>, Linker<ComponentState>> = HashMap::new();
+        let mut prepared = HashMap::new();
+        for spec in component_registry.get_components() {
+            let component = match &spec.precompiled {
+                Some(path) => {
+                    self.load_precompiled(&spec.name, path, spec.precompiled_digest.as_deref())?
+                }
+                None => WasmComponent::from_binary(&self.engine, &spec.bytes).map_err(|e| {
+                    anyhow::anyhow!("Failed to compile component '{}': {e}", spec.name)
+                })?,
+            };
+
+            // Sorted, so link order (and shadowing) is deterministic.
+            let mut capability_set = spec.capabilities.clone();
+            capability_set.sort();
+            let linker = match linkers.entry(capability_set) {
+                Entry::Occupied(e) => e.into_mut(),
+                Entry::Vacant(e) => {
+                    let linker = self.create_linker(e.key(), capability_registry)?;
+                    e.insert(linker)
+                }
+            };
+
+            let counts = InstanceCounts::new(&spec.bytes, &component, &spec.limits)
+                .map_err(|e| anyhow::anyhow!("Component '{}': {e}", spec.name))?;
+            let instance_pre = linker.instantiate_pre(&component).map_err(|e| {
+                tracing::warn!("Component '{}' cannot be linked: {e}", spec.name);
+                e.to_string()
+            });
+            prepared.insert(
+                spec.name.clone(),
+                instance_pre.map(|instance_pre| Prepared {
+                    instance_pre,
+                    counts,
+                }),
+            );
+        }
+        Ok(prepared)
+    }
+
+    // Load a component compiled ahead of time by `composable compile`,
+    // verifying its `precompiled-digest` first. The bytes are read once, so
+    // the verified bytes are the deserialized ones. Wasmtime rejects an
+    // artifact from another wasmtime version or compiled with different
+    // engine settings, e.g. before a component set `fuel`.
+    fn load_precompiled(
+        &self,
+        component_name: &str,
+        path: &Path,
+        digest: Option<&str>,
+    ) -> Result<WasmComponent> {
+        let artifact = path.display();
+        let bytes = std::fs::read(path).map_err(|e| {
+            anyhow::anyhow!(
+                "Component '{component_name}': cannot read precompiled artifact '{artifact}': {e}"
+            )
+        })?;
+        if let Some(digest) = digest {
+            let actual = sha256_digest(&bytes);
+            if actual != digest {
+                return Err(anyhow::anyhow!(
+                    "Component '{component_name}': precompiled artifact '{artifact}' has digest '{actual}', expected '{digest}'"
+                ));
+            }
+        }
+        if Engine::detect_precompiled(&bytes) != Some(Precompiled::Component) {
+            return Err(anyhow::anyhow!(
+                "Component '{component_name}': '{artifact}' is not a precompiled component"
+            ));
+        }
+        // SAFETY: `precompiled` artifacts are trusted like the component
+        // definitions that name them, pinned when their bytes are, and are
+        // written by `Component::serialize`.
+        unsafe { WasmComponent::deserialize(&self.engine, &bytes) }.map_err(|e| {
+            anyhow::anyhow!(
+                "Component '{component_name}': precompiled artifact '{artifact}' is not compatible with this runtime: {e}"
+            )
+        })
+    }
+
     fn create_linker(
         &self,
         capabilities: &[String],
@@ -361,6 +902,9 @@ impl Invoker {
                                 ComponentState::clocks,
                             )?;
                         }
+                        ("wasi:config", _) => {
+                            dynamic_config::add_to_linker(&mut linker)?;
+                        }
                         ("wasi:filesystem", WasiVersion::P3) => {
                             wasmtime_wasi::p3::filesystem::add_to_linker(&mut linker)?;
                         }
@@ -385,6 +929,12 @@ impl Invoker {
                         ("wasi:io", WasiVersion::P2) => {
                             wasmtime_wasi_io::add_to_linker_async(&mut linker)?;
                         }
+                        ("wasi:keyvalue", _) => {
+                            super::keyvalue::add_to_linker(&mut linker)?;
+                        }
+                        ("wasi:logging", _) => {
+                            super::logging::add_to_linker(&mut linker)?;
+                        }
                         ("wasi:random", WasiVersion::P3) => {
                             wasmtime_wasi::p3::random::add_to_linker(&mut linker)?;
                         }
@@ -404,6 +954,9 @@ impl Invoker {
                         }
                         ("wasi:sockets", WasiVersion::P3) => {
                             wasmtime_wasi::p3::sockets::add_to_linker(&mut linker)?;
+                            if capability.properties.contains_key("host-overrides") {
+                                host_overrides::add_to_linker_p3(&mut linker)?;
+                            }
                         }
                         ("wasi:sockets", WasiVersion::P2) => {
                             sockets::tcp::add_to_linker::<ComponentState, WasiSockets>(
@@ -435,6 +988,9 @@ impl Invoker {
                                 &mut linker,
                                 ComponentState::sockets,
                             )?;
+                            if capability.properties.contains_key("host-overrides") {
+                                host_overrides::add_to_linker_p2(&mut linker)?;
+                            }
                         }
                         _ => {
                             anyhow::bail!("Unknown capability type: '{}'", capability.kind);
@@ -456,22 +1012,18 @@ impl Invoker {
         Ok(linker)
     }
 
-    async fn instantiate_from_bytes(
+    async fn instantiate(
         &self,
-        bytes: &[u8],
-        capabilities: &[String],
+        prepared: &Prepared,
+        spec: &ComponentSpec,
         capability_registry: &CapabilityRegistry,
+        contexts: &CapabilityContexts,
         env_vars: &[(String, String)],
     ) -> Result<ComponentInstance> {
-        let component_bytes = bytes.to_vec();
-        let linker = self.create_linker(capabilities, capability_registry)?;
-
+        let capabilities = &spec.capabilities;
         // Build WASI context based on capabilities
         let mut wasi_builder = WasiCtxBuilder::new();
-
-        if !env_vars.is_empty() {
-            wasi_builder.envs(env_vars);
-        }
+        let mut envs = BTreeMap::new();
 
         for capability_name in capabilities {
             if let Some(capability) = capability_registry.get_capability(capability_name) {
@@ -485,6 +1037,9 @@ impl Invoker {
                         add_preopens(&mut wasi_builder, props, capability_name)?;
                     }
                     "wasi:cli" => {
+                        if let Some(env) = contexts.cli_envs.get(capability_name) {
+                            envs.extend(env.iter().cloned());
+                        }
                         if props.get("inherit-stdio").and_then(|v| v.as_bool()) == Some(true) {
                             wasi_builder.inherit_stdio();
                         } else {
@@ -510,23 +1065,56 @@ impl Invoker {
                         {
                             wasi_builder.allow_ip_name_lookup(true);
                         }
+                        if let Some(policy) = contexts.socket_policies.get(capability_name) {
+                            let policy = Arc::clone(policy);
+                            let component = spec.name.clone();
+                            let capability_name = capability_name.clone();
+                            wasi_builder.socket_addr_check(move |addr, addr_use| {
+                                let allowed = policy.allows(addr, addr_use);
+                                if !allowed {
+                                    tracing::warn!(
+                                        "Component '{component}': capability '{capability_name}' denied {addr_use:?} for {addr}"
+                                    );
+                                }
+                                Box::pin(std::future::ready(allowed))
+                            });
+                        }
                     }
                     _ => {}
                 }
             }
         }
+        // Per-call env vars override those of the capabilities.
+        envs.extend(env_vars.iter().cloned());
+        if !envs.is_empty() {
+            wasi_builder.envs(&envs.into_iter().collect::<Vec<_>>());
+        }
 
         // Find the wasi:http capability (if any). Its properties configure the
         // HTTP context and hooks. Matches any version; WasiHttpCtx is shared.
-        let http_capability = capabilities.iter().find_map(|capability_name| {
-            capability_registry
-                .get_capability(capability_name)
-                .filter(|cap| split_wasi_kind(&cap.kind).0 == "wasi:http")
-        });
+        let http_capability = capabilities
+            .iter()
+            .find_map(|capability_name| contexts.http_hooks.get(capability_name));
         let needs_http = http_capability.is_some();
-        let http_hooks = http_capability
-            .map(|cap| HttpHooks::from_properties(&cap.properties))
-            .unwrap_or_default();
+        let http_hooks = http_capability.cloned();
+        let socket_overrides = capabilities
+            .iter()
+            .find_map(|capability_name| contexts.socket_overrides.get(capability_name))
+            .cloned();
+
+        // The store of the first wasi:keyvalue capability backs all of its
+        // interfaces.
+        let keyvalue = capabilities
+            .iter()
+            .find_map(|capability_name| contexts.keyvalue.get(capability_name))
+            .cloned();
+        let config = capabilities
+            .iter()
+            .find_map(|capability_name| contexts.config_sources.get(capability_name))
+            .map(|source| DynamicConfig {
+                table: Arc::clone(&spec.config),
+                source: Arc::clone(source),
+            });
 
         // Collect capability states before creating ComponentState
         let mut extensions = HashMap::new();
@@ -548,6 +1136,7 @@ impl Invoker {
         }
 
         let state = ComponentState {
+            component: spec.name.clone(),
             wasi_ctx: wasi_builder.build(),
             wasi_http_ctx: if needs_http {
                 Some(WasiHttpCtx::new())
@@ -556,42 +1145,43 @@ impl Invoker {
             },
             resource_table: ResourceTable::new(),
             http_hooks,
+            socket_overrides,
+            limiter: ComponentLimiter::new(&spec.name, &spec.limits, prepared.counts),
+            keyvalue,
+            config,
             extensions,
         };
 
         let mut store = Store::new(&self.engine, state);
-        let component = WasmComponent::from_binary(&self.engine, &component_bytes)?;
-        let instance = linker.instantiate_async(&mut store, &component).await?;
+        if spec.limits.has_resource_limits() {
+            store.limiter(|state| &mut state.limiter);
+        }
+        // Instantiation (start functions included) runs under the same limits
+        // as a single call.
+        reset_execution_limits(&mut store, &spec.limits)?;
+        store.data().limiter.admit()?;
+        let instance = prepared.instance_pre.instantiate_async(&mut store).await?;
 
-        Ok(ComponentInstance::new(store, instance))
+        Ok(ComponentInstance::new(
+            store,
+            instance,
+            &spec.name,
+            &spec.limits,
+        ))
     }
+}
 
-    /// Single-use invocation: instantiate, call, drop.
-    pub async fn invoke(
-        &self,
-        bytes: &[u8],
-        capabilities: &[String],
-        capability_registry: &CapabilityRegistry,
-        function: Function,
-        args: Vec<serde_json::Value>,
-        env_vars: &[(String, String)],
-    ) -> Result<serde_json::Value> {
-        let mut instance = self
-            .instantiate_from_bytes(bytes, capabilities, capability_registry, env_vars)
-            .await?;
-
-        let args = args.into_iter().map(Val::Json).collect();
-        let results = instance.call(&function, args).await?;
-
-        match results {
-            None => Ok(serde_json::Value::Null),
-            Some(Val::Json(value)) => Ok(value),
-            Some(Val::Resource(_)) => Err(anyhow::anyhow!(
-                "function '{}' returned a resource, which has no JSON value representation; \
-                 instantiate the component and use `ComponentInstance::call` instead",
-                function.function_name()
-            )),
-        }
+// Convert the results of an invocation to JSON. Resources cannot outlive
+// the call's instance, so they are rejected.
+fn results_to_json(results: Option<Val>, function: &Function) -> Result<serde_json::Value> {
+    match results {
+        None => Ok(serde_json::Value::Null),
+        Some(Val::Json(value)) => Ok(value),
+        Some(Val::Resource(_)) => Err(anyhow::anyhow!(
+            "function '{}' returned a resource, which has no JSON value representation; \
+             instantiate the component and use `ComponentInstance::call` instead",
+            function.function_name()
+        )),
     }
 }
 
@@ -651,3 +1241,125 @@ fn add_preopens(
     }
     Ok(())
 }
+
+// The environment variables of a `wasi:cli` capability:
+//
+//     [capability.cli]
+//     type = "wasi:cli"
+//     env = { LOG_FORMAT = "json" }
+//     inherit-env = ["HOME", "AWS_*"]
+//
+// `inherit-env` passes through the host variables it names, where `*`
+// matches any run of characters, as they are when the runtime is built.
+// `env` takes precedence over them.
+fn cli_env(
+    props: &HashMap<String, serde_json::Value>,
+    capability_name: &str,
+) -> Result<Vec<(String, String)>> {
+    let mut envs = BTreeMap::new();
+    if let Some(value) = props.get("inherit-env") {
+        let patterns = value
+            .as_array()
+            .and_then(|patterns| {
+                patterns
+                    .iter()
+                    .map(|p| p.as_str())
+                    .collect::<Option<Vec<_>>>()
+            })
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "Capability '{capability_name}': 'inherit-env' must be an array of strings"
+                )
+            })?;
+        envs.extend(std::env::vars().filter(|(name, _)| {
+            patterns
+                .iter()
+                .any(|pattern| env_pattern_matches(pattern, name))
+        }));
+    }
+    if let Some(value) = props.get("env") {
+        let table = value.as_object().ok_or_else(|| {
+            anyhow::anyhow!("Capability '{capability_name}': 'env' must be a table of strings")
+        })?;
+        for (name, value) in table {
+            let value = value.as_str().ok_or_else(|| {
+                anyhow::anyhow!("Capability '{capability_name}': env var '{name}' must be a string")
+            })?;
+            envs.insert(name.clone(), value.to_string());
+        }
+    }
+    Ok(envs.into_iter().collect())
+}
+
+// Match an env var name against an `inherit-env` pattern, where `*` matches
+// any run of characters.
+fn env_pattern_matches(pattern: &str, name: &str) -> bool {
+    let Some((prefix, rest)) = pattern.split_once('*') else {
+        return pattern == name;
+    };
+    let Some(mut name) = name.strip_prefix(prefix) else {
+        return false;
+    };
+    let mut parts = rest.split('*').peekable();
+    while let Some(part) = parts.next() {
+        if parts.peek().is_none() {
+            return name.len() >= part.len() && name.ends_with(part);
+        }
+        match name.find(part) {
+            Some(index) => name = &name[index + part.len()..],
+            None => return false,
+        }
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use http_body_util::BodyExt;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    #[tokio::test]
+    async fn p3_guest_zero_between_bytes_timeout_is_clamped() {
+        // Sends the response head, then stalls before the body.
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut head = Vec::new();
+            while !head.ends_with(b"\r\n\r\n") {
+                head.push(stream.read_u8().await.unwrap());
+            }
+            let response = "HTTP/1.1 200 OK\r\ncontent-length: 2\r\n\r\n";
+            stream.write_all(response.as_bytes()).await.unwrap();
+            tokio::time::sleep(Duration::from_secs(5)).await;
+            let _ = stream.write_all(b"ok").await;
+        });
+
+        let mut hooks = HttpHooks::from_properties("http", &HashMap::new()).unwrap();
+        let request = http::Request::get(format!("http://{addr}/"))
+            .body(
+                http_body_util::Empty::new()
+                    .map_err(|never| match never {})
+                    .boxed_unsync(),
+            )
+            .unwrap();
+        let options = http_p3::RequestOptions {
+            between_bytes_timeout: Some(Duration::ZERO),
+            ..Default::default()
+        };
+        let send = http_p3::WasiHttpHooks::send_request(
+            &mut hooks,
+            request,
+            Some(options),
+            Box::new(async { Ok(()) }),
+        );
+        let (response, _io) = Box::into_pin(send).await.unwrap();
+        assert_eq!(response.status(), 200);
+        let err = response.into_body().collect().await.unwrap_err();
+        assert!(
+            matches!(err, P3ErrorCode::ConnectionReadTimeout),
+            "unexpected error: {err:?}"
+        );
+    }
+}
@@ -0,0 +1,362 @@
+//! Host overrides of `wasi:http` and `wasi:sockets` capabilities.
+//!
+//! ```toml
+//! [capability.http]
+//! type = "wasi:http"
+//! host-overrides = { "api.partner.com:443" = "127.0.0.1:8443", "auth.partner.com" = "localhost" }
+//! preserve-host = true        # the default
+//!
+//! [capability.net]
+//! type = "wasi:sockets"
+//! allow-ip-name-lookup = true
+//! host-overrides = { "db.partner.com" = "127.0.0.1" }
+//! ```
+//!
+//! A key with a port matches only that port, and takes precedence over a
+//! key without one, which matches any port. A value without a port keeps the
+//! original one.
+//!
+//! For `wasi:http`, only the connection is redirected while `preserve-host`
+//! is set: the request keeps its `Host` header, and TLS sends and verifies
+//! the original name. Otherwise the request's authority is rewritten, so both
+//! name the override. Proxies and `denied-cidrs` apply to the override.
+//!
+//! For `wasi:sockets`, names are rewritten as they are resolved. A guest
+//! picks the port after resolving, so ports cannot be overridden there.
+
+use anyhow::Result;
+use std::collections::HashMap;
+use std::sync::Arc;
+use wasmtime::component::{Accessor, HasData, Linker, Resource};
+use wasmtime_wasi::sockets::{WasiSockets, WasiSocketsCtxView, WasiSocketsView};
+
+use crate::types::ComponentState;
+
+#[derive(Debug, Default)]
+pub(crate) struct HostOverrides {
+    // By lowercase host, then by port; `None` matches any port.
+    entries: HashMap<String, HashMap<Option<u16>, Target>>,
+    /// Keep the original `Host` header and TLS server name.
+    pub(crate) preserve_host: bool,
+}
+
+// One side of an entry: a lowercase host, without IPv6 brackets, and its
+// port, if given.
+#[derive(Debug)]
+struct Target {
+    host: String,
+    port: Option<u16>,
+}
+
+impl HostOverrides {
+    /// The capability's overrides, if it sets `host-overrides`. Ports are
+    /// rejected unless `ports` is set.
+    pub(crate) fn from_properties(
+        capability_name: &str,
+        props: &HashMap<String, serde_json::Value>,
+        ports: bool,
+    ) -> Result<Option<Self>> {
+        let table = match props.get("host-overrides") {
+            None => return Ok(None),
+            Some(serde_json::Value::Object(table)) => table,
+            Some(_) => anyhow::bail!(
+                "Capability '{capability_name}': 'host-overrides' must be a table of strings"
+            ),
+        };
+        let preserve_host = match props.get("preserve-host") {
+            None => true,
+            Some(serde_json::Value::Bool(preserve_host)) => *preserve_host,
+            Some(_) => {
+                anyhow::bail!("Capability '{capability_name}': 'preserve-host' must be a boolean")
+            }
+        };
+
+        let parse = |authority: &str| {
+            let target = parse_authority(authority).ok_or_else(|| {
+                anyhow::anyhow!(
+                    "Capability '{capability_name}': invalid 'host-overrides' entry '{authority}'"
+                )
+            })?;
+            if target.port.is_some() && !ports {
+                anyhow::bail!(
+                    "Capability '{capability_name}': 'host-overrides' entry '{authority}' cannot \
+                     have a port, since the guest chooses the port after resolving"
+                );
+            }
+            Ok(target)
+        };
+        let mut entries: HashMap<String, HashMap<_, _>> = HashMap::new();
+        for (from, to) in table {
+            let to = to.as_str().ok_or_else(|| {
+                anyhow::anyhow!(
+                    "Capability '{capability_name}': 'host-overrides' must be a table of strings"
+                )
+            })?;
+            let Target { host, port } = parse(from)?;
+            entries.entry(host).or_default().insert(port, parse(to)?);
+        }
+        Ok(Some(Self {
+            entries,
+            preserve_host,
+        }))
+    }
+
+    /// Where a connection to `host:port` goes instead, if anywhere.
+    pub(crate) fn apply(&self, host: &str, port: u16) -> Option<(&str, u16)> {
+        let ports = self.entries.get(&host.to_ascii_lowercase())?;
+        let to = ports.get(&Some(port)).or_else(|| ports.get(&None))?;
+        Some((&to.host, to.port.unwrap_or(port)))
+    }
+
+    /// `uri` with its authority overridden, if it is.
+    pub(crate) fn apply_to_uri(&self, uri: &http::Uri) -> Option<http::Uri> {
+        let host = uri.host()?;
+        let host = host
+            .strip_prefix('[')
+            .and_then(|h| h.strip_suffix(']'))
+            .unwrap_or(host);
+        let default_port = if uri.scheme_str() == Some("https") {
+            443
+        } else {
+            80
+        };
+        let (to_host, to_port) = self.apply(host, uri.port_u16().unwrap_or(default_port))?;
+        let authority = if to_host.contains(':') {
+            format!("[{to_host}]:{to_port}")
+        } else {
+            format!("{to_host}:{to_port}")
+        };
+        let mut parts = uri.clone().into_parts();
+        parts.authority = Some(authority.parse().ok()?);
+        http::Uri::from_parts(parts).ok()
+    }
+
+    // The name a lookup of `name` resolves instead.
+    fn apply_to_name(&self, name: &str) -> Option<&str> {
+        self.apply(name, 0).map(|(host, _)| host)
+    }
+}
+
+// Split `host[:port]`, removing IPv6 brackets and lowercasing the host.
+fn parse_authority(authority: &str) -> Option<Target> {
+    let parsed: http::uri::Authority = authority.parse().ok()?;
+    if parsed.as_str().contains('@') {
+        return None;
+    }
+    let host = parsed.host();
+    let host = host
+        .strip_prefix('[')
+        .and_then(|h| h.strip_suffix(']'))
+        .unwrap_or(host);
+    if host.is_empty() {
+        return None;
+    }
+    Some(Target {
+        host: host.to_ascii_lowercase(),
+        port: parsed.port_u16(),
+    })
+}
+
+/// Link `wasi:sockets/ip-name-lookup` for p2 components, resolving overridden
+/// names. Shadows the interface linked by `wasi:sockets`.
+pub(crate) fn add_to_linker_p2(linker: &mut Linker<ComponentState>) -> Result<()> {
+    wasmtime_wasi::p2::bindings::sockets::ip_name_lookup::add_to_linker::<_, HasLookup>(
+        linker, lookup,
+    )?;
+    Ok(())
+}
+
+/// Link `wasi:sockets/ip-name-lookup` for p3 components, resolving overridden
+/// names. Shadows the interface linked by `wasi:sockets`.
+pub(crate) fn add_to_linker_p3(linker: &mut Linker<ComponentState>) -> Result<()> {
+    wasmtime_wasi::p3::bindings::sockets::ip_name_lookup::add_to_linker::<_, HasLookup>(
+        linker, lookup,
+    )?;
+    Ok(())
+}
+
+fn lookup(state: &mut ComponentState) -> LookupView<'_> {
+    LookupView {
+        overrides: state.socket_overrides.clone(),
+        sockets: state.sockets(),
+    }
+}
+
+struct HasLookup;
+
+impl HasData for HasLookup {
+    type Data<'a> = LookupView<'a>;
+}
+
+struct LookupView<'a> {
+    overrides: Option<Arc<HostOverrides>>,
+    sockets: WasiSocketsCtxView<'a>,
+}
+
+impl LookupView<'_> {
+    fn rewrite(&self, name: String) -> String {
+        match self
+            .overrides
+            .as_deref()
+            .and_then(|overrides| overrides.apply_to_name(&name))
+        {
+            Some(host) => host.to_string(),
+            None => name,
+        }
+    }
+}
+
+mod p2 {
+    use super::*;
+    use wasmtime_wasi::p2::bindings::sockets::ip_name_lookup::{
+        Host, HostResolveAddressStream, ResolveAddressStream,
+    };
+    use wasmtime_wasi::p2::bindings::sockets::network::{self, ErrorCode, IpAddress, Network};
+    use wasmtime_wasi::p2::{DynPollable, SocketError};
+    use wasmtime_wasi_io::streams::Error as StreamError;
+
+    // Required by the `ip-name-lookup` bindings, which share its error type.
+    impl network::Host for LookupView<'_> {
+        fn convert_error_code(&mut self, error: SocketError) -> wasmtime::Result<ErrorCode> {
+            self.sockets.convert_error_code(error)
+        }
+
+        fn network_error_code(
+            &mut self,
+            err: Resource<StreamError>,
+        ) -> wasmtime::Result<Option<ErrorCode>> {
+            self.sockets.network_error_code(err)
+        }
+    }
+
+    impl network::HostNetwork for LookupView<'_> {
+        fn drop(&mut self, network: Resource<Network>) -> wasmtime::Result<()> {
+            network::HostNetwork::drop(&mut self.sockets, network)
+        }
+    }
+
+    impl Host for LookupView<'_> {
+        fn resolve_addresses(
+            &mut self,
+            network: Resource<Network>,
+            name: String,
+        ) -> Result<Resource<ResolveAddressStream>, SocketError> {
+            let name = self.rewrite(name);
+            self.sockets.resolve_addresses(network, name)
+        }
+    }
+
+    impl HostResolveAddressStream for LookupView<'_> {
+        fn resolve_next_address(
+            &mut self,
+            resource: Resource<ResolveAddressStream>,
+        ) -> Result<Option<IpAddress>, SocketError> {
+            self.sockets.resolve_next_address(resource)
+        }
+
+        fn subscribe(
+            &mut self,
+            resource: Resource<ResolveAddressStream>,
+        ) -> wasmtime::Result<Resource<DynPollable>> {
+            self.sockets.subscribe(resource)
+        }
+
+        fn drop(&mut self, resource: Resource<ResolveAddressStream>) -> wasmtime::Result<()> {
+            HostResolveAddressStream::drop(&mut self.sockets, resource)
+        }
+    }
+}
+
+mod p3 {
+    use super::*;
+    use wasmtime_wasi::p3::bindings::sockets::ip_name_lookup::{ErrorCode, Host, HostWithStore};
+    use wasmtime_wasi::p3::bindings::sockets::types::IpAddress;
+
+    impl HostWithStore<ComponentState> for HasLookup {
+        async fn resolve_addresses(
+            store: &Accessor<ComponentState, Self>,
+            name: String,
+        ) -> wasmtime::Result<Result<Vec<IpAddress>, ErrorCode>> {
+            let name = store.with(|mut view| view.get().rewrite(name));
+            let store = store.with_getter::<WasiSockets>(ComponentState::sockets);
+            <WasiSockets as HostWithStore<ComponentState>>::resolve_addresses(&store, name).await
+        }
+    }
+
+    impl Host for LookupView<'_> {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn overrides(props: serde_json::Value, ports: bool) -> Result<Option<HostOverrides>> {
+        let props = serde_json::from_value(props).unwrap();
+        HostOverrides::from_properties("net", &props, ports)
+    }
+
+    #[test]
+    fn applies_most_specific_override() {
+        let overrides = overrides(
+            serde_json::json!({"host-overrides": {
+                "api.partner.com:443": "127.0.0.1:8443",
+                "API.partner.com": "localhost",
+                "[::1]:80": "[::2]"
+            }}),
+            true,
+        )
+        .unwrap()
+        .unwrap();
+        assert!(overrides.preserve_host);
+        assert_eq!(
+            overrides.apply("api.partner.com", 443),
+            Some(("127.0.0.1", 8443))
+        );
+        assert_eq!(
+            overrides.apply("api.partner.com", 80),
+            Some(("localhost", 80))
+        );
+        assert_eq!(overrides.apply("::1", 80), Some(("::2", 80)));
+        assert_eq!(overrides.apply("other.partner.com", 443), None);
+
+        let uri = "https://api.partner.com/v1?q=1".parse().unwrap();
+        assert_eq!(
+            overrides.apply_to_uri(&uri).unwrap(),
+            "https://127.0.0.1:8443/v1?q=1"
+        );
+        let uri = "http://[::1]/".parse().unwrap();
+        assert_eq!(overrides.apply_to_uri(&uri).unwrap(), "http://[::2]:80/");
+    }
+
+    #[test]
+    fn rejects_invalid_properties() {
+        let err = |props, ports| overrides(props, ports).unwrap_err().to_string();
+        assert!(
+            err(serde_json::json!({"host-overrides": ["a"]}), true)
+                .contains("must be a table of strings")
+        );
+        assert!(
+            err(serde_json::json!({"host-overrides": {"a": 1}}), true)
+                .contains("must be a table of strings")
+        );
+        assert!(
+            err(serde_json::json!({"host-overrides": {"a/b": "c"}}), true)
+                .contains("invalid 'host-overrides' entry 'a/b'")
+        );
+        assert!(
+            err(
+                serde_json::json!({"host-overrides": {"db:5432": "c"}}),
+                false
+            )
+            .contains("cannot have a port")
+        );
+        assert!(
+            err(
+                serde_json::json!({"host-overrides": {}, "preserve-host": "yes"}),
+                true
+            )
+            .contains("'preserve-host' must be a boolean")
+        );
+        assert!(overrides(serde_json::json!({}), true).unwrap().is_none());
+    }
+}
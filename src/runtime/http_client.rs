@@ -0,0 +1,1068 @@
+//! Outbound HTTP client of a `wasi:http` capability.
+//!
+//! ```toml
+//! [capability.http]
+//! type = "wasi:http"
+//! connect-timeout-ms = 5000
+//! first-byte-timeout-ms = 10000
+//! between-bytes-timeout-ms = 10000
+//! retries = 2                 # for idempotent methods only
+//! retry-backoff-ms = 100      # doubled after each attempt
+//! pool-max-idle-per-host = 16
+//! pool-idle-timeout-ms = 90000
+//! ```
+//!
+//! Each capability keeps one pool of keep-alive connections, shared by every
+//! component that imports it. Timeouts default to wasmtime's 10 minutes. A
+//! guest's request options can shorten the first-byte and between-bytes
+//! timeouts, but not lengthen them. A request is retried when it could not be
+//! sent or got no response in time, and the body of a request that may be
+//! retried is buffered so it can be sent again.
+//!
+//! With `h2c-for-grpc`, `application/grpc` requests use a separate HTTP/2
+//! pool: cleartext (h2c, prior knowledge) for `http`, and TLS negotiating
+//! `h2` via ALPN for `https`.
+//!
+//! Host names are resolved once per connection, and the connection goes to
+//! exactly the resolved addresses, after checking each against the policy's
+//! `denied-cidrs` (see `runtime::http_policy`). A name cannot resolve to an
+//! allowed address for the check and a denied one for the connection.
+//! Names sent through a proxy are not resolved locally: the proxy resolves
+//! them, so only targets given as addresses are checked against the policy.
+//!
+//! TLS trusts the capability's `ca-bundle` and presents its `client-cert`, if
+//! set (see `runtime::tls`). Connections go through the capability's `proxy`,
+//! if set (see `runtime::http_proxy`), and to its `host-overrides`, if set
+//! (see `runtime::host_overrides`).
+
+use anyhow::Result;
+use bytes::Bytes;
+use http_body_util::combinators::UnsyncBoxBody;
+use http_body_util::{BodyExt, Full};
+use hyper_util::client::legacy::Client;
+use hyper_util::client::legacy::connect::{Connected, Connection};
+use hyper_util::rt::{TokioExecutor, TokioIo, TokioTimer};
+use std::collections::HashMap;
+use std::error::Error as StdError;
+use std::future::Future;
+use std::net::{IpAddr, SocketAddr};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tokio_rustls::TlsConnector;
+
+use crate::runtime::host_overrides::HostOverrides;
+use crate::runtime::http_policy::HttpPolicy;
+use crate::runtime::http_proxy::Proxy;
+
+pub(crate) type BoxError = Box<dyn StdError + Send + Sync>;
+
+/// The request body sent by [`HttpClient`]. Its error is the guest's
+/// `ErrorCode`, boxed so that p2 and p3 components share one pool.
+pub(crate) type ClientBody = UnsyncBoxBody<Bytes, BoxError>;
+
+// Mirror the defaults of `default_send_request`.
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(600);
+const DEFAULT_RETRY_BACKOFF: Duration = Duration::from_millis(100);
+// Bounds of the connect, first-byte and between-bytes timeouts, whether
+// configured or asked for by the guest.
+pub(crate) const MIN_TIMEOUT: Duration = Duration::from_millis(1);
+const MAX_TIMEOUT: Duration = Duration::from_secs(24 * 60 * 60);
+
+tokio::task_local! {
+    // The connect timeout the guest asked for, read by `Connector::call`,
+    // which only sees the uri. Hyper calls the connector from the task
+    // sending the request.
+    static CONNECT_TIMEOUT: Duration;
+}
+
+#[derive(Clone)]
+pub(crate) struct HttpClient {
+    http: Client<Connector, ClientBody>,
+    grpc: Option<Client<Connector, ClientBody>>,
+    proxy: Option<Arc<Proxy>>,
+    overrides: Option<Arc<HostOverrides>>,
+    pub(crate) first_byte_timeout: Duration,
+    pub(crate) between_bytes_timeout: Duration,
+    retries: u32,
+    retry_backoff: Duration,
+}
+
+impl HttpClient {
+    pub(crate) fn from_properties(
+        capability_name: &str,
+        props: &HashMap<String, serde_json::Value>,
+        policy: Arc<HttpPolicy>,
+    ) -> Result<Self> {
+        let number = |key: &str| match props.get(key) {
+            None => Ok(None),
+            Some(value) => value.as_u64().map(Some).ok_or_else(|| {
+                anyhow::anyhow!(
+                    "Capability '{capability_name}': '{key}' must be a non-negative integer"
+                )
+            }),
+        };
+        let millis = |key: &str| Ok::<_, anyhow::Error>(number(key)?.map(Duration::from_millis));
+        let timeout = |key: &str| match number(key)? {
+            Some(0) => Err(anyhow::anyhow!(
+                "Capability '{capability_name}': '{key}' must be positive"
+            )),
+            ms => Ok(ms.map(|ms| Duration::from_millis(ms).min(MAX_TIMEOUT))),
+        };
+
+        let connect_timeout = timeout("connect-timeout-ms")?.unwrap_or(DEFAULT_TIMEOUT);
+        let mut builder = Client::builder(TokioExecutor::new());
+        builder.pool_timer(TokioTimer::new());
+        if let Some(max_idle) = number("pool-max-idle-per-host")? {
+            builder.pool_max_idle_per_host(max_idle as usize);
+        }
+        if let Some(idle_timeout) = millis("pool-idle-timeout-ms")? {
+            builder.pool_idle_timeout(idle_timeout);
+        }
+
+        let proxy = Proxy::from_properties(capability_name, props)?.map(Arc::new);
+        let overrides = HostOverrides::from_properties(capability_name, props, true)?.map(Arc::new);
+        // Without `preserve-host`, `send` rewrites the request instead.
+        let connect_overrides = overrides
+            .clone()
+            .filter(|overrides| overrides.preserve_host);
+        let tls = crate::runtime::tls::client_config(capability_name, props, Vec::new())?;
+        let http = builder.build(Connector {
+            tls: TlsConnector::from(Arc::new(tls)),
+            connect_timeout,
+            h2: false,
+            proxy: proxy.clone(),
+            overrides: connect_overrides.clone(),
+            policy: Arc::clone(&policy),
+        });
+        let grpc = if props.get("h2c-for-grpc").and_then(|v| v.as_bool()) == Some(true) {
+            let tls =
+                crate::runtime::tls::client_config(capability_name, props, vec![b"h2".to_vec()])?;
+            Some(builder.http2_only(true).build(Connector {
+                tls: TlsConnector::from(Arc::new(tls)),
+                connect_timeout,
+                h2: true,
+                proxy: proxy.clone(),
+                overrides: connect_overrides,
+                policy,
+            }))
+        } else {
+            None
+        };
+
+        Ok(Self {
+            http,
+            grpc,
+            proxy,
+            overrides,
+            first_byte_timeout: timeout("first-byte-timeout-ms")?.unwrap_or(DEFAULT_TIMEOUT),
+            between_bytes_timeout: timeout("between-bytes-timeout-ms")?.unwrap_or(DEFAULT_TIMEOUT),
+            retries: number("retries")?.unwrap_or(0).try_into().map_err(|_| {
+                anyhow::anyhow!("Capability '{capability_name}': 'retries' is too large")
+            })?,
+            retry_backoff: millis("retry-backoff-ms")?.unwrap_or(DEFAULT_RETRY_BACKOFF),
+        })
+    }
+
+    /// Whether `application/grpc` requests are sent over HTTP/2.
+    pub(crate) fn grpc_enabled(&self) -> bool {
+        self.grpc.is_some()
+    }
+
+    /// Send a request, retrying idempotent ones as configured, and return the
+    /// response once its head has arrived. `E` is the `ErrorCode` the
+    /// request body fails with. The guest's timeouts only shorten the
+    /// capability's.
+    pub(crate) async fn send<E: StdError + Clone + 'static>(
+        &self,
+        mut request: http::Request<ClientBody>,
+        grpc: bool,
+        connect_timeout: Duration,
+        first_byte_timeout: Duration,
+    ) -> Result<http::Response<hyper::body::Incoming>, SendError<E>> {
+        let client = match &self.grpc {
+            Some(grpc_client) if grpc => grpc_client,
+            _ => &self.http,
+        };
+        let connect_timeout = connect_timeout.max(MIN_TIMEOUT);
+        let first_byte_timeout = first_byte_timeout
+            .min(self.first_byte_timeout)
+            .max(MIN_TIMEOUT);
+        if let Some(overrides) = &self.overrides
+            && !overrides.preserve_host
+            && let Some(uri) = overrides.apply_to_uri(request.uri())
+        {
+            if let Some(authority) = uri.authority()
+                && request.headers().contains_key(http::header::HOST)
+                && let Ok(host) = http::HeaderValue::from_str(authority.as_str())
+            {
+                request.headers_mut().insert(http::header::HOST, host);
+            }
+            *request.uri_mut() = uri;
+        }
+        // Forwarded (not tunneled) requests carry the proxy credentials.
+        if let Some(proxy) = &self.proxy
+            && let Some(authorization) = &proxy.authorization
+            && !grpc
+            && request.uri().scheme_str() == Some("http")
+            && request
+                .uri()
+                .host()
+                .is_some_and(|host| proxy.applies_to(host))
+        {
+            request
+                .headers_mut()
+                .insert(http::header::PROXY_AUTHORIZATION, authorization.clone());
+        }
+        if self.retries == 0 || !request.method().is_idempotent() {
+            return send_once(client, request, connect_timeout, first_byte_timeout).await;
+        }
+
+        let (parts, body) = request.into_parts();
+        let body = match body.collect().await {
+            Ok(collected) => collected.to_bytes(),
+            Err(e) => {
+                return Err(match e.downcast::<E>() {
+                    Ok(code) => SendError::Body(*code),
+                    Err(e) => SendError::Protocol(e),
+                });
+            }
+        };
+        let mut backoff = self.retry_backoff;
+        let mut attempt = 0;
+        loop {
+            let mut request = http::Request::new(
+                Full::new(body.clone())
+                    .map_err(|never| match never {})
+                    .boxed_unsync(),
+            );
+            *request.method_mut() = parts.method.clone();
+            *request.uri_mut() = parts.uri.clone();
+            *request.version_mut() = parts.version;
+            *request.headers_mut() = parts.headers.clone();
+            match send_once(client, request, connect_timeout, first_byte_timeout).await {
+                Err(e) if attempt < self.retries && e.is_retryable() => {
+                    tracing::debug!("retrying {} {}: {e}", parts.method, parts.uri);
+                    tokio::time::sleep(backoff).await;
+                    backoff = backoff.saturating_mul(2);
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+async fn send_once<E: StdError + Clone + 'static>(
+    client: &Client<Connector, ClientBody>,
+    request: http::Request<ClientBody>,
+    connect_timeout: Duration,
+    first_byte_timeout: Duration,
+) -> Result<http::Response<hyper::body::Incoming>, SendError<E>> {
+    let response = tokio::time::timeout(first_byte_timeout, client.request(request));
+    match CONNECT_TIMEOUT.scope(connect_timeout, response).await {
+        Err(_) => Err(SendError::FirstByteTimeout),
+        Ok(Ok(response)) => Ok(response),
+        Ok(Err(e)) => Err(SendError::from_client_error(e)),
+    }
+}
+
+/// Why a request got no response. Each `send_request` hook maps it to the
+/// `ErrorCode` of its wasi:http version.
+#[derive(Debug)]
+pub(crate) enum SendError<E> {
+    Connect(ConnectError),
+    FirstByteTimeout,
+    /// The connection closed before the response arrived.
+    Closed,
+    /// The request body failed with this `ErrorCode`.
+    Body(E),
+    Protocol(BoxError),
+}
+
+impl<E: StdError + Clone + 'static> SendError<E> {
+    fn from_client_error(err: hyper_util::client::legacy::Error) -> Self {
+        let mut source = err.source();
+        while let Some(cause) = source {
+            if let Some(connect) = cause.downcast_ref::<ConnectError>() {
+                return Self::Connect(connect.clone());
+            }
+            if let Some(code) = cause.downcast_ref::<E>() {
+                return Self::Body(code.clone());
+            }
+            if let Some(hyper_err) = cause.downcast_ref::<hyper::Error>()
+                && (hyper_err.is_canceled()
+                    || hyper_err.is_closed()
+                    || hyper_err.is_incomplete_message())
+            {
+                return Self::Closed;
+            }
+            source = cause.source();
+        }
+        if err.is_connect() {
+            return Self::Connect(ConnectError::Refused);
+        }
+        Self::Protocol(Box::new(err))
+    }
+
+    fn is_retryable(&self) -> bool {
+        matches!(
+            self,
+            Self::Connect(ConnectError::Timeout | ConnectError::Refused)
+                | Self::FirstByteTimeout
+                | Self::Closed
+        )
+    }
+}
+
+impl<E: std::fmt::Display> std::fmt::Display for SendError<E> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Connect(e) => write!(f, "{e}"),
+            Self::FirstByteTimeout => write!(f, "no response within the first-byte timeout"),
+            Self::Closed => write!(f, "connection closed before the response"),
+            Self::Body(e) => write!(f, "request body failed: {e}"),
+            Self::Protocol(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+/// Why a connection could not be set up.
+#[derive(Debug, Clone)]
+pub(crate) enum ConnectError {
+    InvalidUri,
+    Timeout,
+    Refused,
+    Dns,
+    /// The host resolved to an address in the policy's `denied-cidrs`.
+    Denied,
+    Tls,
+    /// A gRPC server did not negotiate HTTP/2 via ALPN.
+    NoH2,
+}
+
+impl std::fmt::Display for ConnectError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::InvalidUri => "invalid request URI",
+            Self::Timeout => "connect timed out",
+            Self::Refused => "connection refused",
+            Self::Dns => "host name lookup failed",
+            Self::Denied => "address denied by policy",
+            Self::Tls => "TLS handshake failed",
+            Self::NoH2 => "server did not negotiate HTTP/2",
+        })
+    }
+}
+
+impl StdError for ConnectError {}
+
+// Opens the pooled connections: plain TCP for `http`, TLS for `https`,
+// either through the proxy. `connect_timeout` covers all of it.
+#[derive(Clone)]
+struct Connector {
+    tls: TlsConnector,
+    connect_timeout: Duration,
+    // Require `h2` via ALPN over TLS.
+    h2: bool,
+    proxy: Option<Arc<Proxy>>,
+    // Applied to the connection only, with `preserve-host`.
+    overrides: Option<Arc<HostOverrides>>,
+    policy: Arc<HttpPolicy>,
+}
+
+impl tower_service::Service<http::Uri> for Connector {
+    type Response = Stream;
+    type Error = ConnectError;
+    type Future = Pin<Box<dyn Future<Output = Result<Stream, ConnectError>> + Send>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), ConnectError>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, uri: http::Uri) -> Self::Future {
+        let connector = self.clone();
+        let connect_timeout = CONNECT_TIMEOUT
+            .try_with(|timeout| *timeout)
+            .map_or(self.connect_timeout, |timeout| {
+                timeout.min(self.connect_timeout)
+            });
+        Box::pin(async move {
+            tokio::time::timeout(connect_timeout, connector.connect(&uri))
+                .await
+                .map_err(|_| ConnectError::Timeout)?
+        })
+    }
+}
+
+impl Connector {
+    async fn connect(&self, uri: &http::Uri) -> Result<Stream, ConnectError> {
+        let use_tls = uri.scheme_str() == Some("https");
+        let host = uri.host().ok_or(ConnectError::InvalidUri)?;
+        let port = uri.port_u16().unwrap_or(if use_tls { 443 } else { 80 });
+        let host = host
+            .strip_prefix('[')
+            .and_then(|h| h.strip_suffix(']'))
+            .unwrap_or(host);
+        // TLS keeps the original name, the connection goes to the override.
+        let overridden = self
+            .overrides
+            .as_deref()
+            .and_then(|overrides| overrides.apply(host, port));
+        let (target_host, target_port) = overridden.unwrap_or((host, port));
+
+        let stream: Box<dyn AsyncStream> = match self
+            .proxy
+            .as_deref()
+            .filter(|proxy| proxy.applies_to(target_host))
+        {
+            None => {
+                let addrs = self.allowed_addrs(target_host, target_port).await?;
+                Box::new(tcp_connect(&addrs).await?)
+            }
+            Some(proxy) => {
+                // The proxy resolves names, so only an address is checked.
+                if let Ok(ip) = target_host.parse::<IpAddr>()
+                    && self.policy.denies(ip)
+                {
+                    return Err(ConnectError::Denied);
+                }
+                let proxy_addrs = resolve(&proxy.host, proxy.port).await?;
+                let tcp_stream = tcp_connect(&proxy_addrs).await?;
+                let mut stream: Box<dyn AsyncStream> = match &proxy.tls {
+                    Some(tls) => {
+                        Box::new(tls_connect(tls, &proxy.host, proxy.port, tcp_stream).await?)
+                    }
+                    None => Box::new(tcp_stream),
+                };
+                // Plain HTTP/1.1 is forwarded in absolute form; anything
+                // else, or an overridden target, needs a tunnel.
+                if !use_tls && !self.h2 && overridden.is_none() {
+                    return Ok(Stream {
+                        io: TokioIo::new(stream),
+                        h2: false,
+                        proxied: true,
+                    });
+                }
+                proxy
+                    .tunnel(&mut stream, target_host, target_port)
+                    .await
+                    .map_err(|e| {
+                        tracing::warn!("proxy tunnel to {target_host}:{target_port} failed: {e}");
+                        ConnectError::Refused
+                    })?;
+                stream
+            }
+        };
+        if !use_tls {
+            return Ok(Stream {
+                io: TokioIo::new(stream),
+                h2: false,
+                proxied: false,
+            });
+        }
+
+        let tls_stream = tls_connect(&self.tls, host, port, stream).await?;
+        let h2 = tls_stream.get_ref().1.alpn_protocol() == Some(b"h2");
+        if self.h2 && !h2 {
+            tracing::warn!("gRPC server {host}:{port} did not negotiate HTTP/2 via ALPN");
+            return Err(ConnectError::NoH2);
+        }
+        Ok(Stream {
+            io: TokioIo::new(Box::new(tls_stream)),
+            h2,
+            proxied: false,
+        })
+    }
+
+    // Resolve the target and refuse it if any address is denied, so that
+    // what is checked is what is connected to.
+    async fn allowed_addrs(&self, host: &str, port: u16) -> Result<Vec<SocketAddr>, ConnectError> {
+        let addrs = resolve(host, port).await?;
+        if addrs.iter().any(|addr| self.policy.denies(addr.ip())) {
+            tracing::debug!("{host}:{port} resolves to a denied address");
+            return Err(ConnectError::Denied);
+        }
+        Ok(addrs)
+    }
+}
+
+async fn resolve(host: &str, port: u16) -> Result<Vec<SocketAddr>, ConnectError> {
+    if let Ok(ip) = host.parse::<IpAddr>() {
+        return Ok(vec![SocketAddr::new(ip, port)]);
+    }
+    let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host, port))
+        .await
+        .map_err(|e| {
+            tracing::debug!("lookup of {host} failed: {e}");
+            ConnectError::Dns
+        })?
+        .collect();
+    if addrs.is_empty() {
+        return Err(ConnectError::Dns);
+    }
+    Ok(addrs)
+}
+
+// Tries each address in turn, like `TcpStream::connect` does for a name.
+async fn tcp_connect(addrs: &[SocketAddr]) -> Result<tokio::net::TcpStream, ConnectError> {
+    let tcp_stream = tokio::net::TcpStream::connect(addrs)
+        .await
+        .map_err(|_| ConnectError::Refused)?;
+    let _ = tcp_stream.set_nodelay(true);
+    Ok(tcp_stream)
+}
+
+async fn tls_connect<S: AsyncStream>(
+    tls: &TlsConnector,
+    host: &str,
+    port: u16,
+    stream: S,
+) -> Result<tokio_rustls::client::TlsStream<S>, ConnectError> {
+    let server_name = rustls::pki_types::ServerName::try_from(host.to_string())
+        .map_err(|_| ConnectError::InvalidUri)?;
+    tls.connect(server_name, stream).await.map_err(|e| {
+        tracing::warn!("TLS handshake with {host}:{port} failed: {e}");
+        ConnectError::Tls
+    })
+}
+
+trait AsyncStream: tokio::io::AsyncRead + tokio::io::AsyncWrite + Send + Unpin + 'static {}
+
+impl<T> AsyncStream for T where
+    T: tokio::io::AsyncRead + tokio::io::AsyncWrite + Send + Unpin + 'static
+{
+}
+
+// A pooled connection, over TCP or TLS.
+struct Stream {
+    io: TokioIo<Box<dyn AsyncStream>>,
+    h2: bool,
+    // Connected to a forward proxy, so requests use the absolute form.
+    proxied: bool,
+}
+
+impl Connection for Stream {
+    fn connected(&self) -> Connected {
+        let connected = Connected::new().proxy(self.proxied);
+        if self.h2 {
+            connected.negotiated_h2()
+        } else {
+            connected
+        }
+    }
+}
+
+impl hyper::rt::Read for Stream {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: hyper::rt::ReadBufCursor<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.io).poll_read(cx, buf)
+    }
+}
+
+impl hyper::rt::Write for Stream {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        Pin::new(&mut self.io).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.io).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.io).poll_shutdown(cx)
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        self.io.is_write_vectored()
+    }
+
+    fn poll_write_vectored(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[std::io::IoSlice<'_>],
+    ) -> Poll<std::io::Result<usize>> {
+        Pin::new(&mut self.io).poll_write_vectored(cx, bufs)
+    }
+}
+
+/// A response body that fails with `error` when no frame arrives within
+/// `timeout` of the previous one.
+pub(crate) struct TimeoutBody<E> {
+    incoming: hyper::body::Incoming,
+    timeout: Duration,
+    deadline: Pin<Box<tokio::time::Sleep>>,
+    map_err: fn(hyper::Error) -> E,
+    error: E,
+}
+
+impl<E> TimeoutBody<E> {
+    pub(crate) fn new(
+        incoming: hyper::body::Incoming,
+        timeout: Duration,
+        map_err: fn(hyper::Error) -> E,
+        error: E,
+    ) -> Self {
+        let timeout = timeout.max(MIN_TIMEOUT);
+        Self {
+            incoming,
+            timeout,
+            deadline: Box::pin(tokio::time::sleep_until(deadline(timeout))),
+            map_err,
+            error,
+        }
+    }
+}
+
+// `timeout` from now, or as good as never if that is not representable.
+fn deadline(timeout: Duration) -> tokio::time::Instant {
+    let now = tokio::time::Instant::now();
+    now.checked_add(timeout)
+        .unwrap_or_else(|| now + MAX_TIMEOUT)
+}
+
+impl<E: Clone + Unpin> http_body::Body for TimeoutBody<E> {
+    type Data = Bytes;
+    type Error = E;
+
+    fn poll_frame(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<http_body::Frame<Bytes>, E>>> {
+        match Pin::new(&mut self.incoming).poll_frame(cx) {
+            Poll::Ready(None) => Poll::Ready(None),
+            Poll::Ready(Some(Err(e))) => Poll::Ready(Some(Err((self.map_err)(e)))),
+            Poll::Ready(Some(Ok(frame))) => {
+                let deadline = deadline(self.timeout);
+                self.deadline.as_mut().reset(deadline);
+                Poll::Ready(Some(Ok(frame)))
+            }
+            Poll::Pending => {
+                std::task::ready!(self.deadline.as_mut().poll(cx));
+                Poll::Ready(Some(Err(self.error.clone())))
+            }
+        }
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.incoming.is_end_stream()
+    }
+
+    fn size_hint(&self) -> http_body::SizeHint {
+        self.incoming.size_hint()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustls::pki_types::PrivateKeyDer;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use wasmtime_wasi_http::p3::bindings::http::types::ErrorCode;
+
+    fn client(props: serde_json::Value) -> HttpClient {
+        let props = serde_json::from_value(props).unwrap();
+        let policy = Arc::new(HttpPolicy::from_properties("http", &props).unwrap());
+        HttpClient::from_properties("http", &props, policy).unwrap()
+    }
+
+    fn get(uri: &str) -> http::Request<ClientBody> {
+        http::Request::get(uri)
+            .body(
+                Full::new(Bytes::new())
+                    .map_err(|never| match never {})
+                    .boxed_unsync(),
+            )
+            .unwrap()
+    }
+
+    // Serves `200 OK` over keep-alive connections, dropping the first
+    // `drop_first` connections without a response. Returns its address and
+    // the number of connections accepted.
+    async fn server(drop_first: usize) -> (std::net::SocketAddr, Arc<AtomicUsize>) {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let accepted = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&accepted);
+        tokio::spawn(async move {
+            loop {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                if counter.fetch_add(1, Ordering::SeqCst) < drop_first {
+                    continue;
+                }
+                tokio::spawn(async move {
+                    let mut buf = [0; 4096];
+                    while let Ok(n) = stream.read(&mut buf).await {
+                        if n == 0 {
+                            break;
+                        }
+                        let response = "HTTP/1.1 200 OK\r\ncontent-length: 2\r\n\r\nok";
+                        if stream.write_all(response.as_bytes()).await.is_err() {
+                            break;
+                        }
+                    }
+                });
+            }
+        });
+        (addr, accepted)
+    }
+
+    #[tokio::test]
+    async fn reuses_pooled_connections() {
+        let (addr, accepted) = server(0).await;
+        let client = client(serde_json::json!({}));
+        for _ in 0..3 {
+            let response = client
+                .send::<ErrorCode>(
+                    get(&format!("http://{addr}/")),
+                    false,
+                    DEFAULT_TIMEOUT,
+                    DEFAULT_TIMEOUT,
+                )
+                .await
+                .unwrap();
+            assert_eq!(response.status(), 200);
+            response.into_body().collect().await.unwrap();
+        }
+        assert_eq!(accepted.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn retries_idempotent_requests() {
+        let (addr, accepted) = server(2).await;
+        let client = client(serde_json::json!({"retries": 2, "retry-backoff-ms": 1}));
+        let response = client
+            .send::<ErrorCode>(
+                get(&format!("http://{addr}/")),
+                false,
+                DEFAULT_TIMEOUT,
+                DEFAULT_TIMEOUT,
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 200);
+        assert_eq!(accepted.load(Ordering::SeqCst), 3);
+
+        let (addr, accepted) = server(1).await;
+        let mut post = get(&format!("http://{addr}/"));
+        *post.method_mut() = http::Method::POST;
+        let err = client
+            .send::<ErrorCode>(post, false, DEFAULT_TIMEOUT, DEFAULT_TIMEOUT)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, SendError::Closed), "unexpected error: {err}");
+        assert_eq!(accepted.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn forwards_http_requests_through_proxy() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let proxy = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut head = Vec::new();
+            while !head.ends_with(b"\r\n\r\n") {
+                head.push(stream.read_u8().await.unwrap());
+            }
+            let response = "HTTP/1.1 200 OK\r\ncontent-length: 2\r\n\r\nok";
+            stream.write_all(response.as_bytes()).await.unwrap();
+            String::from_utf8(head).unwrap()
+        });
+
+        let client = client(serde_json::json!({
+            "proxy": format!("http://user:secret@{addr}"),
+            "no-proxy": ["localhost"]
+        }));
+        let response = client
+            .send::<ErrorCode>(
+                get("http://api.example.com/v1"),
+                false,
+                DEFAULT_TIMEOUT,
+                DEFAULT_TIMEOUT,
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 200);
+        let request = proxy.await.unwrap();
+        assert!(
+            request.starts_with("GET http://api.example.com/v1 HTTP/1.1\r\n"),
+            "unexpected request: {request}"
+        );
+        assert!(request.contains("proxy-authorization: Basic dXNlcjpzZWNyZXQ=\r\n"));
+    }
+
+    #[tokio::test]
+    async fn leaves_resolving_proxied_names_to_the_proxy() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let proxy = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut head = Vec::new();
+            while !head.ends_with(b"\r\n\r\n") {
+                head.push(stream.read_u8().await.unwrap());
+            }
+            let response = "HTTP/1.1 200 OK\r\ncontent-length: 2\r\n\r\nok";
+            stream.write_all(response.as_bytes()).await.unwrap();
+        });
+
+        let client = client(serde_json::json!({
+            "proxy": format!("http://{addr}"),
+            "denied-cidrs": ["10.0.0.0/8"]
+        }));
+        // Unresolvable here, but the proxy can resolve it.
+        let response = client
+            .send::<ErrorCode>(
+                get("http://api.example.com/v1"),
+                false,
+                DEFAULT_TIMEOUT,
+                DEFAULT_TIMEOUT,
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 200);
+        proxy.await.unwrap();
+
+        let err = client
+            .send::<ErrorCode>(
+                get("http://10.1.2.3/v1"),
+                false,
+                DEFAULT_TIMEOUT,
+                DEFAULT_TIMEOUT,
+            )
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(err, SendError::Connect(ConnectError::Denied)),
+            "unexpected error: {err}"
+        );
+    }
+
+    #[tokio::test]
+    async fn connects_to_host_overrides() {
+        for preserve_host in [true, false] {
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr = listener.local_addr().unwrap();
+            let server = tokio::spawn(async move {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let mut head = Vec::new();
+                while !head.ends_with(b"\r\n\r\n") {
+                    head.push(stream.read_u8().await.unwrap());
+                }
+                let response = "HTTP/1.1 200 OK\r\ncontent-length: 2\r\n\r\nok";
+                stream.write_all(response.as_bytes()).await.unwrap();
+                String::from_utf8(head).unwrap()
+            });
+
+            let client = client(serde_json::json!({
+                "host-overrides": {"api.partner.test": addr.to_string()},
+                "preserve-host": preserve_host
+            }));
+            let response = client
+                .send::<ErrorCode>(
+                    get("http://api.partner.test/v1"),
+                    false,
+                    DEFAULT_TIMEOUT,
+                    DEFAULT_TIMEOUT,
+                )
+                .await
+                .unwrap();
+            assert_eq!(response.status(), 200);
+            let request = server.await.unwrap();
+            // Without `preserve-host`, the request names the override.
+            let host = if preserve_host {
+                "api.partner.test".to_string()
+            } else {
+                addr.to_string()
+            };
+            assert!(
+                request.contains(&format!("host: {host}\r\n")),
+                "unexpected request: {request}"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn refuses_denied_addresses_when_connecting() {
+        let (addr, accepted) = server(0).await;
+        let client = client(serde_json::json!({"denied-cidrs": ["127.0.0.0/8", "::1/128"]}));
+        for uri in [
+            format!("http://{addr}/"),
+            format!("http://localhost:{}/", addr.port()),
+        ] {
+            let err = client
+                .send::<ErrorCode>(get(&uri), false, DEFAULT_TIMEOUT, DEFAULT_TIMEOUT)
+                .await
+                .unwrap_err();
+            assert!(
+                matches!(err, SendError::Connect(ConnectError::Denied)),
+                "unexpected error: {err}"
+            );
+        }
+        // Refused before a connection is made.
+        assert_eq!(accepted.load(Ordering::SeqCst), 0);
+    }
+
+    // Serves `200 OK` over TLS with a certificate for `localhost` issued by
+    // a fresh CA, offering the given ALPN protocols. Returns its address and
+    // the CA certificate as PEM.
+    async fn tls_server(alpn_protocols: Vec<Vec<u8>>) -> (std::net::SocketAddr, String) {
+        let ca = rcgen::CertifiedIssuer::self_signed(
+            {
+                let mut params = rcgen::CertificateParams::new(Vec::<String>::new()).unwrap();
+                params.is_ca = rcgen::IsCa::Ca(rcgen::BasicConstraints::Unconstrained);
+                params
+            },
+            rcgen::KeyPair::generate().unwrap(),
+        )
+        .unwrap();
+        let key = rcgen::KeyPair::generate().unwrap();
+        let cert = rcgen::CertificateParams::new(vec!["localhost".to_string()])
+            .unwrap()
+            .signed_by(&key, &ca)
+            .unwrap();
+        let mut config = rustls::ServerConfig::builder_with_provider(Arc::new(
+            rustls::crypto::aws_lc_rs::default_provider(),
+        ))
+        .with_safe_default_protocol_versions()
+        .unwrap()
+        .with_no_client_auth()
+        .with_single_cert(
+            vec![cert.der().clone()],
+            PrivateKeyDer::Pkcs8(key.serialize_der().into()),
+        )
+        .unwrap();
+        config.alpn_protocols = alpn_protocols;
+        let acceptor = tokio_rustls::TlsAcceptor::from(Arc::new(config));
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            loop {
+                let (stream, _) = listener.accept().await.unwrap();
+                let acceptor = acceptor.clone();
+                tokio::spawn(async move {
+                    let Ok(stream) = acceptor.accept(stream).await else {
+                        return;
+                    };
+                    let h2 = stream.get_ref().1.alpn_protocol() == Some(b"h2");
+                    let io = TokioIo::new(stream);
+                    let service = hyper::service::service_fn(|_| async {
+                        Ok::<_, std::convert::Infallible>(http::Response::new(Full::new(
+                            Bytes::from("ok"),
+                        )))
+                    });
+                    let _ = if h2 {
+                        hyper::server::conn::http2::Builder::new(TokioExecutor::new())
+                            .serve_connection(io, service)
+                            .await
+                    } else {
+                        hyper::server::conn::http1::Builder::new()
+                            .serve_connection(io, service)
+                            .await
+                    };
+                });
+            }
+        });
+        (addr, ca.pem())
+    }
+
+    #[tokio::test]
+    async fn trusts_ca_bundle_and_negotiates_h2_for_grpc() {
+        let (addr, ca) = tls_server(vec![b"h2".to_vec(), b"http/1.1".to_vec()]).await;
+        let dir = tempfile::tempdir().unwrap();
+        let ca_bundle = dir.path().join("ca.pem");
+        std::fs::write(&ca_bundle, ca).unwrap();
+        let uri = format!("https://localhost:{}/", addr.port());
+
+        let client = client(serde_json::json!({
+            "ca-bundle": ca_bundle,
+            "h2c-for-grpc": true
+        }));
+        for (grpc, version) in [
+            (false, http::Version::HTTP_11),
+            (true, http::Version::HTTP_2),
+        ] {
+            let response = client
+                .send::<ErrorCode>(get(&uri), grpc, DEFAULT_TIMEOUT, DEFAULT_TIMEOUT)
+                .await
+                .unwrap();
+            assert_eq!(response.status(), 200);
+            assert_eq!(response.version(), version, "grpc: {grpc}");
+        }
+
+        // Without the bundle, the server's CA is not trusted.
+        let err = super::tests::client(serde_json::json!({}))
+            .send::<ErrorCode>(get(&uri), false, DEFAULT_TIMEOUT, DEFAULT_TIMEOUT)
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(err, SendError::Connect(ConnectError::Tls)),
+            "unexpected error: {err}"
+        );
+    }
+
+    #[tokio::test]
+    async fn rejects_grpc_servers_without_h2() {
+        // A server without ALPN, as rustls aborts the handshake when the
+        // offered protocols do not overlap.
+        let (addr, ca) = tls_server(Vec::new()).await;
+        let dir = tempfile::tempdir().unwrap();
+        let ca_bundle = dir.path().join("ca.pem");
+        std::fs::write(&ca_bundle, ca).unwrap();
+
+        let client = client(serde_json::json!({
+            "ca-bundle": ca_bundle,
+            "h2c-for-grpc": true
+        }));
+        let err = client
+            .send::<ErrorCode>(
+                get(&format!("https://localhost:{}/", addr.port())),
+                true,
+                DEFAULT_TIMEOUT,
+                DEFAULT_TIMEOUT,
+            )
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(err, SendError::Connect(ConnectError::NoH2)),
+            "unexpected error: {err}"
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_properties() {
+        let props =
+            serde_json::from_value(serde_json::json!({"connect-timeout-ms": "5s"})).unwrap();
+        let err = HttpClient::from_properties("http", &props, Arc::default())
+            .err()
+            .unwrap()
+            .to_string();
+        assert!(err.contains("'connect-timeout-ms' must be a non-negative integer"));
+
+        for key in [
+            "connect-timeout-ms",
+            "first-byte-timeout-ms",
+            "between-bytes-timeout-ms",
+        ] {
+            let props = serde_json::from_value(serde_json::json!({key: 0})).unwrap();
+            let err = HttpClient::from_properties("http", &props, Arc::default())
+                .err()
+                .unwrap()
+                .to_string();
+            assert!(err.contains(&format!("'{key}' must be positive")), "{err}");
+        }
+
+        // Far-off timeouts are capped, so deadlines cannot overflow.
+        let client = client(serde_json::json!({"between-bytes-timeout-ms": u64::MAX}));
+        assert_eq!(client.between_bytes_timeout, MAX_TIMEOUT);
+    }
+}
@@ -0,0 +1,426 @@
+//! Outbound request policy of a `wasi:http` capability.
+//!
+//! ```toml
+//! [capability.http]
+//! type = "wasi:http"
+//! allowed-hosts = ["api.example.com", "*.example.org:8443", "localhost:*"]
+//! allowed-methods = ["GET", "POST"]
+//! denied-cidrs = ["link-local", "10.0.0.0/8"]
+//! max-request-body-bytes = 1048576
+//! ```
+//!
+//! Unset properties allow everything. A host pattern without a port only
+//! matches the default port of the request's scheme, and `*.` matches any
+//! subdomain. `link-local` in `denied-cidrs` stands for `169.254.0.0/16` and
+//! `fe80::/10`, which covers cloud metadata endpoints. Host names are
+//! resolved once when connecting, and the connection only goes to the
+//! addresses that passed `denied-cidrs`. Through a `proxy`, the proxy
+//! resolves names, so `denied-cidrs` only applies to address targets.
+
+use anyhow::Result;
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use bytes::Bytes;
+
+/// Why a request was refused. Each `send_request` hook maps it to the
+/// `ErrorCode` of its wasi:http version.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum PolicyViolation {
+    /// The host, port or method is not allowed.
+    Denied,
+    /// The request body exceeds `max-request-body-bytes`. Carries the
+    /// declared length when known.
+    BodyTooLarge(Option<u64>),
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct HttpPolicy {
+    allowed_hosts: Option<Vec<HostPattern>>,
+    allowed_methods: Option<Vec<String>>,
+    denied_cidrs: Vec<Cidr>,
+    pub(crate) max_request_body_bytes: Option<u64>,
+}
+
+impl HttpPolicy {
+    pub(crate) fn from_properties(
+        capability_name: &str,
+        props: &HashMap<String, serde_json::Value>,
+    ) -> Result<Self> {
+        let strings = |key: &str| -> Result<Option<Vec<String>>> {
+            match props.get(key) {
+                None => Ok(None),
+                Some(serde_json::Value::Array(values)) => values
+                    .iter()
+                    .map(|v| v.as_str().map(str::to_string))
+                    .collect::<Option<Vec<_>>>()
+                    .map(Some)
+                    .ok_or_else(|| {
+                        anyhow::anyhow!(
+                            "Capability '{capability_name}': '{key}' must be an array of strings"
+                        )
+                    }),
+                Some(_) => anyhow::bail!(
+                    "Capability '{capability_name}': '{key}' must be an array of strings"
+                ),
+            }
+        };
+        let invalid =
+            |key: &str, e: String| anyhow::anyhow!("Capability '{capability_name}': '{key}': {e}");
+
+        let allowed_hosts = strings("allowed-hosts")?
+            .map(|hosts| {
+                hosts
+                    .iter()
+                    .map(|h| HostPattern::parse(h))
+                    .collect::<Result<Vec<_>, _>>()
+            })
+            .transpose()
+            .map_err(|e| invalid("allowed-hosts", e))?;
+        let allowed_methods = strings("allowed-methods")?
+            .map(|methods| methods.iter().map(|m| m.to_ascii_uppercase()).collect());
+        let mut denied_cidrs = Vec::new();
+        for entry in strings("denied-cidrs")?.unwrap_or_default() {
+            if entry == "link-local" {
+                denied_cidrs.extend(
+                    ["169.254.0.0/16", "fe80::/10"]
+                        .map(|c| Cidr::parse(c).expect("built-in link-local ranges are valid")),
+                );
+            } else {
+                denied_cidrs.push(Cidr::parse(&entry).map_err(|e| invalid("denied-cidrs", e))?);
+            }
+        }
+        let max_request_body_bytes = match props.get("max-request-body-bytes") {
+            None => None,
+            Some(value) => Some(value.as_u64().ok_or_else(|| {
+                invalid(
+                    "max-request-body-bytes",
+                    "must be a non-negative integer".to_string(),
+                )
+            })?),
+        };
+        Ok(Self {
+            allowed_hosts,
+            allowed_methods,
+            denied_cidrs,
+            max_request_body_bytes,
+        })
+    }
+
+    /// Check what is known before connecting: the target, the method and the
+    /// declared body length.
+    pub(crate) fn check_request<B>(
+        &self,
+        request: &http::Request<B>,
+    ) -> Result<(), PolicyViolation> {
+        if let Some(methods) = &self.allowed_methods
+            && !methods.iter().any(|m| m == request.method().as_str())
+        {
+            return Err(PolicyViolation::Denied);
+        }
+        if let Some(patterns) = &self.allowed_hosts {
+            let (host, port) = target(request.uri()).ok_or(PolicyViolation::Denied)?;
+            let default_port = default_port(request.uri());
+            if !patterns
+                .iter()
+                .any(|p| p.matches(&host, port, default_port))
+            {
+                return Err(PolicyViolation::Denied);
+            }
+        }
+        if let Some(max) = self.max_request_body_bytes
+            && let Some(length) = request
+                .headers()
+                .get(http::header::CONTENT_LENGTH)
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.parse::<u64>().ok())
+            && length > max
+        {
+            return Err(PolicyViolation::BodyTooLarge(Some(length)));
+        }
+        Ok(())
+    }
+
+    /// Whether `denied-cidrs` covers an address a request would connect to.
+    /// Checked by the client's connector, on the addresses it connects to.
+    pub(crate) fn denies(&self, ip: IpAddr) -> bool {
+        self.denied_cidrs.iter().any(|cidr| cidr.contains(ip))
+    }
+}
+
+// The request's host, without IPv6 brackets, and port, defaulting to the
+// scheme's.
+fn target(uri: &http::Uri) -> Option<(String, u16)> {
+    let host = uri.host()?;
+    let host = host
+        .strip_prefix('[')
+        .and_then(|h| h.strip_suffix(']'))
+        .unwrap_or(host)
+        .to_ascii_lowercase();
+    Some((host, uri.port_u16().unwrap_or(default_port(uri))))
+}
+
+fn default_port(uri: &http::Uri) -> u16 {
+    if uri.scheme_str() == Some("https") {
+        443
+    } else {
+        80
+    }
+}
+
+#[derive(Debug)]
+struct HostPattern {
+    host: HostMatch,
+    port: PortMatch,
+}
+
+#[derive(Debug)]
+enum HostMatch {
+    Any,
+    // `*.example.com`, stored as `.example.com`
+    Subdomain(String),
+    Exact(String),
+}
+
+#[derive(Debug)]
+enum PortMatch {
+    Any,
+    Default,
+    Exact(u16),
+}
+
+impl HostPattern {
+    fn parse(pattern: &str) -> Result<Self, String> {
+        let pattern = pattern.to_ascii_lowercase();
+        // `[v6]:port`, `host:port` or a bare host (including a bare v6 literal).
+        let (host, port) = if let Some(rest) = pattern.strip_prefix('[') {
+            let (host, rest) = rest
+                .split_once(']')
+                .ok_or_else(|| format!("invalid host pattern '{pattern}'"))?;
+            match rest.strip_prefix(':') {
+                Some(port) => (host.to_string(), Some(port.to_string())),
+                None if rest.is_empty() => (host.to_string(), None),
+                None => return Err(format!("invalid host pattern '{pattern}'")),
+            }
+        } else {
+            match pattern.split_once(':') {
+                Some((host, port)) if !port.contains(':') => {
+                    (host.to_string(), Some(port.to_string()))
+                }
+                _ => (pattern.clone(), None),
+            }
+        };
+        if host.is_empty() {
+            return Err(format!("invalid host pattern '{pattern}'"));
+        }
+        let host = match host.strip_prefix("*.") {
+            _ if host == "*" => HostMatch::Any,
+            Some(domain) => HostMatch::Subdomain(format!(".{domain}")),
+            None => HostMatch::Exact(host),
+        };
+        let port = match port.as_deref() {
+            None => PortMatch::Default,
+            Some("*") => PortMatch::Any,
+            Some(port) => PortMatch::Exact(
+                port.parse()
+                    .map_err(|_| format!("invalid port in host pattern '{pattern}'"))?,
+            ),
+        };
+        Ok(Self { host, port })
+    }
+
+    fn matches(&self, host: &str, port: u16, default_port: u16) -> bool {
+        let host_matches = match &self.host {
+            HostMatch::Any => true,
+            HostMatch::Subdomain(suffix) => host.ends_with(suffix.as_str()),
+            HostMatch::Exact(exact) => host == exact,
+        };
+        let port_matches = match self.port {
+            PortMatch::Any => true,
+            PortMatch::Default => port == default_port,
+            PortMatch::Exact(exact) => port == exact,
+        };
+        host_matches && port_matches
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct Cidr {
+    network: IpAddr,
+    prefix: u8,
+}
+
+impl Cidr {
+    pub(crate) fn parse(cidr: &str) -> Result<Self, String> {
+        let invalid = || format!("invalid CIDR '{cidr}'");
+        let (network, prefix) = match cidr.split_once('/') {
+            Some((network, prefix)) => (
+                network.parse::<IpAddr>().map_err(|_| invalid())?,
+                prefix.parse::<u8>().map_err(|_| invalid())?,
+            ),
+            None => {
+                let ip = cidr.parse::<IpAddr>().map_err(|_| invalid())?;
+                (ip, if ip.is_ipv4() { 32 } else { 128 })
+            }
+        };
+        let max = if network.is_ipv4() { 32 } else { 128 };
+        if prefix > max {
+            return Err(invalid());
+        }
+        Ok(Self { network, prefix })
+    }
+
+    pub(crate) fn contains(&self, ip: IpAddr) -> bool {
+        // An IPv4-mapped IPv6 address reaches the IPv4 host.
+        match (self.network, ip.to_canonical()) {
+            (IpAddr::V4(network), IpAddr::V4(ip)) => {
+                let mask = u32::MAX
+                    .checked_shl(32 - u32::from(self.prefix))
+                    .unwrap_or(0);
+                u32::from(network) & mask == u32::from(ip) & mask
+            }
+            (IpAddr::V6(network), IpAddr::V6(ip)) => {
+                let mask = u128::MAX
+                    .checked_shl(128 - u32::from(self.prefix))
+                    .unwrap_or(0);
+                u128::from(network) & mask == u128::from(ip) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+/// A request body that fails with `error` once more than `remaining` bytes
+/// have been sent, for bodies without a declared length.
+pub(crate) struct LimitedBody<B, E> {
+    inner: B,
+    remaining: u64,
+    error: E,
+}
+
+impl<B, E> LimitedBody<B, E> {
+    pub(crate) fn new(inner: B, limit: u64, error: E) -> Self {
+        Self {
+            inner,
+            remaining: limit,
+            error,
+        }
+    }
+}
+
+impl<B, E> http_body::Body for LimitedBody<B, E>
+where
+    B: http_body::Body<Data = Bytes, Error = E> + Unpin,
+    E: Clone + Unpin,
+{
+    type Data = Bytes;
+    type Error = E;
+
+    fn poll_frame(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<http_body::Frame<Bytes>, E>>> {
+        let frame = std::task::ready!(Pin::new(&mut self.inner).poll_frame(cx));
+        if let Some(Ok(frame)) = &frame
+            && let Some(data) = frame.data_ref()
+        {
+            let len = data.len() as u64;
+            if len > self.remaining {
+                return Poll::Ready(Some(Err(self.error.clone())));
+            }
+            self.remaining -= len;
+        }
+        Poll::Ready(frame)
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> http_body::SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(props: serde_json::Value) -> HttpPolicy {
+        let props = serde_json::from_value(props).unwrap();
+        HttpPolicy::from_properties("http", &props).unwrap()
+    }
+
+    fn request(method: &str, uri: &str) -> http::Request<()> {
+        http::Request::builder()
+            .method(method)
+            .uri(uri)
+            .body(())
+            .unwrap()
+    }
+
+    #[test]
+    fn allowed_hosts_match_wildcards_and_ports() {
+        let policy = policy(serde_json::json!({
+            "allowed-hosts": ["api.example.com", "*.example.org:8443", "localhost:*", "[::1]:9000"]
+        }));
+        let allowed = |uri| policy.check_request(&request("GET", uri)).is_ok();
+        assert!(allowed("https://api.example.com/v1"));
+        assert!(allowed("http://API.example.com/"));
+        assert!(!allowed("https://api.example.com:8443/"));
+        assert!(allowed("https://a.b.example.org:8443/"));
+        assert!(!allowed("https://example.org:8443/"));
+        assert!(allowed("http://localhost:3000/"));
+        assert!(allowed("http://[::1]:9000/"));
+        assert!(!allowed("https://evil.com/"));
+    }
+
+    #[test]
+    fn methods_and_declared_body_length() {
+        let policy = policy(serde_json::json!({
+            "allowed-methods": ["get"],
+            "max-request-body-bytes": 4
+        }));
+        assert!(policy.check_request(&request("GET", "http://a/")).is_ok());
+        assert_eq!(
+            policy.check_request(&request("POST", "http://a/")),
+            Err(PolicyViolation::Denied)
+        );
+        let mut large = request("GET", "http://a/");
+        large
+            .headers_mut()
+            .insert(http::header::CONTENT_LENGTH, "5".parse().unwrap());
+        assert_eq!(
+            policy.check_request(&large),
+            Err(PolicyViolation::BodyTooLarge(Some(5)))
+        );
+    }
+
+    #[test]
+    fn denied_cidrs_cover_mapped_addresses() {
+        let policy = policy(serde_json::json!({
+            "denied-cidrs": ["link-local", "10.0.0.0/8"]
+        }));
+        let denied = |ip: &str| policy.denies(ip.parse().unwrap());
+        assert!(denied("169.254.169.254"));
+        assert!(denied("10.1.2.3"));
+        assert!(denied("::ffff:10.0.0.1"));
+        assert!(denied("fe80::1"));
+        assert!(!denied("192.168.1.1"));
+    }
+
+    #[test]
+    fn rejects_invalid_properties() {
+        let err = |props: serde_json::Value| {
+            let props = serde_json::from_value(props).unwrap();
+            HttpPolicy::from_properties("http", &props)
+                .unwrap_err()
+                .to_string()
+        };
+        assert!(err(serde_json::json!({"denied-cidrs": ["10.0.0.0/33"]})).contains("invalid CIDR"));
+        assert!(err(serde_json::json!({"allowed-hosts": ["a:port"]})).contains("invalid port"));
+        assert!(err(serde_json::json!({"allowed-methods": "GET"})).contains("array of strings"));
+    }
+}
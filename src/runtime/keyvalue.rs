@@ -0,0 +1,684 @@
+//! Built-in `wasi:keyvalue` capability.
+//!
+//! Each `[capability.*]` block with `type = "wasi:keyvalue"` owns one store,
+//! so two blocks never see each other's data:
+//!
+//! ```toml
+//! [capability.cache]
+//! type = "wasi:keyvalue"
+//! backend = "memory"          # default
+//!
+//! [capability.state]
+//! type = "wasi:keyvalue"
+//! backend = "file"
+//! path = "./data/state"
+//! buckets = ["counters"]      # optional allow-list for `open`
+//! ```
+//!
+//! Stores belong to the runtime rather than to a load of the definitions, so
+//! in-memory data survives a reload as long as the capability keeps its name
+//! and backend.
+
+use anyhow::Result;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use wasmtime::component::{HasData, Linker, Resource, ResourceTable, ResourceTableError};
+
+use crate::composition::registry::{CapabilityRegistry, split_wasi_kind};
+use crate::types::ComponentState;
+
+mod generated {
+    wasmtime::component::bindgen!({
+        path: "wit/deps/wasi-keyvalue-0.2.0-draft",
+        world: "wasi:keyvalue/imports",
+        imports: { default: async | trappable },
+        with: {
+            "wasi:keyvalue/store.bucket": super::Bucket,
+        },
+        trappable_error_type: {
+            "wasi:keyvalue/store.error" => super::KeyValueError,
+        },
+    });
+}
+
+use generated::wasi::keyvalue::{atomics, batch, store};
+
+// Keys returned by one `list-keys` call.
+const LIST_KEYS_PAGE_SIZE: usize = 1000;
+
+// Longest file name `encode` may produce: file names are limited to 255
+// bytes, and a temporary file adds a 5-byte prefix.
+const MAX_ENCODED_LEN: usize = 250;
+
+/// Error raised by a store operation, converted to `wasi:keyvalue/store.error`.
+///
+/// Any bucket name opens a bucket, so `no-such-store` is never raised.
+pub enum KeyValueError {
+    AccessDenied,
+    Other(String),
+}
+
+impl From<ResourceTableError> for KeyValueError {
+    fn from(err: ResourceTableError) -> Self {
+        Self::Other(err.to_string())
+    }
+}
+
+impl From<std::io::Error> for KeyValueError {
+    fn from(err: std::io::Error) -> Self {
+        Self::Other(err.to_string())
+    }
+}
+
+/// An open bucket of a component's store.
+pub struct Bucket {
+    name: String,
+}
+
+#[derive(Clone, PartialEq, Eq)]
+enum BackendConfig {
+    Memory,
+    File(PathBuf),
+}
+
+enum Backend {
+    // bucket -> key -> value
+    Memory(Mutex<HashMap<String, BTreeMap<String, Vec<u8>>>>),
+    // One directory per bucket and one file per key, named by `file_name`.
+    // The lock makes read-modify-write operations atomic.
+    File { dir: PathBuf, lock: Mutex<()> },
+}
+
+struct KeyValueStore {
+    config: BackendConfig,
+    backend: Backend,
+}
+
+impl KeyValueStore {
+    fn new(config: BackendConfig) -> Self {
+        let backend = match &config {
+            BackendConfig::Memory => Backend::Memory(Mutex::new(HashMap::new())),
+            BackendConfig::File(dir) => Backend::File {
+                dir: dir.clone(),
+                lock: Mutex::new(()),
+            },
+        };
+        Self { config, backend }
+    }
+
+    // Run a store operation, off the async executor if it does file I/O.
+    async fn run<T: Send + 'static>(
+        self: &Arc<Self>,
+        op: impl FnOnce(&Self) -> Result<T, KeyValueError> + Send + 'static,
+    ) -> Result<T, KeyValueError> {
+        match self.backend {
+            Backend::Memory(_) => op(self),
+            Backend::File { .. } => {
+                let store = Arc::clone(self);
+                tokio::task::spawn_blocking(move || op(&store))
+                    .await
+                    .map_err(|e| KeyValueError::Other(e.to_string()))?
+            }
+        }
+    }
+
+    fn get(&self, bucket: &str, key: &str) -> Result<Option<Vec<u8>>, KeyValueError> {
+        match &self.backend {
+            Backend::Memory(buckets) => Ok(buckets
+                .lock()
+                .unwrap()
+                .get(bucket)
+                .and_then(|b| b.get(key))
+                .cloned()),
+            Backend::File { dir, .. } => read_value(dir, bucket, key),
+        }
+    }
+
+    fn set(&self, bucket: &str, key: &str, value: Vec<u8>) -> Result<(), KeyValueError> {
+        match &self.backend {
+            Backend::Memory(buckets) => {
+                buckets
+                    .lock()
+                    .unwrap()
+                    .entry(bucket.to_string())
+                    .or_default()
+                    .insert(key.to_string(), value);
+                Ok(())
+            }
+            Backend::File { dir, lock } => {
+                let _guard = lock.lock().unwrap();
+                write_value(dir, bucket, key, &value)
+            }
+        }
+    }
+
+    fn delete(&self, bucket: &str, key: &str) -> Result<(), KeyValueError> {
+        match &self.backend {
+            Backend::Memory(buckets) => {
+                if let Some(b) = buckets.lock().unwrap().get_mut(bucket) {
+                    b.remove(key);
+                }
+                Ok(())
+            }
+            Backend::File { dir, lock } => {
+                let _guard = lock.lock().unwrap();
+                match std::fs::remove_file(key_path(dir, bucket, key)) {
+                    Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+                    _ => Ok(()),
+                }
+            }
+        }
+    }
+
+    // Sorted, so a cursor stays meaningful between calls.
+    fn keys(&self, bucket: &str) -> Result<Vec<String>, KeyValueError> {
+        match &self.backend {
+            Backend::Memory(buckets) => Ok(buckets
+                .lock()
+                .unwrap()
+                .get(bucket)
+                .map(|b| b.keys().cloned().collect())
+                .unwrap_or_default()),
+            Backend::File { dir, .. } => {
+                let entries = match std::fs::read_dir(bucket_dir(dir, bucket)) {
+                    Ok(entries) => entries,
+                    Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+                    Err(e) => return Err(e.into()),
+                };
+                let mut keys = Vec::new();
+                for entry in entries {
+                    let entry = entry?;
+                    let Some(name) = entry.file_name().to_str().map(str::to_string) else {
+                        continue;
+                    };
+                    if let Some(key) = decode("k", &name) {
+                        keys.push(key);
+                    } else if name.starts_with('h')
+                        && let Some(contents) = read_file(&entry.path())?
+                        && let Some((key, _)) = split_hashed(&contents)
+                        && let Ok(key) = String::from_utf8(key.to_vec())
+                    {
+                        keys.push(key);
+                    }
+                }
+                keys.sort();
+                Ok(keys)
+            }
+        }
+    }
+
+    fn increment(&self, bucket: &str, key: &str, delta: u64) -> Result<u64, KeyValueError> {
+        let add = |current: Option<&[u8]>| {
+            let current = match current {
+                Some(bytes) => std::str::from_utf8(bytes)
+                    .ok()
+                    .and_then(|s| s.parse::<u64>().ok())
+                    .ok_or_else(|| {
+                        KeyValueError::Other(format!("value of '{key}' is not an unsigned integer"))
+                    })?,
+                None => 0,
+            };
+            current
+                .checked_add(delta)
+                .ok_or_else(|| KeyValueError::Other(format!("value of '{key}' would overflow")))
+        };
+        match &self.backend {
+            Backend::Memory(buckets) => {
+                let mut buckets = buckets.lock().unwrap();
+                let values = buckets.entry(bucket.to_string()).or_default();
+                let value = add(values.get(key).map(Vec::as_slice))?;
+                values.insert(key.to_string(), value.to_string().into_bytes());
+                Ok(value)
+            }
+            Backend::File { dir, lock } => {
+                let _guard = lock.lock().unwrap();
+                let value = add(read_value(dir, bucket, key)?.as_deref())?;
+                write_value(dir, bucket, key, value.to_string().as_bytes())?;
+                Ok(value)
+            }
+        }
+    }
+}
+
+// Bucket and key names are hex-encoded behind a one-letter prefix, so any
+// string maps to a valid file name and temporary files never look like keys.
+fn encode(prefix: &str, name: &str) -> String {
+    let mut encoded = String::with_capacity(prefix.len() + name.len() * 2);
+    encoded.push_str(prefix);
+    for byte in name.bytes() {
+        encoded.push_str(&format!("{byte:02x}"));
+    }
+    encoded
+}
+
+fn decode(prefix: &str, encoded: &str) -> Option<String> {
+    let hex = encoded.strip_prefix(prefix)?;
+    if hex.len() % 2 != 0 {
+        return None;
+    }
+    let bytes = (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect::<Option<Vec<u8>>>()?;
+    String::from_utf8(bytes).ok()
+}
+
+// A name whose encoding would be too long for a file name is replaced by its
+// SHA-256 hash behind an `h` prefix. The second value is whether it was. A
+// hashed key's file starts with the key itself (see `split_hashed`), so the
+// key can still be listed.
+fn file_name(prefix: &str, name: &str) -> (String, bool) {
+    let encoded = encode(prefix, name);
+    if encoded.len() <= MAX_ENCODED_LEN {
+        (encoded, false)
+    } else {
+        (format!("h{:x}", Sha256::digest(name)), true)
+    }
+}
+
+fn bucket_dir(dir: &Path, bucket: &str) -> PathBuf {
+    dir.join(file_name("b", bucket).0)
+}
+
+fn key_path(dir: &Path, bucket: &str, key: &str) -> PathBuf {
+    bucket_dir(dir, bucket).join(file_name("k", key).0)
+}
+
+fn read_file(path: &Path) -> Result<Option<Vec<u8>>, KeyValueError> {
+    match std::fs::read(path) {
+        Ok(contents) => Ok(Some(contents)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn read_value(dir: &Path, bucket: &str, key: &str) -> Result<Option<Vec<u8>>, KeyValueError> {
+    let Some(contents) = read_file(&key_path(dir, bucket, key))? else {
+        return Ok(None);
+    };
+    if !file_name("k", key).1 {
+        return Ok(Some(contents));
+    }
+    match split_hashed(&contents) {
+        Some((stored, value)) if stored == key.as_bytes() => Ok(Some(value.to_vec())),
+        _ => Err(KeyValueError::Other(format!(
+            "file of key '{key}' holds another key"
+        ))),
+    }
+}
+
+// A hashed key's file holds the key's length in decimal, a newline, the key
+// and then the value.
+fn split_hashed(contents: &[u8]) -> Option<(&[u8], &[u8])> {
+    let newline = contents.iter().position(|&b| b == b'\n')?;
+    let len: usize = std::str::from_utf8(&contents[..newline])
+        .ok()?
+        .parse()
+        .ok()?;
+    let rest = &contents[newline + 1..];
+    (rest.len() >= len).then(|| rest.split_at(len))
+}
+
+// Write through a temporary file and rename it, so a crash never leaves a
+// partially written value behind.
+fn write_value(dir: &Path, bucket: &str, key: &str, value: &[u8]) -> Result<(), KeyValueError> {
+    let bucket_dir = bucket_dir(dir, bucket);
+    std::fs::create_dir_all(&bucket_dir)?;
+    let (name, hashed) = file_name("k", key);
+    let tmp = bucket_dir.join(format!(".tmp-{name}"));
+    if hashed {
+        let mut contents = format!("{}\n{key}", key.len()).into_bytes();
+        contents.extend_from_slice(value);
+        std::fs::write(&tmp, contents)?;
+    } else {
+        std::fs::write(&tmp, value)?;
+    }
+    std::fs::rename(&tmp, bucket_dir.join(name))?;
+    Ok(())
+}
+
+/// A component's view of the store of its `wasi:keyvalue` capability.
+#[derive(Clone)]
+pub(crate) struct KeyValueCtx {
+    store: Arc<KeyValueStore>,
+    // Buckets `open` accepts; all of them when unset.
+    buckets: Option<Arc<[String]>>,
+}
+
+/// The `wasi:keyvalue` stores of a runtime, one per capability.
+#[derive(Default)]
+pub(crate) struct KeyValueStores {
+    stores: Mutex<HashMap<String, Arc<KeyValueStore>>>,
+}
+
+impl KeyValueStores {
+    /// Validate every `wasi:keyvalue` capability and return its context, by
+    /// capability name. A capability that keeps its backend keeps its store.
+    pub(crate) fn contexts(
+        &self,
+        capability_registry: &CapabilityRegistry,
+    ) -> Result<HashMap<String, KeyValueCtx>> {
+        let mut stores = self.stores.lock().unwrap();
+        let mut contexts = HashMap::new();
+        let mut paths: HashMap<PathBuf, &str> = HashMap::new();
+        for (name, capability) in capability_registry.capabilities.iter() {
+            if split_wasi_kind(&capability.kind).0 != "wasi:keyvalue" {
+                continue;
+            }
+            let (config, buckets) = parse_properties(name, &capability.properties)?;
+            if let BackendConfig::File(path) = &config
+                && let Some(other) = paths.insert(path.clone(), name)
+            {
+                anyhow::bail!(
+                    "Capability '{name}': 'path' '{}' is already used by capability '{other}'",
+                    path.display()
+                );
+            }
+            let store = match stores.get(name) {
+                Some(store) if store.config == config => Arc::clone(store),
+                _ => {
+                    let store = Arc::new(KeyValueStore::new(config));
+                    stores.insert(name.clone(), Arc::clone(&store));
+                    store
+                }
+            };
+            contexts.insert(name.clone(), KeyValueCtx { store, buckets });
+        }
+        Ok(contexts)
+    }
+}
+
+fn parse_properties(
+    name: &str,
+    props: &HashMap<String, serde_json::Value>,
+) -> Result<(BackendConfig, Option<Arc<[String]>>)> {
+    let path = match props.get("path") {
+        Some(serde_json::Value::String(path)) => Some(std::path::absolute(path)?),
+        Some(other) => anyhow::bail!("Capability '{name}': 'path' must be a string, got {other}"),
+        None => None,
+    };
+    let config = match props.get("backend") {
+        None => BackendConfig::Memory,
+        Some(serde_json::Value::String(backend)) => match (backend.as_str(), path) {
+            ("memory", None) => BackendConfig::Memory,
+            ("memory", Some(_)) => {
+                anyhow::bail!("Capability '{name}': 'path' requires backend \"file\"")
+            }
+            ("file", Some(path)) => BackendConfig::File(path),
+            ("file", None) => {
+                anyhow::bail!("Capability '{name}': backend \"file\" requires a 'path'")
+            }
+            (other, _) => anyhow::bail!(
+                "Capability '{name}': 'backend' must be \"memory\" or \"file\", got \"{other}\""
+            ),
+        },
+        Some(other) => {
+            anyhow::bail!("Capability '{name}': 'backend' must be a string, got {other}")
+        }
+    };
+    let buckets = match props.get("buckets") {
+        None => None,
+        Some(serde_json::Value::Array(values)) => Some(
+            values
+                .iter()
+                .map(|v| v.as_str().map(str::to_string))
+                .collect::<Option<Arc<[String]>>>()
+                .ok_or_else(|| {
+                    anyhow::anyhow!("Capability '{name}': 'buckets' must be an array of strings")
+                })?,
+        ),
+        Some(_) => {
+            anyhow::bail!("Capability '{name}': 'buckets' must be an array of strings")
+        }
+    };
+    Ok((config, buckets))
+}
+
+/// Link `wasi:keyvalue/{store,atomics,batch}` against the component's
+/// [`KeyValueCtx`].
+pub(crate) fn add_to_linker(linker: &mut Linker<ComponentState>) -> Result<()> {
+    fn view(state: &mut ComponentState) -> KeyValueView<'_> {
+        KeyValueView {
+            ctx: state.keyvalue.as_ref().expect(
+                "Component requires 'wasi:keyvalue' capability, so its context should be available",
+            ),
+            table: &mut state.resource_table,
+        }
+    }
+    store::add_to_linker::<_, HasKeyValue>(linker, view)?;
+    atomics::add_to_linker::<_, HasKeyValue>(linker, view)?;
+    batch::add_to_linker::<_, HasKeyValue>(linker, view)?;
+    Ok(())
+}
+
+struct HasKeyValue;
+
+impl HasData for HasKeyValue {
+    type Data<'a> = KeyValueView<'a>;
+}
+
+struct KeyValueView<'a> {
+    ctx: &'a KeyValueCtx,
+    table: &'a mut ResourceTable,
+}
+
+impl KeyValueView<'_> {
+    fn bucket(&self, bucket: &Resource<Bucket>) -> Result<String, KeyValueError> {
+        Ok(self.table.get(bucket)?.name.clone())
+    }
+}
+
+impl store::Host for KeyValueView<'_> {
+    async fn open(&mut self, identifier: String) -> Result<Resource<Bucket>, KeyValueError> {
+        if let Some(buckets) = &self.ctx.buckets
+            && !buckets.contains(&identifier)
+        {
+            return Err(KeyValueError::AccessDenied);
+        }
+        Ok(self.table.push(Bucket { name: identifier })?)
+    }
+
+    fn convert_error(&mut self, err: KeyValueError) -> wasmtime::Result<store::Error> {
+        Ok(match err {
+            KeyValueError::AccessDenied => store::Error::AccessDenied,
+            KeyValueError::Other(e) => store::Error::Other(e),
+        })
+    }
+}
+
+impl store::HostBucket for KeyValueView<'_> {
+    async fn get(
+        &mut self,
+        bucket: Resource<Bucket>,
+        key: String,
+    ) -> Result<Option<Vec<u8>>, KeyValueError> {
+        let bucket = self.bucket(&bucket)?;
+        self.ctx.store.run(move |s| s.get(&bucket, &key)).await
+    }
+
+    async fn set(
+        &mut self,
+        bucket: Resource<Bucket>,
+        key: String,
+        value: Vec<u8>,
+    ) -> Result<(), KeyValueError> {
+        let bucket = self.bucket(&bucket)?;
+        self.ctx
+            .store
+            .run(move |s| s.set(&bucket, &key, value))
+            .await
+    }
+
+    async fn delete(&mut self, bucket: Resource<Bucket>, key: String) -> Result<(), KeyValueError> {
+        let bucket = self.bucket(&bucket)?;
+        self.ctx.store.run(move |s| s.delete(&bucket, &key)).await
+    }
+
+    async fn exists(
+        &mut self,
+        bucket: Resource<Bucket>,
+        key: String,
+    ) -> Result<bool, KeyValueError> {
+        let bucket = self.bucket(&bucket)?;
+        self.ctx
+            .store
+            .run(move |s| Ok(s.get(&bucket, &key)?.is_some()))
+            .await
+    }
+
+    // The cursor is the offset of the next key in sorted order.
+    async fn list_keys(
+        &mut self,
+        bucket: Resource<Bucket>,
+        cursor: Option<u64>,
+    ) -> Result<store::KeyResponse, KeyValueError> {
+        let bucket = self.bucket(&bucket)?;
+        let keys = self.ctx.store.run(move |s| s.keys(&bucket)).await?;
+        let start = usize::try_from(cursor.unwrap_or(0))
+            .unwrap_or(usize::MAX)
+            .min(keys.len());
+        let end = start.saturating_add(LIST_KEYS_PAGE_SIZE).min(keys.len());
+        Ok(store::KeyResponse {
+            keys: keys[start..end].to_vec(),
+            cursor: (end < keys.len()).then_some(end as u64),
+        })
+    }
+
+    async fn drop(&mut self, bucket: Resource<Bucket>) -> wasmtime::Result<()> {
+        self.table.delete(bucket)?;
+        Ok(())
+    }
+}
+
+impl atomics::Host for KeyValueView<'_> {
+    async fn increment(
+        &mut self,
+        bucket: Resource<Bucket>,
+        key: String,
+        delta: u64,
+    ) -> Result<u64, KeyValueError> {
+        let bucket = self.bucket(&bucket)?;
+        self.ctx
+            .store
+            .run(move |s| s.increment(&bucket, &key, delta))
+            .await
+    }
+}
+
+impl batch::Host for KeyValueView<'_> {
+    async fn get_many(
+        &mut self,
+        bucket: Resource<Bucket>,
+        keys: Vec<String>,
+    ) -> Result<Vec<Option<(String, Vec<u8>)>>, KeyValueError> {
+        let bucket = self.bucket(&bucket)?;
+        self.ctx
+            .store
+            .run(move |s| {
+                keys.into_iter()
+                    .map(|key| Ok(s.get(&bucket, &key)?.map(|value| (key, value))))
+                    .collect()
+            })
+            .await
+    }
+
+    async fn set_many(
+        &mut self,
+        bucket: Resource<Bucket>,
+        key_values: Vec<(String, Vec<u8>)>,
+    ) -> Result<(), KeyValueError> {
+        let bucket = self.bucket(&bucket)?;
+        self.ctx
+            .store
+            .run(move |s| {
+                for (key, value) in key_values {
+                    s.set(&bucket, &key, value)?;
+                }
+                Ok(())
+            })
+            .await
+    }
+
+    async fn delete_many(
+        &mut self,
+        bucket: Resource<Bucket>,
+        keys: Vec<String>,
+    ) -> Result<(), KeyValueError> {
+        let bucket = self.bucket(&bucket)?;
+        self.ctx
+            .store
+            .run(move |s| {
+                for key in keys {
+                    s.delete(&bucket, &key)?;
+                }
+                Ok(())
+            })
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_round_trip_through_file_names() {
+        for name in ["", "counters", "a/b\\..", "ünïcode"] {
+            let encoded = encode("k", name);
+            assert!(encoded.chars().all(|c| c.is_ascii_alphanumeric()));
+            assert_eq!(decode("k", &encoded).as_deref(), Some(name));
+        }
+        assert_eq!(decode("k", "b00"), None);
+        assert_eq!(decode("k", ".tmp-k00"), None);
+    }
+
+    #[test]
+    fn file_backend_stores_long_names() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = KeyValueStore::new(BackendConfig::File(dir.path().to_path_buf()));
+        let bucket = "b".repeat(300);
+        let long = "k\n".repeat(500);
+        assert!(store.set(&bucket, &long, b"long".to_vec()).is_ok());
+        assert!(store.set(&bucket, "short", b"short".to_vec()).is_ok());
+        assert_eq!(store.get(&bucket, &long).ok(), Some(Some(b"long".to_vec())));
+        assert_eq!(
+            store.keys(&bucket).ok(),
+            Some(vec![long.clone(), "short".into()])
+        );
+        assert!(store.delete(&bucket, &long).is_ok());
+        assert_eq!(store.get(&bucket, &long).ok(), Some(None));
+        assert_eq!(store.keys(&bucket).ok(), Some(vec!["short".into()]));
+    }
+
+    #[test]
+    fn increment_rejects_non_numeric_values() {
+        let store = KeyValueStore::new(BackendConfig::Memory);
+        assert_eq!(store.increment("b", "n", 2).ok(), Some(2));
+        assert_eq!(store.increment("b", "n", 3).ok(), Some(5));
+        store.set("b", "s", b"text".to_vec()).ok();
+        assert!(store.increment("b", "s", 1).is_err());
+        store
+            .set("b", "max", u64::MAX.to_string().into_bytes())
+            .ok();
+        assert!(store.increment("b", "max", 1).is_err());
+    }
+
+    #[test]
+    fn rejects_invalid_properties() {
+        let props = |value: serde_json::Value| {
+            serde_json::from_value::<HashMap<String, serde_json::Value>>(value).unwrap()
+        };
+        let err = |value| {
+            parse_properties("kv", &props(value))
+                .err()
+                .unwrap()
+                .to_string()
+        };
+        assert!(err(serde_json::json!({"backend": "redis"})).contains("\"memory\" or \"file\""));
+        assert!(err(serde_json::json!({"backend": "file"})).contains("requires a 'path'"));
+        assert!(err(serde_json::json!({"buckets": ["a", 1]})).contains("array of strings"));
+        assert!(parse_properties("kv", &props(serde_json::json!({"buckets": ["a"]}))).is_ok());
+    }
+}
@@ -0,0 +1,84 @@
+//! Reuse of component instances across invocations, for the `singleton` and
+//! `pool` lifecycles.
+
+use anyhow::Result;
+use std::future::Future;
+use std::sync::Mutex;
+use tokio::sync::Semaphore;
+
+use crate::runtime::component::{ComponentInstance, Val};
+use crate::types::{Function, InvocationError, Lifecycle};
+
+/// Idle instances of one component, and permits bounding how many calls run
+/// at once. A singleton is a pool of one.
+pub(crate) struct InstancePool {
+    idle: Mutex<Vec<PooledInstance>>,
+    permits: Semaphore,
+    max_calls: Option<u64>,
+}
+
+struct PooledInstance {
+    instance: ComponentInstance,
+    calls: u64,
+}
+
+impl InstancePool {
+    /// Returns `None` for `per-invocation`, which keeps no instances.
+    pub(crate) fn new(lifecycle: Lifecycle) -> Option<Self> {
+        let (size, max_calls) = match lifecycle {
+            Lifecycle::PerInvocation => return None,
+            Lifecycle::Singleton { max_calls } => (1, max_calls),
+            Lifecycle::Pool { size, max_calls } => (size as usize, max_calls),
+        };
+        Some(Self {
+            idle: Mutex::new(Vec::with_capacity(size)),
+            permits: Semaphore::new(size),
+            max_calls,
+        })
+    }
+
+    /// Call `function` on an idle instance, or on a new one from `instantiate`
+    /// if none is idle. Waits while every instance is busy.
+    ///
+    /// The instance is returned to the pool afterwards, unless the call failed
+    /// inside the guest (a trapped instance cannot be re-entered) or it has
+    /// reached `max-calls`. Rejected arguments and a returned WIT `err` leave
+    /// the instance usable.
+    /// An instance whose call is cancelled is dropped with it.
+    pub(crate) async fn call<F, Fut>(
+        &self,
+        instantiate: F,
+        function: &Function,
+        args: Vec<Val>,
+    ) -> Result<Option<Val>>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<ComponentInstance>>,
+    {
+        let _permit = self.permits.acquire().await?;
+
+        let idle = self.idle.lock().unwrap().pop();
+        let mut pooled = match idle {
+            Some(pooled) => pooled,
+            None => PooledInstance {
+                instance: instantiate().await?,
+                calls: 0,
+            },
+        };
+
+        let result = pooled.instance.call(function, args).await;
+        pooled.calls += 1;
+
+        let exhausted = self.max_calls.is_some_and(|max| pooled.calls >= max);
+        let reusable = matches!(
+            result,
+            Ok(_)
+                | Err(InvocationError::InvalidArguments { .. })
+                | Err(InvocationError::ErrorResult { .. })
+        );
+        if reusable && !exhausted {
+            self.idle.lock().unwrap().push(pooled);
+        }
+        Ok(result?)
+    }
+}
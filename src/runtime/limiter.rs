@@ -0,0 +1,237 @@
+//! Store resource limiter enforcing the memory and table limits of a component.
+
+use wasmparser::{
+    ComponentAlias, ComponentExternalKind, ComponentInstance, ComponentOuterAliasKind,
+    ComponentTypeRef, Encoding, Instance, Parser, Payload,
+};
+use wasmtime::component::Component as WasmComponent;
+use wasmtime::{ResourceLimiter, Result};
+
+use crate::types::{ComponentLimits, ResourceLimitExceeded};
+
+/// Enforces `max-memory-bytes`, `max-tables`, `max-table-elements` and
+/// `max-instances` for one store. Growth past a limit traps with a
+/// [`ResourceLimitExceeded`] rather than failing silently inside the guest.
+#[derive(Debug, Default)]
+pub(crate) struct ComponentLimiter {
+    component: String,
+    limits: ComponentLimits,
+    counts: InstanceCounts,
+    // Bytes across all linear memories; a composed component has several.
+    memory_bytes: u64,
+    // Growth admitted by the last `memory_growing`, undone if it then fails.
+    pending_memory_growth: u64,
+}
+
+/// The core instances and tables one instantiation of a component creates,
+/// counted when it is compiled so that `max-instances` and `max-tables` are
+/// checked before anything is instantiated.
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct InstanceCounts {
+    // Core instances the component declares, including those of nested
+    // components once per instantiation of them.
+    instances: u64,
+    // `None` when the component instantiates an imported core module.
+    tables: Option<u64>,
+}
+
+impl InstanceCounts {
+    /// Counted only for components with `max-instances` or `max-tables`.
+    pub(crate) fn new(
+        bytes: &[u8],
+        component: &WasmComponent,
+        limits: &ComponentLimits,
+    ) -> anyhow::Result<Self> {
+        if limits.max_instances.is_none() && limits.max_tables.is_none() {
+            return Ok(Self::default());
+        }
+        Ok(Self {
+            instances: core_instances(bytes)?,
+            tables: component
+                .resources_required()
+                .map(|required| required.num_tables.into()),
+        })
+    }
+}
+
+impl ComponentLimiter {
+    pub(crate) fn new(component: &str, limits: &ComponentLimits, counts: InstanceCounts) -> Self {
+        Self {
+            component: component.to_string(),
+            limits: limits.clone(),
+            counts,
+            ..Default::default()
+        }
+    }
+
+    fn exceeded(&self, limit: &'static str, maximum: u64) -> ResourceLimitExceeded {
+        ResourceLimitExceeded {
+            component: self.component.clone(),
+            limit,
+            maximum,
+        }
+    }
+
+    /// Refuse an instantiation that would create more core instances or
+    /// tables than the component allows.
+    pub(crate) fn admit(&self) -> std::result::Result<(), ResourceLimitExceeded> {
+        if let Some(max) = self.limits.max_instances
+            && self.counts.instances > max.into()
+        {
+            return Err(self.exceeded("max-instances", max.into()));
+        }
+        if let Some(max) = self.limits.max_tables
+            && self.counts.tables.is_some_and(|tables| tables > max.into())
+        {
+            return Err(self.exceeded("max-tables", max.into()));
+        }
+        Ok(())
+    }
+}
+
+// Walks the component and its nested components and modules. Each component
+// gets a frame, holding the instances counted so far and, for each entry of
+// its component index space, the instances instantiating that entry creates.
+fn core_instances(bytes: &[u8]) -> anyhow::Result<u64> {
+    struct Frame {
+        instances: u64,
+        components: Vec<u64>,
+    }
+    // `None` for a core module nested in a component.
+    let mut frames: Vec<Option<Frame>> = Vec::new();
+    let component_count = |frames: &[Option<Frame>], depth: usize, index: u32| {
+        frames
+            .len()
+            .checked_sub(depth + 1)
+            .and_then(|outer| frames[outer].as_ref())
+            .and_then(|frame| frame.components.get(index as usize).copied())
+            .unwrap_or(0)
+    };
+    for payload in Parser::new(0).parse_all(bytes) {
+        let payload = payload?;
+        if let Payload::Version { encoding, .. } = payload {
+            frames.push(match encoding {
+                Encoding::Component => Some(Frame {
+                    instances: 0,
+                    components: Vec::new(),
+                }),
+                Encoding::Module => None,
+            });
+            continue;
+        }
+        if let Payload::End(_) = payload {
+            match (frames.pop(), frames.last_mut()) {
+                (Some(Some(frame)), Some(Some(parent))) => parent.components.push(frame.instances),
+                (Some(Some(frame)), None) => return Ok(frame.instances),
+                _ => {}
+            }
+            continue;
+        }
+        let mut added = Vec::new();
+        let mut instances = 0;
+        match payload {
+            Payload::InstanceSection(reader) => {
+                for instance in reader {
+                    if let Instance::Instantiate { .. } = instance? {
+                        instances += 1;
+                    }
+                }
+            }
+            Payload::ComponentInstanceSection(reader) => {
+                for instance in reader {
+                    if let ComponentInstance::Instantiate {
+                        component_index, ..
+                    } = instance?
+                    {
+                        instances += component_count(&frames, 0, component_index);
+                    }
+                }
+            }
+            Payload::ComponentImportSection(reader) => {
+                for import in reader {
+                    if let ComponentTypeRef::Component(_) = import?.ty {
+                        added.push(0);
+                    }
+                }
+            }
+            Payload::ComponentAliasSection(reader) => {
+                for alias in reader {
+                    match alias? {
+                        ComponentAlias::InstanceExport {
+                            kind: ComponentExternalKind::Component,
+                            ..
+                        } => added.push(0),
+                        ComponentAlias::Outer {
+                            kind: ComponentOuterAliasKind::Component,
+                            count,
+                            index,
+                        } => added.push(component_count(&frames, count as usize, index)),
+                        _ => {}
+                    }
+                }
+            }
+            Payload::ComponentExportSection(reader) => {
+                for export in reader {
+                    let export = export?;
+                    if export.kind == ComponentExternalKind::Component {
+                        added.push(component_count(&frames, 0, export.index));
+                    }
+                }
+            }
+            _ => {}
+        }
+        if let Some(Some(frame)) = frames.last_mut() {
+            frame.instances += instances;
+            frame.components.extend(added);
+        }
+    }
+    Ok(0)
+}
+
+impl ResourceLimiter for ComponentLimiter {
+    fn memory_growing(
+        &mut self,
+        current: usize,
+        desired: usize,
+        _maximum: Option<usize>,
+    ) -> Result<bool> {
+        let growth = (desired - current) as u64;
+        if let Some(max) = self.limits.max_memory_bytes
+            && self.memory_bytes.saturating_add(growth) > max
+        {
+            return Err(wasmtime::Error::new(self.exceeded("max-memory-bytes", max)));
+        }
+        self.memory_bytes += growth;
+        self.pending_memory_growth = growth;
+        Ok(true)
+    }
+
+    fn memory_grow_failed(&mut self, _error: wasmtime::Error) -> Result<()> {
+        self.memory_bytes -= std::mem::take(&mut self.pending_memory_growth);
+        Ok(())
+    }
+
+    fn table_growing(
+        &mut self,
+        _current: usize,
+        desired: usize,
+        _maximum: Option<usize>,
+    ) -> Result<bool> {
+        if let Some(max) = self.limits.max_table_elements
+            && desired as u64 > max
+        {
+            return Err(wasmtime::Error::new(
+                self.exceeded("max-table-elements", max),
+            ));
+        }
+        Ok(true)
+    }
+
+    // Wasmtime counts tables the same way as `InstanceCounts`, so it only
+    // refuses here when they could not be counted up front.
+    fn tables(&self) -> usize {
+        self.limits
+            .max_tables
+            .map_or(wasmtime::DEFAULT_TABLE_LIMIT, |max| max as usize)
+    }
+}
@@ -0,0 +1,116 @@
+//! Built-in `wasi:logging` capability.
+//!
+//! Each `log` call becomes a `tracing` event with the target
+//! `composable_runtime::guest`, so `RUST_LOG` filters guest logs like any
+//! other. The event carries the component name, the guest's context string
+//! and, within a traced invocation, the trace id of its `traceparent`.
+//! `critical` has no `tracing` level of its own and is logged as `ERROR`.
+
+use anyhow::Result;
+use wasmtime::component::{HasData, Linker};
+
+use crate::context::PROPAGATION_CONTEXT;
+use crate::types::ComponentState;
+
+mod generated {
+    wasmtime::component::bindgen!({
+        path: "wit/deps/wasi-logging-0.1.0-draft",
+        world: "wasi:logging/imports",
+    });
+}
+
+use generated::wasi::logging::logging::{self, Level};
+
+/// Link `wasi:logging/logging` for the store's component.
+pub(crate) fn add_to_linker(linker: &mut Linker<ComponentState>) -> Result<()> {
+    logging::add_to_linker::<_, HasLogging>(linker, |state| LoggingView {
+        component: &state.component,
+    })?;
+    Ok(())
+}
+
+struct HasLogging;
+
+impl HasData for HasLogging {
+    type Data<'a> = LoggingView<'a>;
+}
+
+struct LoggingView<'a> {
+    component: &'a str,
+}
+
+// `tracing` levels must be constant at each callsite.
+macro_rules! guest_event {
+    ($level:expr, $component:expr, $context:expr, $trace_id:expr, $message:expr) => {
+        tracing::event!(
+            target: "composable_runtime::guest",
+            $level,
+            component = $component,
+            context = $context,
+            trace_id = $trace_id,
+            "{}",
+            $message
+        )
+    };
+}
+
+impl logging::Host for LoggingView<'_> {
+    fn log(&mut self, level: Level, context: String, message: String) {
+        let component = self.component;
+        let trace_id = current_trace_id();
+        let trace_id = trace_id.as_deref();
+        match level {
+            Level::Trace => {
+                guest_event!(tracing::Level::TRACE, component, context, trace_id, message)
+            }
+            Level::Debug => {
+                guest_event!(tracing::Level::DEBUG, component, context, trace_id, message)
+            }
+            Level::Info => {
+                guest_event!(tracing::Level::INFO, component, context, trace_id, message)
+            }
+            Level::Warn => {
+                guest_event!(tracing::Level::WARN, component, context, trace_id, message)
+            }
+            Level::Error | Level::Critical => {
+                guest_event!(tracing::Level::ERROR, component, context, trace_id, message)
+            }
+        }
+    }
+}
+
+// The trace id of the invocation's `traceparent`, if it has one.
+fn current_trace_id() -> Option<String> {
+    PROPAGATION_CONTEXT
+        .try_with(|ctx| {
+            ctx.as_ref()
+                .and_then(|ctx| ctx.entries.get("traceparent"))
+                .and_then(|traceparent| trace_id(traceparent))
+                .map(str::to_string)
+        })
+        .ok()
+        .flatten()
+}
+
+// `{version}-{trace-id}-{parent-id}-{flags}`
+fn trace_id(traceparent: &str) -> Option<&str> {
+    let mut parts = traceparent.split('-');
+    let _version = parts.next()?;
+    let trace_id = parts.next()?;
+    (trace_id.len() == 32 && trace_id.bytes().all(|b| b.is_ascii_hexdigit())).then_some(trace_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn trace_id_from_traceparent() {
+        assert_eq!(
+            trace_id("00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01"),
+            Some("4bf92f3577b34da6a3ce929d0e0e4736")
+        );
+        assert_eq!(trace_id("00-abc-def-01"), None);
+        assert_eq!(trace_id(""), None);
+    }
+}
@@ -1,29 +1,53 @@
 use anyhow::Result;
 use serde::de::DeserializeOwned;
 use std::collections::HashMap;
-use std::path::PathBuf;
-use std::sync::Arc;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
 
-use crate::composition::graph::ComponentGraph;
+use crate::composition::graph::{ComponentGraph, Node};
+use crate::composition::lockfile::LOCKFILE;
 use crate::composition::registry::{HostCapability, HostCapabilityFactory, build_registries};
 use crate::config::types::{ConfigHandler, DefinitionLoader};
 #[cfg(feature = "messaging")]
 use crate::message::MessagePublisher;
+use crate::metrics::{Metrics, MetricsService};
 use crate::service::Service;
 use crate::types::{Component, ComponentInvoker};
+use keyvalue::KeyValueStores;
 
 pub mod component;
+mod concurrency;
 pub(crate) mod conversion;
-mod grpc;
+pub(crate) mod dynamic_config;
 pub(crate) mod host;
+pub(crate) mod host_overrides;
+pub(crate) mod http_client;
+pub(crate) mod http_policy;
+mod http_proxy;
+pub(crate) mod keyvalue;
+mod lifecycle;
+pub(crate) mod limiter;
+mod logging;
+mod precompiled;
+pub(crate) mod socket_policy;
+pub(crate) mod tls;
 
 pub use component::{ComponentInstance, ComponentResource, Val};
 use host::ComponentHost;
 
+/// How often `--watch` checks the definition files for changes.
+const WATCH_INTERVAL: Duration = Duration::from_secs(1);
+
 /// Composable Runtime for invoking Wasm Components
 pub struct Runtime {
     host: ComponentHost,
+    source: DefinitionSource,
     services: Vec<Box<dyn Service>>,
+    started: AtomicBool,
+    metrics: Arc<Metrics>,
+    keyvalue: KeyValueStores,
     #[cfg(feature = "messaging")]
     publisher: Arc<dyn MessagePublisher>,
 }
@@ -38,12 +62,12 @@ impl Runtime {
     pub fn list_components(
         &self,
         selector: Option<&crate::config::types::Selector>,
-    ) -> Vec<&Component> {
+    ) -> Vec<Component> {
         self.host.list_components(selector)
     }
 
     /// Get a specific component by name
-    pub fn get_component(&self, name: &str) -> Option<&Component> {
+    pub fn get_component(&self, name: &str) -> Option<Component> {
         self.host.get_component(name)
     }
 
@@ -51,6 +75,8 @@ impl Runtime {
     ///
     /// Call one or more exports on it via [`ComponentInstance::call`].
     /// Resources it produces are no longer valid once the instance is dropped.
+    /// `env` takes precedence over the env vars of the component's `wasi:cli`
+    /// capability.
     pub async fn instantiate(
         &self,
         component_name: &str,
@@ -61,11 +87,44 @@ impl Runtime {
         self.host.instantiate(component_name, &env_pairs).await
     }
 
+    /// Write each component, fully composed and compiled, to `dir`, together
+    /// with a definitions file that loads them back without recomposing or
+    /// recompiling. Returns the path of that file.
+    ///
+    /// The definitions file covers the components, the capabilities they use
+    /// and the `[runtime.*]` engine settings; definitions for services are
+    /// not included. Its paths are relative to the current directory when
+    /// `dir` is.
+    pub fn compile_to(&self, dir: impl AsRef<Path>) -> Result<PathBuf> {
+        precompiled::write_bundle(&self.host, dir.as_ref())
+    }
+
+    /// Write a component's fully composed bytes to `path`, with a manifest
+    /// next to it (`path` with a `.toml` extension) that lists the interfaces
+    /// it still imports and the capabilities that provide them. Returns the
+    /// path of the manifest.
+    ///
+    /// The manifest is also a definitions file, so the composed component
+    /// can be loaded back as it is.
+    pub fn compose_to(&self, component_name: &str, path: impl AsRef<Path>) -> Result<PathBuf> {
+        precompiled::write_composed(&self.host, component_name, path.as_ref())
+    }
+
     /// Get a component invoker for this runtime.
     pub fn invoker(&self) -> Arc<dyn ComponentInvoker> {
         Arc::new(self.host.clone())
     }
 
+    /// The runtime's metrics in OpenMetrics text format, as served on
+    /// `/metrics` by any `[server.*]` definition with `type = "metrics"`.
+    ///
+    /// Covers invocation counts and latencies per component function,
+    /// instantiation latencies per component and, with messaging, queue
+    /// depths and dispatch latencies per channel.
+    pub fn metrics(&self) -> String {
+        self.metrics.render()
+    }
+
     /// Get a message publisher for this runtime (messaging feature only).
     #[cfg(feature = "messaging")]
     pub fn publisher(&self) -> Arc<dyn MessagePublisher> {
@@ -84,11 +143,51 @@ impl Runtime {
             service.set_publisher(Arc::clone(&self.publisher));
             service.start()?;
         }
+        self.started.store(true, Ordering::SeqCst);
         Ok(())
     }
 
+    /// Load the definitions again and switch to the components built from
+    /// them, then notify each service via [`Service::reload`] if the runtime
+    /// has been started.
+    ///
+    /// Invokers obtained earlier see the new components too. Invocations and
+    /// instances already in progress finish on the previous ones. If the
+    /// definitions fail to load, the previous components stay in place.
+    pub async fn reload(&self) -> Result<()> {
+        if !self.source.reloadable {
+            return Err(anyhow::anyhow!(
+                "Reload is not supported for runtimes built with custom definition loaders or config handlers"
+            ));
+        }
+        let (host, files) = load(
+            &self.source,
+            Vec::new(),
+            Vec::new(),
+            &self.services,
+            &self.metrics,
+            &self.keyvalue,
+        )
+        .await?;
+        self.host.replace(host);
+        *self.source.files.lock().unwrap() = files;
+        if !self.started.load(Ordering::SeqCst) {
+            return Ok(());
+        }
+
+        let mut result = Ok(());
+        for service in &self.services {
+            if let Err(e) = service.reload().await {
+                tracing::error!("Service reload failed: {e}");
+                result = result.and(Err(e));
+            }
+        }
+        result
+    }
+
     /// Shutdown all services in reverse registration order.
     pub async fn shutdown(&self) {
+        self.started.store(false, Ordering::SeqCst);
         for service in self.services.iter().rev() {
             service.shutdown().await;
         }
@@ -96,11 +195,42 @@ impl Runtime {
 
     /// Start the runtime and block until a shutdown signal (SIGINT/SIGTERM).
     ///
+    /// SIGHUP reloads the definitions, as does any change to the definition
+    /// files when built with [`RuntimeBuilder::watch`]. A failed reload is
+    /// logged and the runtime keeps running the previous definitions.
+    ///
     /// Intended for long-lived processes (`composable run`).
     /// For one-off invocations, use `start()` / `shutdown().await` directly.
     pub async fn run(&self) -> Result<()> {
         self.start()?;
-        wait_for_shutdown().await?;
+        let mut signals = Signals::new()?;
+        let mut watcher = self
+            .source
+            .watch
+            .then(|| FileWatcher::new(self.source.files.lock().unwrap().clone()));
+        loop {
+            let changed = async {
+                match watcher.as_mut() {
+                    Some(watcher) => watcher.changed().await,
+                    None => std::future::pending().await,
+                }
+            };
+            let reason = tokio::select! {
+                signal = signals.recv() => match signal? {
+                    Signal::Shutdown => break,
+                    Signal::Reload => "SIGHUP",
+                },
+                _ = changed => "definition files changed",
+            };
+            tracing::info!("Reloading definitions ({reason})");
+            match self.reload().await {
+                Ok(()) => tracing::info!("Reloaded definitions"),
+                Err(e) => tracing::error!("Failed to reload definitions: {e}"),
+            }
+            if let Some(watcher) = watcher.as_mut() {
+                *watcher = FileWatcher::new(self.source.files.lock().unwrap().clone());
+            }
+        }
         self.shutdown().await;
         Ok(())
     }
@@ -112,8 +242,10 @@ pub struct RuntimeBuilder {
     loaders: Vec<Box<dyn DefinitionLoader>>,
     handlers: Vec<Box<dyn ConfigHandler>>,
     services: Vec<Box<dyn Service>>,
-    factories: HashMap<&'static str, HostCapabilityFactory>,
+    capabilities: HashMap<&'static str, fn() -> HostCapabilityFactory>,
     use_default_loaders: bool,
+    watch: bool,
+    lockfile: Option<PathBuf>,
 }
 
 impl RuntimeBuilder {
@@ -123,12 +255,14 @@ impl RuntimeBuilder {
             loaders: Vec::new(),
             handlers: Vec::new(),
             services: Vec::new(),
-            factories: HashMap::new(),
+            capabilities: HashMap::new(),
             use_default_loaders: true,
+            watch: false,
+            lockfile: None,
         }
     }
 
-    /// Add a definition source path (.toml, .wasm, oci://, etc.)
+    /// Add a definition source path (.toml, .wasm, oci://, pkg:, etc.)
     pub fn from_path(mut self, path: impl Into<PathBuf>) -> Self {
         self.paths.push(path.into());
         self
@@ -158,6 +292,21 @@ impl RuntimeBuilder {
         self
     }
 
+    /// Reload from [`Runtime::run`] whenever a local definition file, or a
+    /// `.wasm` or precompiled file a definition refers to, changes.
+    pub fn watch(mut self) -> Self {
+        self.watch = true;
+        self
+    }
+
+    /// Pin component uris to the digests in the lockfile at `path`, written
+    /// by `composable lock`. Without this, `composable.lock` in the current
+    /// directory is used if it exists.
+    pub fn with_lockfile(mut self, path: impl Into<PathBuf>) -> Self {
+        self.lockfile = Some(path.into());
+        self
+    }
+
     /// Register a lifecycle-managed service.
     ///
     /// The service's config handler (if any) participates in config parsing.
@@ -178,94 +327,269 @@ impl RuntimeBuilder {
     where
         T: HostCapability + DeserializeOwned + Default + 'static,
     {
-        self.factories.insert(
-            name,
-            Box::new(
-                |config: serde_json::Value| -> Result<Box<dyn HostCapability>> {
-                    match serde_json::from_value::<T>(config.clone()) {
-                        Ok(instance) => Ok(Box::new(instance)),
-                        Err(e) => {
-                            if config == serde_json::json!({}) {
-                                Ok(Box::new(T::default()))
-                            } else {
-                                Err(e.into())
-                            }
-                        }
-                    }
-                },
-            ),
-        );
+        self.capabilities.insert(name, capability_factory::<T>);
         self
     }
 
     /// Build the Runtime: load config, build graph, build registries, create component host
-    #[allow(unused_mut)]
     pub async fn build(mut self) -> Result<Runtime> {
+        let metrics = Arc::new(Metrics::default());
+        self.services
+            .push(Box::new(MetricsService::new(Arc::clone(&metrics))));
+
         // Auto-register MessagingService when feature is enabled
         #[cfg(feature = "messaging")]
         let messaging_publisher: Arc<dyn MessagePublisher> = {
-            let svc = crate::messaging::MessagingService::new();
+            let svc = crate::messaging::MessagingService::new(Arc::clone(&metrics));
             let publisher = svc.publisher();
             self.services.push(Box::new(svc));
             publisher
         };
 
-        let mut graph_builder = ComponentGraph::builder().from_paths(&self.paths);
-        if !self.use_default_loaders {
-            graph_builder = graph_builder.no_default_loaders();
-        }
-        for loader in self.loaders {
-            graph_builder = graph_builder.add_loader(loader);
-        }
-        for handler in self.handlers {
+        let source = DefinitionSource {
+            paths: self.paths,
+            use_default_loaders: self.use_default_loaders,
+            reloadable: self.loaders.is_empty() && self.handlers.is_empty(),
+            capabilities: self.capabilities,
+            watch: self.watch,
+            lockfile: self
+                .lockfile
+                .or_else(|| Some(PathBuf::from(LOCKFILE)).filter(|path| path.is_file())),
+            files: Mutex::new(Vec::new()),
+        };
+        let keyvalue = KeyValueStores::default();
+        let (host, files) = load(
+            &source,
+            self.loaders,
+            self.handlers,
+            &self.services,
+            &metrics,
+            &keyvalue,
+        )
+        .await?;
+        *source.files.lock().unwrap() = files;
+
+        Ok(Runtime {
+            host,
+            source,
+            services: self.services,
+            started: AtomicBool::new(false),
+            metrics,
+            keyvalue,
+            #[cfg(feature = "messaging")]
+            publisher: messaging_publisher,
+        })
+    }
+}
+
+// What a runtime was built from, kept so that it can be loaded again.
+struct DefinitionSource {
+    paths: Vec<PathBuf>,
+    use_default_loaders: bool,
+    // Custom loaders and handlers are consumed by the first load.
+    reloadable: bool,
+    capabilities: HashMap<&'static str, fn() -> HostCapabilityFactory>,
+    watch: bool,
+    lockfile: Option<PathBuf>,
+    // Local files the current definitions were loaded from.
+    files: Mutex<Vec<PathBuf>>,
+}
+
+// Load config, build graph, build registries and create a component host.
+// Also returns the local files the definitions came from.
+async fn load(
+    source: &DefinitionSource,
+    loaders: Vec<Box<dyn DefinitionLoader>>,
+    handlers: Vec<Box<dyn ConfigHandler>>,
+    services: &[Box<dyn Service>],
+    metrics: &Arc<Metrics>,
+    keyvalue: &KeyValueStores,
+) -> Result<(ComponentHost, Vec<PathBuf>)> {
+    let mut graph_builder = ComponentGraph::builder().from_paths(&source.paths);
+    if !source.use_default_loaders {
+        graph_builder = graph_builder.no_default_loaders();
+    }
+    if let Some(lockfile) = &source.lockfile {
+        graph_builder = graph_builder.with_lockfile(lockfile);
+    }
+    for loader in loaders {
+        graph_builder = graph_builder.add_loader(loader);
+    }
+    for handler in handlers {
+        graph_builder = graph_builder.add_handler(handler);
+    }
+    // Add config handlers from registered services
+    for service in services {
+        if let Some(handler) = service.config_handler() {
             graph_builder = graph_builder.add_handler(handler);
         }
-        // Add config handlers from registered services
-        for service in &self.services {
-            if let Some(handler) = service.config_handler() {
-                graph_builder = graph_builder.add_handler(handler);
-            }
+    }
+    let graph = graph_builder.build()?;
+
+    // Collect capability factories from both with_capability and service registrations
+    let mut factories: HashMap<&'static str, HostCapabilityFactory> = source
+        .capabilities
+        .iter()
+        .map(|(name, factory)| (*name, factory()))
+        .collect();
+    for service in services {
+        for (name, factory) in service.capabilities() {
+            factories.insert(name, factory);
         }
-        let graph = graph_builder.build()?;
+    }
 
-        // Collect capability factories from both with_capability and service registrations
-        let mut factories = self.factories;
-        for service in &self.services {
-            for (name, factory) in service.capabilities() {
-                factories.insert(name, factory);
+    // Build registries from graph
+    let (component_registry, capability_registry) = build_registries(&graph, factories).await?;
+
+    let keyvalue = keyvalue.contexts(&capability_registry)?;
+
+    // Create component host
+    let host = ComponentHost::new(
+        graph.runtime_config().clone(),
+        component_registry,
+        capability_registry,
+        keyvalue,
+        Arc::clone(metrics),
+    )?;
+
+    // Everything loaded, so services can adopt what their handlers parsed.
+    for service in services {
+        service.commit_config();
+    }
+
+    let mut files = local_files(&source.paths, &graph);
+    files.extend(source.lockfile.clone());
+    Ok((host, files))
+}
+
+fn local_files(paths: &[PathBuf], graph: &ComponentGraph) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = paths
+        .iter()
+        .filter(|path| !is_remote(&path.to_string_lossy()))
+        .cloned()
+        .collect();
+    for node in graph.nodes() {
+        if let Node::Component(definition) = &node.weight {
+            let uri = definition.uri.as_str();
+            if !is_remote(uri) {
+                files.push(PathBuf::from(uri.strip_prefix("file://").unwrap_or(uri)));
+            }
+            if let Some(precompiled) = &definition.precompiled {
+                files.push(PathBuf::from(precompiled));
             }
         }
+    }
+    files.sort();
+    files.dedup();
+    files
+}
 
-        // Build registries from graph
-        let (component_registry, capability_registry) = build_registries(&graph, factories).await?;
+fn is_remote(uri: &str) -> bool {
+    uri.starts_with("oci://") || uri.starts_with("pkg:")
+}
 
-        // Create component host
-        let host = ComponentHost::new(component_registry, capability_registry)?;
+fn capability_factory<T>() -> HostCapabilityFactory
+where
+    T: HostCapability + DeserializeOwned + Default + 'static,
+{
+    Box::new(
+        |config: serde_json::Value| -> Result<Box<dyn HostCapability>> {
+            match serde_json::from_value::<T>(config.clone()) {
+                Ok(instance) => Ok(Box::new(instance)),
+                Err(e) => {
+                    if config == serde_json::json!({}) {
+                        Ok(Box::new(T::default()))
+                    } else {
+                        Err(e.into())
+                    }
+                }
+            }
+        },
+    )
+}
 
-        Ok(Runtime {
-            host,
-            services: self.services,
-            #[cfg(feature = "messaging")]
-            publisher: messaging_publisher,
-        })
+// Polls the modification times of a set of files.
+struct FileWatcher {
+    files: Vec<(PathBuf, Option<SystemTime>)>,
+}
+
+impl FileWatcher {
+    fn new(paths: Vec<PathBuf>) -> Self {
+        Self {
+            files: snapshot(paths),
+        }
     }
+
+    // Resolves once a file has been modified, created or removed, and has
+    // then stayed unchanged for one interval, so a file still being written
+    // is not loaded half-way.
+    async fn changed(&mut self) {
+        let mut changed = false;
+        loop {
+            tokio::time::sleep(WATCH_INTERVAL).await;
+            let current = snapshot(self.files.iter().map(|(path, _)| path.clone()));
+            if current != self.files {
+                self.files = current;
+                changed = true;
+            } else if changed {
+                return;
+            }
+        }
+    }
+}
+
+fn snapshot(paths: impl IntoIterator<Item = PathBuf>) -> Vec<(PathBuf, Option<SystemTime>)> {
+    paths
+        .into_iter()
+        .map(|path| {
+            let modified = std::fs::metadata(&path).and_then(|m| m.modified()).ok();
+            (path, modified)
+        })
+        .collect()
 }
 
-async fn wait_for_shutdown() -> Result<()> {
-    let ctrl_c = tokio::signal::ctrl_c();
+enum Signal {
+    Shutdown,
+    Reload,
+}
 
+// Registered once for the whole of `Runtime::run`, so a signal arriving
+// while a reload is in progress is still received afterwards.
+struct Signals {
     #[cfg(unix)]
-    {
-        let mut sigterm =
-            tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())?;
+    sigterm: tokio::signal::unix::Signal,
+    #[cfg(unix)]
+    sighup: tokio::signal::unix::Signal,
+}
+
+impl Signals {
+    fn new() -> Result<Self> {
+        #[cfg(unix)]
+        {
+            use tokio::signal::unix::{SignalKind, signal};
+            Ok(Self {
+                sigterm: signal(SignalKind::terminate())?,
+                sighup: signal(SignalKind::hangup())?,
+            })
+        }
+
+        #[cfg(not(unix))]
+        Ok(Self {})
+    }
+
+    async fn recv(&mut self) -> Result<Signal> {
+        let ctrl_c = tokio::signal::ctrl_c();
+
+        #[cfg(unix)]
         tokio::select! {
             result = ctrl_c => result?,
-            _ = sigterm.recv() => {}
+            _ = self.sigterm.recv() => {}
+            _ = self.sighup.recv() => return Ok(Signal::Reload),
         }
-    }
 
-    #[cfg(not(unix))]
-    ctrl_c.await?;
+        #[cfg(not(unix))]
+        ctrl_c.await?;
 
-    Ok(())
+        Ok(Signal::Shutdown)
+    }
 }
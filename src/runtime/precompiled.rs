@@ -0,0 +1,287 @@
+//! Ahead-of-time compiled bundles, written by `composable compile`, and
+//! single composed components, written by `composable compose`.
+//!
+//! A bundle holds each component's fully composed bytes (`<name>.wasm`) and
+//! their compiled form (`<name>.cwasm`), plus a definitions file that loads
+//! them back. The composed bytes already include each component's static
+//! config, dependencies and interceptors, so loading the bundle skips
+//! composition (a component with dynamic config keeps its `config` table),
+//! and each component names its `precompiled` artifact, so it skips
+//! compilation too. Each component's `digest` pins its composed bytes, so an
+//! artifact is never run for bytes it was not compiled from, and its
+//! `precompiled-digest` pins the artifact itself, which is checked before it
+//! is deserialized. Paths are relative to the definitions file, so the
+//! directory can be moved.
+//!
+//! Capability properties and dynamic `config` tables are written as they
+//! were, with `${...}` placeholders unresolved, so neither a bundle nor a
+//! manifest holds the secrets they resolve to.
+//!
+//! A composed component is written without its compiled form, for other
+//! component hosts. Its manifest is a definitions file holding just that
+//! component and the capabilities it still needs, headed by a comment that
+//! lists the interfaces it imports.
+
+use anyhow::Result;
+use std::path::{Path, PathBuf};
+
+use crate::composition::lockfile::sha256_digest;
+use crate::composition::registry::{Capability, ComponentSpec};
+use crate::runtime::host::ComponentHost;
+use crate::types::{ComponentLimits, Lifecycle, OptLevel, RuntimeConfig};
+
+/// Name of the definitions file in a bundle directory.
+const DEFINITIONS_FILE: &str = "components.toml";
+
+/// Write every component, with the capabilities it uses and the engine
+/// settings its artifact was compiled for, to `dir`. Returns the path of the
+/// definitions file.
+pub(crate) fn write_bundle(host: &ComponentHost, dir: &Path) -> Result<PathBuf> {
+    std::fs::create_dir_all(dir)?;
+    let host = host.state();
+
+    let mut components = toml::Table::new();
+    for spec in host.component_registry.get_components() {
+        let uri = PathBuf::from(format!("{}.wasm", spec.name));
+        let precompiled = PathBuf::from(format!("{}.cwasm", spec.name));
+        let artifact = host
+            .compiled_component(&spec.name)?
+            .serialize()
+            .map_err(|e| anyhow::anyhow!("Failed to serialize component '{}': {e}", spec.name))?;
+        std::fs::write(dir.join(&uri), &spec.bytes)?;
+        std::fs::write(dir.join(&precompiled), &artifact)?;
+        let mut table = component_table(spec, &uri, Some(&precompiled))?;
+        table.insert("digest".to_string(), sha256_digest(&spec.bytes).into());
+        table.insert(
+            "precompiled-digest".to_string(),
+            sha256_digest(&artifact).into(),
+        );
+        components.insert(spec.name.clone(), table.into());
+    }
+
+    let mut capabilities = toml::Table::new();
+    for (name, capability) in host.capability_registry.capabilities.iter() {
+        let table = capability_table(capability)
+            .map_err(|e| anyhow::anyhow!("Cannot write capability '{name}' to the bundle: {e}"))?;
+        capabilities.insert(name.clone(), table.into());
+    }
+
+    let mut definitions = toml::Table::new();
+    let runtime = runtime_table(&host.runtime_config);
+    if !runtime.is_empty() {
+        definitions.insert("runtime".to_string(), runtime.into());
+    }
+    if !capabilities.is_empty() {
+        definitions.insert("capability".to_string(), capabilities.into());
+    }
+    definitions.insert("component".to_string(), components.into());
+
+    let path = dir.join(DEFINITIONS_FILE);
+    std::fs::write(&path, toml::to_string(&definitions)?)?;
+    Ok(path)
+}
+
+/// Write the composed bytes of `component_name` to `path`, and its manifest
+/// next to it with a `.toml` extension. Returns the path of the manifest.
+pub(crate) fn write_composed(
+    host: &ComponentHost,
+    component_name: &str,
+    path: &Path,
+) -> Result<PathBuf> {
+    let host = host.state();
+    let spec = host
+        .component_registry
+        .get_component(component_name)
+        .ok_or_else(|| anyhow::anyhow!("Component '{component_name}' not found"))?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    std::fs::write(path, &spec.bytes)?;
+
+    let mut capabilities = toml::Table::new();
+    for name in &spec.capabilities {
+        let Some(capability) = host.capability_registry.get_capability(name) else {
+            continue;
+        };
+        let table = capability_table(capability).map_err(|e| {
+            anyhow::anyhow!("Cannot write capability '{name}' to the manifest: {e}")
+        })?;
+        capabilities.insert(name.clone(), table.into());
+    }
+
+    // The manifest is written next to the bytes.
+    let uri = path.file_name().map_or(path, Path::new);
+    let mut components = toml::Table::new();
+    components.insert(spec.name.clone(), component_table(spec, uri, None)?.into());
+    let mut definitions = toml::Table::new();
+    if !capabilities.is_empty() {
+        definitions.insert("capability".to_string(), capabilities.into());
+    }
+    definitions.insert("component".to_string(), components.into());
+
+    let mut manifest = format!("# Composed component '{}'\n", spec.name);
+    let mut imports = spec.imports.clone();
+    imports.sort();
+    if imports.is_empty() {
+        manifest.push_str("# Imports: none\n");
+    } else {
+        manifest.push_str("# Imports:\n");
+        for import in &imports {
+            manifest.push_str(&format!("#   {import}\n"));
+        }
+    }
+    manifest.push('\n');
+    manifest.push_str(&toml::to_string(&definitions)?);
+
+    let manifest_path = path.with_extension("toml");
+    std::fs::write(&manifest_path, manifest)?;
+    Ok(manifest_path)
+}
+
+// The component's dependencies are composed in, so it only imports the
+// capabilities they needed.
+fn component_table(
+    spec: &ComponentSpec,
+    uri: &Path,
+    precompiled: Option<&Path>,
+) -> Result<toml::Table> {
+    let mut table = toml::Table::new();
+    table.insert("uri".to_string(), uri.display().to_string().into());
+    if let Some(precompiled) = precompiled {
+        table.insert(
+            "precompiled".to_string(),
+            precompiled.display().to_string().into(),
+        );
+    }
+    let mut imports = spec.capabilities.clone();
+    imports.sort();
+    if !imports.is_empty() {
+        table.insert("imports".to_string(), imports.into());
+    }
+    if !spec.labels.is_empty() {
+        let labels: toml::Table = spec
+            .labels
+            .iter()
+            .map(|(k, v)| (k.clone(), v.clone().into()))
+            .collect();
+        table.insert("labels".to_string(), labels.into());
+    }
+    // A component still importing `wasi:config/store` uses dynamic config:
+    // in static mode its config is composed in and the import dropped.
+    if spec
+        .imports
+        .iter()
+        .any(|import| import.starts_with("wasi:config/store"))
+    {
+        table.insert("config-mode".to_string(), "dynamic".into());
+        if !spec.unresolved_config.is_empty() {
+            // Keys are already flattened, so they are written quoted and
+            // flatten to themselves when read back. Placeholders are kept,
+            // so resolved secrets are not written out.
+            let config: toml::Table = spec
+                .unresolved_config
+                .iter()
+                .map(|(k, v)| (k.clone(), v.clone().into()))
+                .collect();
+            table.insert("config".to_string(), config.into());
+        }
+    }
+    for (key, value) in limit_properties(&spec.limits) {
+        if let Some(value) = value {
+            table.insert(key.to_string(), integer(key, value)?);
+        }
+    }
+    match spec.lifecycle {
+        Lifecycle::PerInvocation => {}
+        Lifecycle::Singleton { max_calls } => {
+            table.insert("lifecycle".to_string(), "singleton".into());
+            if let Some(max_calls) = max_calls {
+                table.insert("max-calls".to_string(), integer("max-calls", max_calls)?);
+            }
+        }
+        Lifecycle::Pool { size, max_calls } => {
+            table.insert("lifecycle".to_string(), "pool".into());
+            table.insert("pool-size".to_string(), i64::from(size).into());
+            if let Some(max_calls) = max_calls {
+                table.insert("max-calls".to_string(), integer("max-calls", max_calls)?);
+            }
+        }
+    }
+    Ok(table)
+}
+
+fn limit_properties(limits: &ComponentLimits) -> [(&'static str, Option<u64>); 8] {
+    [
+        ("timeout-ms", limits.timeout_ms),
+        ("fuel", limits.fuel),
+        ("max-memory-bytes", limits.max_memory_bytes),
+        ("max-tables", limits.max_tables.map(u64::from)),
+        ("max-table-elements", limits.max_table_elements),
+        ("max-instances", limits.max_instances.map(u64::from)),
+        ("max-concurrency", limits.max_concurrency.map(u64::from)),
+        ("max-queue", limits.max_queue.map(u64::from)),
+    ]
+}
+
+fn capability_table(capability: &Capability) -> Result<toml::Table> {
+    let mut table = toml::Table::new();
+    table.insert("type".to_string(), capability.kind.clone().into());
+    table.insert("scope".to_string(), capability.scope.clone().into());
+    // As written, so values resolved from placeholders are not written out.
+    for (key, value) in &capability.unresolved_properties {
+        table.insert(key.clone(), toml::Value::try_from(value)?);
+    }
+    Ok(table)
+}
+
+fn runtime_table(config: &RuntimeConfig) -> toml::Table {
+    let mut engine = toml::Table::new();
+    if let Some(cache_dir) = &config.cache_dir {
+        engine.insert(
+            "cache-dir".to_string(),
+            cache_dir.display().to_string().into(),
+        );
+    }
+    if config.debug_info {
+        engine.insert("debug-info".to_string(), true.into());
+    }
+    if let Some(opt_level) = config.opt_level {
+        let opt_level = match opt_level {
+            OptLevel::None => "none",
+            OptLevel::Speed => "speed",
+            OptLevel::SpeedAndSize => "speed-and-size",
+        };
+        engine.insert("opt-level".to_string(), opt_level.into());
+    }
+
+    let mut runtime = toml::Table::new();
+    if !engine.is_empty() {
+        runtime.insert("engine".to_string(), engine.into());
+    }
+    if let Some(pooling) = &config.pooling {
+        let mut allocator = toml::Table::new();
+        allocator.insert("strategy".to_string(), "pooling".into());
+        let limits = [
+            (
+                "total-component-instances",
+                pooling.total_component_instances,
+            ),
+            ("total-memories", pooling.total_memories),
+            ("total-tables", pooling.total_tables),
+        ];
+        for (key, value) in limits {
+            if let Some(value) = value {
+                allocator.insert(key.to_string(), i64::from(value).into());
+            }
+        }
+        runtime.insert("allocator".to_string(), allocator.into());
+    }
+    runtime
+}
+
+// TOML integers are signed 64-bit.
+fn integer(key: &str, value: u64) -> Result<toml::Value> {
+    i64::try_from(value)
+        .map(toml::Value::from)
+        .map_err(|_| anyhow::anyhow!("'{key}' value {value} does not fit in a TOML integer"))
+}
@@ -0,0 +1,213 @@
+//! Address allow-lists of a `wasi:sockets` capability.
+//!
+//! ```toml
+//! [capability.db-net]
+//! type = "wasi:sockets"
+//! allow-connect = ["10.0.4.12:5432", "[fd00::/64]:5432"]
+//! allow-bind = ["127.0.0.1:8000-8099"]
+//! allow-udp = ["10.0.0.0/8:53"]
+//! ```
+//!
+//! A rule is an address or CIDR range, or `*` for any, followed by a port,
+//! a range of ports, or `*`. Without a port it matches every port; an IPv6
+//! address or range with a port is written in brackets. `allow-connect`
+//! covers TCP connections, `allow-bind` TCP listeners and `allow-udp` every
+//! UDP bind, connect and datagram.
+//!
+//! Setting any of the lists grants network access to what they allow and
+//! nothing else, whether or not `inherit-network` is set. Denied uses are
+//! logged.
+
+use anyhow::Result;
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::ops::RangeInclusive;
+use wasmtime_wasi::sockets::SocketAddrUse;
+
+use crate::runtime::http_policy::Cidr;
+
+#[derive(Debug, Default)]
+pub(crate) struct SocketPolicy {
+    connect: Vec<Rule>,
+    bind: Vec<Rule>,
+    udp: Vec<Rule>,
+}
+
+#[derive(Debug)]
+struct Rule {
+    // `None` matches any address.
+    cidr: Option<Cidr>,
+    ports: RangeInclusive<u16>,
+}
+
+impl SocketPolicy {
+    /// The capability's policy, if it sets any of the allow-lists.
+    pub(crate) fn from_properties(
+        capability_name: &str,
+        props: &HashMap<String, serde_json::Value>,
+    ) -> Result<Option<Self>> {
+        let rules = |key: &str| -> Result<Option<Vec<Rule>>> {
+            let Some(value) = props.get(key) else {
+                return Ok(None);
+            };
+            let entries = value.as_array().ok_or_else(|| {
+                anyhow::anyhow!(
+                    "Capability '{capability_name}': '{key}' must be an array of strings"
+                )
+            })?;
+            entries
+                .iter()
+                .map(|entry| {
+                    let entry = entry.as_str().ok_or_else(|| {
+                        anyhow::anyhow!(
+                            "Capability '{capability_name}': '{key}' must be an array of strings"
+                        )
+                    })?;
+                    Rule::parse(entry).map_err(|e| {
+                        anyhow::anyhow!("Capability '{capability_name}': '{key}': {e}")
+                    })
+                })
+                .collect::<Result<_>>()
+                .map(Some)
+        };
+        let (connect, bind, udp) = (
+            rules("allow-connect")?,
+            rules("allow-bind")?,
+            rules("allow-udp")?,
+        );
+        if connect.is_none() && bind.is_none() && udp.is_none() {
+            return Ok(None);
+        }
+        Ok(Some(Self {
+            connect: connect.unwrap_or_default(),
+            bind: bind.unwrap_or_default(),
+            udp: udp.unwrap_or_default(),
+        }))
+    }
+
+    /// Whether the guest may use `addr` as `addr_use`.
+    pub(crate) fn allows(&self, addr: SocketAddr, addr_use: SocketAddrUse) -> bool {
+        let rules = match addr_use {
+            SocketAddrUse::TcpConnect => &self.connect,
+            SocketAddrUse::TcpBind => &self.bind,
+            SocketAddrUse::UdpBind
+            | SocketAddrUse::UdpConnect
+            | SocketAddrUse::UdpOutgoingDatagram => &self.udp,
+        };
+        rules.iter().any(|rule| {
+            rule.ports.contains(&addr.port())
+                && rule
+                    .cidr
+                    .as_ref()
+                    .is_none_or(|cidr| cidr.contains(addr.ip()))
+        })
+    }
+}
+
+impl Rule {
+    fn parse(rule: &str) -> Result<Self, String> {
+        let invalid = || format!("invalid rule '{rule}'");
+        // Only a bracketed IPv6 address or range, or one without colons, has a
+        // port.
+        let (address, ports) = if let Some(rest) = rule.strip_prefix('[') {
+            let (address, rest) = rest.split_once(']').ok_or_else(invalid)?;
+            match rest {
+                "" => (address, None),
+                _ => (address, Some(rest.strip_prefix(':').ok_or_else(invalid)?)),
+            }
+        } else if rule.matches(':').count() == 1 {
+            let (address, ports) = rule.split_once(':').ok_or_else(invalid)?;
+            (address, Some(ports))
+        } else {
+            (rule, None)
+        };
+        let cidr = match address {
+            "*" => None,
+            address => Some(Cidr::parse(address).map_err(|_| invalid())?),
+        };
+        let ports = match ports {
+            None | Some("*") => 0..=u16::MAX,
+            Some(ports) => {
+                let port = |port: &str| port.parse::<u16>().map_err(|_| invalid());
+                match ports.split_once('-') {
+                    Some((start, end)) => {
+                        let (start, end) = (port(start)?, port(end)?);
+                        if start > end {
+                            return Err(invalid());
+                        }
+                        start..=end
+                    }
+                    None => {
+                        let port = port(ports)?;
+                        port..=port
+                    }
+                }
+            }
+        };
+        Ok(Self { cidr, ports })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(props: serde_json::Value) -> SocketPolicy {
+        let props = serde_json::from_value(props).unwrap();
+        SocketPolicy::from_properties("net", &props)
+            .unwrap()
+            .unwrap()
+    }
+
+    #[test]
+    fn matches_addresses_and_ports_per_use() {
+        let policy = policy(serde_json::json!({
+            "allow-connect": ["10.0.4.12:5432", "[fd00::/64]:5432", "192.168.0.0/16"],
+            "allow-bind": ["*:8000-8099"]
+        }));
+        let addr = |addr: &str| addr.parse::<SocketAddr>().unwrap();
+        assert!(policy.allows(addr("10.0.4.12:5432"), SocketAddrUse::TcpConnect));
+        assert!(!policy.allows(addr("10.0.4.12:5433"), SocketAddrUse::TcpConnect));
+        assert!(!policy.allows(addr("10.0.4.13:5432"), SocketAddrUse::TcpConnect));
+        assert!(policy.allows(addr("[fd00::12]:5432"), SocketAddrUse::TcpConnect));
+        assert!(policy.allows(addr("[::ffff:10.0.4.12]:5432"), SocketAddrUse::TcpConnect));
+        assert!(policy.allows(addr("192.168.1.1:22"), SocketAddrUse::TcpConnect));
+        assert!(!policy.allows(addr("10.0.4.12:5432"), SocketAddrUse::TcpBind));
+        assert!(policy.allows(addr("0.0.0.0:8080"), SocketAddrUse::TcpBind));
+        assert!(!policy.allows(addr("0.0.0.0:8100"), SocketAddrUse::TcpBind));
+        // No `allow-udp`, so no UDP at all.
+        assert!(!policy.allows(addr("10.0.4.12:53"), SocketAddrUse::UdpOutgoingDatagram));
+    }
+
+    #[test]
+    fn rejects_invalid_properties() {
+        let err = |props: serde_json::Value| {
+            let props = serde_json::from_value(props).unwrap();
+            SocketPolicy::from_properties("net", &props)
+                .unwrap_err()
+                .to_string()
+        };
+        assert!(
+            err(serde_json::json!({"allow-connect": "10.0.0.1"}))
+                .contains("'allow-connect' must be an array of strings")
+        );
+        for rule in [
+            "10.0.0.1:99999",
+            "10.0.0.0/33",
+            "[::1]5432",
+            "*:90-80",
+            "db:5432",
+        ] {
+            assert!(
+                err(serde_json::json!({"allow-udp": [rule]}))
+                    .contains(&format!("'allow-udp': invalid rule '{rule}'")),
+                "{rule}"
+            );
+        }
+        assert!(
+            SocketPolicy::from_properties("net", &HashMap::new())
+                .unwrap()
+                .is_none()
+        );
+    }
+}
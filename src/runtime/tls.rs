@@ -0,0 +1,129 @@
+//! TLS client settings of a `wasi:http` capability.
+//!
+//! ```toml
+//! [capability.http]
+//! type = "wasi:http"
+//! ca-bundle = "./certs/internal-ca.pem"     # trusted in addition to the web PKI roots
+//! client-cert = "./certs/client.pem"        # presented for mutual TLS,
+//! client-key = "./certs/client-key.pem"     # together with its private key
+//! ```
+//!
+//! Paths name PEM files. They are read when the runtime is built, so a
+//! missing or invalid file fails the build rather than the first request.
+
+use anyhow::Result;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+
+/// Build the client config for a `wasi:http` capability, offering the given
+/// ALPN protocols.
+pub(crate) fn client_config(
+    capability_name: &str,
+    props: &HashMap<String, serde_json::Value>,
+    alpn_protocols: Vec<Vec<u8>>,
+) -> Result<rustls::ClientConfig> {
+    let path = |key: &str| match props.get(key) {
+        None => Ok(None),
+        Some(serde_json::Value::String(path)) => Ok(Some(path.as_str())),
+        Some(other) => Err(anyhow::anyhow!(
+            "Capability '{capability_name}': '{key}' must be a string, got {other}"
+        )),
+    };
+
+    let mut roots = rustls::RootCertStore {
+        roots: webpki_roots::TLS_SERVER_ROOTS.into(),
+    };
+    if let Some(ca_bundle) = path("ca-bundle")? {
+        let certs = read_certs(ca_bundle).map_err(|e| {
+            anyhow::anyhow!(
+                "Capability '{capability_name}': invalid 'ca-bundle' '{ca_bundle}': {e}"
+            )
+        })?;
+        for cert in certs {
+            roots.add(cert).map_err(|e| {
+                anyhow::anyhow!(
+                    "Capability '{capability_name}': invalid 'ca-bundle' '{ca_bundle}': {e}"
+                )
+            })?;
+        }
+    }
+
+    // An explicit provider, so embedders need not install a process default.
+    let builder = rustls::ClientConfig::builder_with_provider(Arc::new(
+        rustls::crypto::aws_lc_rs::default_provider(),
+    ))
+    .with_safe_default_protocol_versions()?
+    .with_root_certificates(roots);
+    let mut config = match (path("client-cert")?, path("client-key")?) {
+        (None, None) => builder.with_no_client_auth(),
+        (Some(cert), Some(key)) => {
+            let certs = read_certs(cert).map_err(|e| {
+                anyhow::anyhow!(
+                    "Capability '{capability_name}': invalid 'client-cert' '{cert}': {e}"
+                )
+            })?;
+            let key = PrivateKeyDer::from_pem_file(key).map_err(|e| {
+                anyhow::anyhow!("Capability '{capability_name}': invalid 'client-key' '{key}': {e}")
+            })?;
+            builder.with_client_auth_cert(certs, key).map_err(|e| {
+                anyhow::anyhow!(
+                    "Capability '{capability_name}': 'client-cert' does not match 'client-key': {e}"
+                )
+            })?
+        }
+        _ => anyhow::bail!(
+            "Capability '{capability_name}': 'client-cert' and 'client-key' must be set together"
+        ),
+    };
+    config.alpn_protocols = alpn_protocols;
+    Ok(config)
+}
+
+fn read_certs(path: &str) -> Result<Vec<CertificateDer<'static>>> {
+    let certs = CertificateDer::pem_file_iter(path)?.collect::<Result<Vec<_>, _>>()?;
+    if certs.is_empty() {
+        anyhow::bail!("no certificates found");
+    }
+    Ok(certs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn err(props: serde_json::Value) -> String {
+        let props = serde_json::from_value(props).unwrap();
+        client_config("http", &props, Vec::new())
+            .unwrap_err()
+            .to_string()
+    }
+
+    #[test]
+    fn defaults_to_web_pki_roots() {
+        let config = client_config("http", &HashMap::new(), vec![b"h2".to_vec()]).unwrap();
+        assert_eq!(config.alpn_protocols, [b"h2".to_vec()]);
+        assert!(!config.client_auth_cert_resolver.has_certs());
+    }
+
+    #[test]
+    fn rejects_invalid_properties() {
+        let dir = tempfile::tempdir().unwrap();
+        let empty = dir.path().join("empty.pem");
+        std::fs::write(&empty, "").unwrap();
+
+        let missing = dir.path().join("missing.pem");
+        assert!(
+            err(serde_json::json!({"ca-bundle": missing})).contains("invalid 'ca-bundle'"),
+            "missing file"
+        );
+        assert!(err(serde_json::json!({"ca-bundle": empty})).contains("no certificates found"));
+        assert!(err(serde_json::json!({"ca-bundle": 1})).contains("must be a string"));
+        assert!(
+            err(serde_json::json!({"client-cert": "client.pem"}))
+                .contains("'client-cert' and 'client-key' must be set together")
+        );
+    }
+}
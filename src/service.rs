@@ -21,6 +21,11 @@ use crate::types::ComponentInvoker;
 /// config processing, the handler is dropped and the service can read the
 /// accumulated state in its `capabilities()` and `start()` implementations.
 ///
+/// On reload, `config_handler()` is called again for the new definitions.
+/// Those may still fail to load, so a handler should write to state that the
+/// service only adopts in `commit_config()`, which is called once the
+/// definitions have loaded. A failed reload leaves the service as it was.
+///
 /// Dependencies are injected via `set_*` methods before `start()` is called.
 /// Override only the ones your service needs; all have default no-ops.
 pub trait Service: Send + Sync {
@@ -30,6 +35,11 @@ pub trait Service: Send + Sync {
         None
     }
 
+    /// Adopt the config parsed by the handler from the last `config_handler()`
+    /// call. Called once the definitions have loaded, before `start()` or
+    /// `reload()`, and not at all when they fail to load.
+    fn commit_config(&self) {}
+
     /// Provide any HostCapability factories to register (default is empty).
     /// Called after config parsing and before registry build.
     /// Each factory creates capability instances from `config.*` values,
@@ -53,6 +63,14 @@ pub trait Service: Send + Sync {
         Ok(())
     }
 
+    /// Called after the runtime reloads its definitions. By then this
+    /// service's config handler has parsed the reloaded definitions and the
+    /// injected invoker serves the reloaded components. Override to rebuild
+    /// anything derived from either, such as routes or subscriptions.
+    fn reload(&self) -> Pin<Box<dyn Future<Output = Result<()>> + Send + '_>> {
+        Box::pin(async { Ok(()) })
+    }
+
     /// Shutdown the service, cancelling background tasks.
     fn shutdown(&self) -> Pin<Box<dyn Future<Output = ()> + Send + '_>> {
         Box::pin(async {})
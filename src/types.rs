@@ -21,6 +21,9 @@ pub struct CapabilityDefinition {
     pub kind: String,
     pub scope: String,
     pub properties: HashMap<String, serde_json::Value>,
+    /// `properties` as written, before `${...}` placeholders are resolved,
+    /// so that writing them back out does not leak what they resolved to.
+    pub unresolved_properties: HashMap<String, serde_json::Value>,
 }
 
 /// Component definition.
@@ -32,36 +35,456 @@ pub struct ComponentDefinition {
     pub imports: Vec<String>,
     pub interceptors: Vec<String>,
     pub config: HashMap<String, serde_json::Value>,
+    /// `config` as written, before `${...}` placeholders are resolved.
+    pub unresolved_config: HashMap<String, serde_json::Value>,
     pub labels: HashMap<String, String>,
+    pub limits: ComponentLimits,
+    pub lifecycle: Lifecycle,
+    pub precompiled: Option<String>,
+    /// SHA-256 digest the `precompiled` artifact must have, from the
+    /// `precompiled-digest` property or the lockfile.
+    pub precompiled_digest: Option<String>,
+    pub config_mode: ConfigMode,
+    /// Credentials for pulling an `oci://` uri. When unset, the Docker
+    /// config file is used.
+    pub auth: Option<RegistryCredentials>,
+    /// SHA-256 digest (`sha256:<hex>`) the bytes at `uri` must have, from
+    /// the `digest` property or the lockfile.
+    pub digest: Option<String>,
+}
+
+/// Credentials for an OCI registry, from a component's `auth` table.
+#[derive(Clone, PartialEq, Eq)]
+pub enum RegistryCredentials {
+    Basic { username: String, password: String },
+    Bearer { token: String },
+}
+
+// Definitions are printed by `composable graph`, so secrets are left out.
+impl std::fmt::Debug for RegistryCredentials {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Basic { username, .. } => f
+                .debug_struct("Basic")
+                .field("username", username)
+                .finish_non_exhaustive(),
+            Self::Bearer { .. } => f.debug_struct("Bearer").finish_non_exhaustive(),
+        }
+    }
+}
+
+/// How a component that imports `wasi:config/store` gets its `config` table.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ConfigMode {
+    /// Composed into the component bytes when the component is built.
+    #[default]
+    Static,
+    /// Served at call time by a `wasi:config` capability, so values can
+    /// change without recomposing the component.
+    Dynamic,
+}
+
+/// How instances of a component are created and reused across invocations.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Lifecycle {
+    /// A fresh instance for each invocation, dropped once the call returns.
+    #[default]
+    PerInvocation,
+    /// One instance shared by all invocations, which are serialized.
+    Singleton { max_calls: Option<u64> },
+    /// Up to `size` instances, each serving one invocation at a time.
+    Pool { size: u32, max_calls: Option<u64> },
+}
+
+/// Execution and resource limits applied to a component instance.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ComponentLimits {
+    /// Wall-clock deadline per call, in milliseconds (`timeout-ms`).
+    pub timeout_ms: Option<u64>,
+    /// Fuel budget per call (`fuel`).
+    pub fuel: Option<u64>,
+    /// Total size of all linear memories of an instance (`max-memory-bytes`).
+    pub max_memory_bytes: Option<u64>,
+    /// Number of tables an instance may create (`max-tables`).
+    pub max_tables: Option<u32>,
+    /// Number of elements any single table may grow to (`max-table-elements`).
+    pub max_table_elements: Option<u64>,
+    /// Number of core instances an instance may create (`max-instances`),
+    /// counting those of nested components once per instantiation of them.
+    pub max_instances: Option<u32>,
+    /// Number of invocations or live instances at once (`max-concurrency`).
+    pub max_concurrency: Option<u32>,
+    /// Number of callers that may wait once `max-concurrency` is reached
+    /// (`max-queue`). Unbounded when unset.
+    pub max_queue: Option<u32>,
+}
+
+impl ComponentLimits {
+    /// Whether any limit enforced by the store's resource limiter is set.
+    pub fn has_resource_limits(&self) -> bool {
+        self.max_memory_bytes.is_some()
+            || self.max_tables.is_some()
+            || self.max_table_elements.is_some()
+            || self.max_instances.is_some()
+    }
+}
+
+/// A call into a component was stopped for exceeding one of its
+/// [`ComponentLimits`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExecutionLimitExceeded {
+    /// The call ran past its `timeout-ms` deadline.
+    Timeout { component: String, timeout_ms: u64 },
+    /// The call consumed its entire `fuel` budget.
+    OutOfFuel { component: String, fuel: u64 },
+}
+
+impl fmt::Display for ExecutionLimitExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Timeout {
+                component,
+                timeout_ms,
+            } => write!(f, "component '{component}' timed out after {timeout_ms}ms"),
+            Self::OutOfFuel { component, fuel } => {
+                write!(f, "component '{component}' ran out of fuel ({fuel})")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ExecutionLimitExceeded {}
+
+/// A component instance was stopped for exceeding one of the resource limits
+/// in its [`ComponentLimits`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResourceLimitExceeded {
+    pub component: String,
+    /// The name of the exceeded property, e.g. `max-memory-bytes`.
+    pub limit: &'static str,
+    pub maximum: u64,
+}
+
+impl fmt::Display for ResourceLimitExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "component '{}' exceeded {} ({})",
+            self.component, self.limit, self.maximum
+        )
+    }
+}
+
+impl std::error::Error for ResourceLimitExceeded {}
+
+/// A component was at its `max-concurrency` with a full `max-queue`, so the
+/// invocation was turned away without waiting.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ComponentBusy {
+    pub component: String,
+    pub max_concurrency: u32,
+    pub max_queue: Option<u32>,
+}
+
+impl fmt::Display for ComponentBusy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "component '{}' is busy (max-concurrency {}",
+            self.component, self.max_concurrency
+        )?;
+        if let Some(max_queue) = self.max_queue {
+            write!(f, ", max-queue {max_queue}")?;
+        }
+        write!(f, ")")
+    }
+}
+
+impl std::error::Error for ComponentBusy {}
+
+/// Why an invocation failed, as returned by [`ComponentInvoker::invoke`] and
+/// [`ComponentInstance::call`](crate::ComponentInstance::call).
+#[derive(Debug)]
+pub enum InvocationError {
+    /// No component with this name is registered.
+    ComponentNotFound { component: String },
+    /// The component does not export this function.
+    FunctionNotFound { component: String, function: String },
+    /// The arguments (or env vars) cannot be passed to the function.
+    InvalidArguments {
+        component: String,
+        function: String,
+        message: String,
+    },
+    /// The guest trapped. `backtrace` holds its Wasm frames, when captured.
+    Trap {
+        component: String,
+        function: String,
+        message: String,
+        backtrace: Option<String>,
+    },
+    /// The function returned the `err` case of its WIT `result`. `error` is
+    /// that case's payload as JSON, or null if it has none.
+    ErrorResult {
+        component: String,
+        function: String,
+        error: serde_json::Value,
+    },
+    /// A host import the component called failed on I/O, e.g. a lost
+    /// connection or an unreadable file.
+    Capability {
+        component: String,
+        function: String,
+        message: String,
+    },
+    /// The guest called `wasi:cli/exit` with a non-zero code.
+    Exit {
+        component: String,
+        function: String,
+        code: i32,
+    },
+    /// Stopped by the component's `timeout-ms` or `fuel` limit.
+    LimitExceeded(ExecutionLimitExceeded),
+    /// Stopped by one of the component's resource limits.
+    ResourceLimitExceeded(ResourceLimitExceeded),
+    /// Turned away by the component's `max-concurrency` and `max-queue`.
+    Busy(ComponentBusy),
+    /// Any other failure, including a component that could not be linked to
+    /// its capabilities.
+    Other {
+        component: String,
+        function: String,
+        source: anyhow::Error,
+    },
+}
+
+impl InvocationError {
+    /// The component that was invoked.
+    pub fn component(&self) -> &str {
+        match self {
+            Self::ComponentNotFound { component }
+            | Self::FunctionNotFound { component, .. }
+            | Self::InvalidArguments { component, .. }
+            | Self::Trap { component, .. }
+            | Self::ErrorResult { component, .. }
+            | Self::Capability { component, .. }
+            | Self::Exit { component, .. }
+            | Self::Other { component, .. } => component,
+            Self::LimitExceeded(
+                ExecutionLimitExceeded::Timeout { component, .. }
+                | ExecutionLimitExceeded::OutOfFuel { component, .. },
+            ) => component,
+            Self::ResourceLimitExceeded(e) => &e.component,
+            Self::Busy(e) => &e.component,
+        }
+    }
+
+    /// Whether the same invocation may succeed if retried: the component was
+    /// busy, timed out, or a capability it depends on failed.
+    pub fn is_transient(&self) -> bool {
+        matches!(
+            self,
+            Self::Busy(_)
+                | Self::Capability { .. }
+                | Self::LimitExceeded(ExecutionLimitExceeded::Timeout { .. })
+        )
+    }
+
+    // Recover the typed error from one raised inside the runtime, which is
+    // either an `InvocationError` already or one of the limit errors.
+    pub(crate) fn from_error(component: &str, function: &str, error: anyhow::Error) -> Self {
+        let error = match error.downcast::<Self>() {
+            Ok(e) => return e,
+            Err(error) => error,
+        };
+        let error = match error.downcast::<ExecutionLimitExceeded>() {
+            Ok(e) => return Self::LimitExceeded(e),
+            Err(error) => error,
+        };
+        let error = match error.downcast::<ResourceLimitExceeded>() {
+            Ok(e) => return Self::ResourceLimitExceeded(e),
+            Err(error) => error,
+        };
+        match error.downcast::<ComponentBusy>() {
+            Ok(e) => Self::Busy(e),
+            Err(error) => Self::Other {
+                component: component.to_string(),
+                function: function.to_string(),
+                source: error,
+            },
+        }
+    }
+}
+
+impl fmt::Display for InvocationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ComponentNotFound { component } => {
+                write!(f, "Component '{component}' not found")
+            }
+            Self::FunctionNotFound {
+                component,
+                function,
+            } => write!(
+                f,
+                "Function '{function}' not found in component '{component}'"
+            ),
+            Self::InvalidArguments {
+                component,
+                function,
+                message,
+            } => write!(f, "Invalid call to '{component}.{function}': {message}"),
+            Self::Trap {
+                component,
+                function,
+                message,
+                ..
+            } => write!(
+                f,
+                "Component '{component}' trapped in '{function}': {message}"
+            ),
+            Self::ErrorResult {
+                component,
+                function,
+                error,
+            } => write!(
+                f,
+                "Component '{component}' returned error from '{function}': {error}"
+            ),
+            Self::Capability {
+                component,
+                function,
+                message,
+            } => write!(
+                f,
+                "Capability failure in '{component}.{function}': {message}"
+            ),
+            Self::Exit {
+                component,
+                function,
+                code,
+            } => write!(
+                f,
+                "Component '{component}' exited with code {code} in '{function}'"
+            ),
+            Self::LimitExceeded(e) => e.fmt(f),
+            Self::ResourceLimitExceeded(e) => e.fmt(f),
+            Self::Busy(e) => e.fmt(f),
+            Self::Other { source, .. } => write!(f, "{source:#}"),
+        }
+    }
+}
+
+impl std::error::Error for InvocationError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::LimitExceeded(e) => Some(e),
+            Self::ResourceLimitExceeded(e) => Some(e),
+            Self::Busy(e) => Some(e),
+            Self::Other { source, .. } => Some(source.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+/// Engine settings from the `[runtime.*]` definitions.
+#[derive(Debug, Clone, Default)]
+pub struct RuntimeConfig {
+    /// Directory for the compiled code cache. When unset, the wasmtime
+    /// default cache configuration is used.
+    pub cache_dir: Option<std::path::PathBuf>,
+    /// Directory for the cache of composed component bytes. When unset,
+    /// components are composed on every start.
+    pub composition_cache_dir: Option<std::path::PathBuf>,
+    /// Emit DWARF debug info for compiled components.
+    pub debug_info: bool,
+    /// Cranelift optimization level. When unset, the wasmtime default applies.
+    pub opt_level: Option<OptLevel>,
+    /// When set, instances are allocated from a pool of preallocated slots
+    /// instead of on demand.
+    pub pooling: Option<PoolingConfig>,
+    /// How `oci://` components are pulled.
+    pub oci: OciConfig,
+    /// `wasm-pkg-client` registry config for `pkg:` components. When unset,
+    /// its global config is used.
+    pub pkg_config_file: Option<std::path::PathBuf>,
+}
+
+/// Cranelift optimization level.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OptLevel {
+    None,
+    Speed,
+    SpeedAndSize,
+}
+
+/// Settings for pulling `oci://` components, from `[runtime.oci]`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct OciConfig {
+    /// Directory for pulled component bytes, stored by digest. When unset,
+    /// components are pulled on every start.
+    pub cache_dir: Option<std::path::PathBuf>,
+    /// Only use the cache, never contacting a registry. Requires `cache_dir`.
+    pub offline: bool,
+    /// Docker config file with registry credentials. When unset,
+    /// `$DOCKER_CONFIG/config.json` or `~/.docker/config.json` is used.
+    pub docker_config: Option<std::path::PathBuf>,
+}
+
+/// Limits for the pooling instance allocator. Unset limits keep the
+/// wasmtime defaults.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PoolingConfig {
+    pub total_component_instances: Option<u32>,
+    pub total_memories: Option<u32>,
+    pub total_tables: Option<u32>,
 }
 
 /// Per-store `wasi:http` hooks, configured from the `wasi:http` capability
 /// properties. The `WasiHttpHooks` trait impl lives in `runtime::host`.
-#[derive(Default)]
+#[derive(Clone)]
 pub(crate) struct HttpHooks {
-    /// When set, outgoing `application/grpc` requests are sent over cleartext
-    /// HTTP/2 (h2c, prior knowledge) instead of HTTP/1.1. Enabled by the
-    /// `h2c-for-grpc` capability property.
-    pub(crate) h2c_for_grpc: bool,
+    /// Sends the requests, over the capability's pooled connections.
+    pub(crate) client: crate::runtime::http_client::HttpClient,
+    /// Which requests may be sent, from the `allowed-hosts`,
+    /// `allowed-methods`, `denied-cidrs` and `max-request-body-bytes`
+    /// capability properties.
+    pub(crate) policy: std::sync::Arc<crate::runtime::http_policy::HttpPolicy>,
 }
 
 impl HttpHooks {
-    pub(crate) fn from_properties(props: &HashMap<String, serde_json::Value>) -> Self {
-        Self {
-            h2c_for_grpc: props
-                .get("h2c-for-grpc")
-                .and_then(|v| v.as_bool())
-                .unwrap_or(false),
-        }
+    pub(crate) fn from_properties(
+        capability_name: &str,
+        props: &HashMap<String, serde_json::Value>,
+    ) -> Result<Self> {
+        let policy = std::sync::Arc::new(crate::runtime::http_policy::HttpPolicy::from_properties(
+            capability_name,
+            props,
+        )?);
+        Ok(Self {
+            client: crate::runtime::http_client::HttpClient::from_properties(
+                capability_name,
+                props,
+                std::sync::Arc::clone(&policy),
+            )?,
+            policy,
+        })
     }
 }
 
 /// State passed to Wasm components during execution.
 pub struct ComponentState {
+    pub(crate) component: String,
     pub wasi_ctx: wasmtime_wasi::WasiCtx,
     pub wasi_http_ctx: Option<wasmtime_wasi_http::WasiHttpCtx>,
-    pub(crate) http_hooks: HttpHooks,
+    pub(crate) http_hooks: Option<HttpHooks>,
+    /// The `host-overrides` of the component's `wasi:sockets` capability.
+    pub(crate) socket_overrides:
+        Option<std::sync::Arc<crate::runtime::host_overrides::HostOverrides>>,
     pub resource_table: wasmtime_wasi::ResourceTable,
+    pub(crate) limiter: crate::runtime::limiter::ComponentLimiter,
+    pub(crate) keyvalue: Option<crate::runtime::keyvalue::KeyValueCtx>,
+    pub(crate) config: Option<crate::runtime::dynamic_config::DynamicConfig>,
     pub(crate) extensions: HashMap<TypeId, Box<dyn Any + Send>>,
 }
 
@@ -298,11 +721,15 @@ pub struct Component {
 }
 
 /// Invoke components by name.
+///
+/// Components are returned by value: a reload can replace them at any time,
+/// so they cannot be borrowed from the invoker.
 pub trait ComponentInvoker: Send + Sync {
-    fn get_component(&self, name: &str) -> Option<&Component>;
+    /// A snapshot of the named component, as currently loaded.
+    fn get_component(&self, name: &str) -> Option<Component>;
 
-    fn list_components(&self, selector: Option<&crate::config::types::Selector>)
-    -> Vec<&Component>;
+    /// Snapshots of the currently loaded components matching `selector`.
+    fn list_components(&self, selector: Option<&crate::config::types::Selector>) -> Vec<Component>;
 
     /// Invoke a component function.
     ///
@@ -310,11 +737,14 @@ pub trait ComponentInvoker: Send + Sync {
     /// task-local [`PROPAGATION_CONTEXT`](crate::PROPAGATION_CONTEXT). Callers
     /// that need to establish or extend propagation must wrap the call with
     /// `PROPAGATION_CONTEXT.scope(...).await` at their boundary.
+    ///
+    /// `env` is added to the environment of the component's `wasi:cli`
+    /// capability, taking precedence over its `env` and `inherit-env`.
     fn invoke<'a>(
         &'a self,
         component_name: &'a str,
         function_name: &'a str,
         args: Vec<serde_json::Value>,
         env: Option<HashMap<String, String>>,
-    ) -> Pin<Box<dyn Future<Output = Result<serde_json::Value>> + Send + 'a>>;
+    ) -> Pin<Box<dyn Future<Output = Result<serde_json::Value, InvocationError>> + Send + 'a>>;
 }
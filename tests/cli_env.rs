@@ -0,0 +1,139 @@
+mod common;
+
+use std::collections::HashMap;
+
+use composable_runtime::Runtime;
+
+// Exports `env() -> list<tuple<string, string>>`, the guest's environment
+// from `wasi:cli/environment`.
+fn env_reader() -> common::TestFile {
+    let wat = r#"
+        (component
+            (import "wasi:cli/environment@0.2.12" (instance $environment
+                (export "get-environment" (func (result (list (tuple string string)))))
+            ))
+
+            (core module $libc
+                (memory (export "memory") 1)
+                (global $heap (mut i32) (i32.const 1024))
+                (func (export "realloc") (param i32 i32 i32 i32) (result i32)
+                    (local $ptr i32)
+                    (local.set $ptr (global.get $heap))
+                    (global.set $heap (i32.add (local.get $ptr) (local.get 3)))
+                    (local.get $ptr))
+            )
+            (core instance $libc (instantiate $libc))
+            (core func $get-environment (canon lower (func $environment "get-environment")
+                (memory $libc "memory") (realloc (func $libc "realloc"))))
+
+            (core module $m
+                (import "environment" "get-environment" (func $get-environment (param i32)))
+                (func (export "env") (result i32)
+                    (call $get-environment (i32.const 0))
+                    (i32.const 0))
+            )
+            (core instance $i (instantiate $m
+                (with "environment" (instance (export "get-environment" (func $get-environment))))))
+
+            (func $env (result (list (tuple string string)))
+                (canon lift (core func $i "env")
+                    (memory $libc "memory") (realloc (func $libc "realloc"))))
+            (export "env" (func $env))
+        )
+    "#;
+    common::create_wasm_test_file(wat)
+}
+
+async fn env(cli_properties: &str, call_env: Option<HashMap<String, String>>) -> serde_json::Value {
+    let wasm = env_reader();
+    let toml_file = common::create_toml_test_file(&format!(
+        r#"
+        [component.reader]
+        uri = "{}"
+        imports = ["cli"]
+
+        [capability.cli]
+        type = "wasi:cli-p2"
+        {cli_properties}
+        "#,
+        wasm.display()
+    ));
+    let runtime = Runtime::builder()
+        .from_path(toml_file.to_path_buf())
+        .build()
+        .await
+        .unwrap();
+    runtime
+        .invoker()
+        .invoke("reader", "env", vec![], call_env)
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn merges_inherited_static_and_per_call_env() {
+    if !common::in_child_with_env(
+        "merges_inherited_static_and_per_call_env",
+        &[
+            ("CLI_ENV_TEST_REGION", "us-east-1"),
+            ("CLI_ENV_TEST_MODE", "host"),
+            ("CLI_ENV_TEST_SECRET", "hidden"),
+        ],
+    ) {
+        return;
+    }
+    let call_env = HashMap::from([("LEVEL".to_string(), "debug".to_string())]);
+    let env = env(
+        r#"
+        env = { CLI_ENV_TEST_MODE = "static", LEVEL = "info", NAME = "reader" }
+        inherit-env = ["CLI_ENV_TEST_R*", "CLI_ENV_TEST_MODE"]
+        "#,
+        Some(call_env),
+    )
+    .await;
+    assert_eq!(
+        env,
+        serde_json::json!([
+            ["CLI_ENV_TEST_MODE", "static"],
+            ["CLI_ENV_TEST_REGION", "us-east-1"],
+            ["LEVEL", "debug"],
+            ["NAME", "reader"]
+        ])
+    );
+}
+
+#[tokio::test]
+async fn no_env_by_default() {
+    assert_eq!(env("", None).await, serde_json::json!([]));
+}
+
+#[tokio::test]
+async fn invalid_env_fails_build() {
+    for (properties, expected) in [
+        (
+            r#"inherit-env = "HOME""#,
+            "'inherit-env' must be an array of strings",
+        ),
+        (r#"env = ["LEVEL"]"#, "'env' must be a table of strings"),
+        (r#"env = { LEVEL = 1 }"#, "env var 'LEVEL' must be a string"),
+    ] {
+        let toml_file = common::create_toml_test_file(&format!(
+            r#"
+            [capability.cli]
+            type = "wasi:cli"
+            {properties}
+            "#
+        ));
+        let err = Runtime::builder()
+            .from_path(toml_file.to_path_buf())
+            .build()
+            .await
+            .err()
+            .expect("invalid env should fail the build")
+            .to_string();
+        assert!(
+            err.contains("Capability 'cli'") && err.contains(expected),
+            "unexpected error: {err}"
+        );
+    }
+}
@@ -155,3 +155,27 @@ pub fn load_graph_and_assert_ok(paths: &[PathBuf]) -> ComponentGraph {
     );
     graph_result.unwrap()
 }
+
+/// Run `test` again in a child process with `vars` set, since setting them in
+/// this process would race with other tests reading the environment. Returns
+/// `true` in the child, where the test should go on, and `false` once the
+/// child has passed.
+pub fn in_child_with_env(test: &str, vars: &[(&str, &str)]) -> bool {
+    const CHILD: &str = "COMPOSABLE_RUNTIME_TEST_CHILD";
+    if std::env::var_os(CHILD).is_some() {
+        return true;
+    }
+    let output = std::process::Command::new(std::env::current_exe().unwrap())
+        .args([test, "--exact"])
+        .env(CHILD, "1")
+        .envs(vars.iter().copied())
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        output.status.success() && stdout.contains("1 passed"),
+        "'{test}' failed in child process:\n{stdout}{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    false
+}
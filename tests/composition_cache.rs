@@ -0,0 +1,94 @@
+mod common;
+
+fn cache_entries(dir: &std::path::Path) -> usize {
+    std::fs::read_dir(dir)
+        .map(|entries| entries.count())
+        .unwrap_or(0)
+}
+
+#[tokio::test]
+async fn reuses_cached_compositions() {
+    let cache_dir = tempfile::tempdir().unwrap();
+    let client_wasm = common::client_wasm();
+    let interceptor_wasm = common::interceptor_wasm();
+    let handler_wasm = common::handler_wasm();
+    let toml_content = format!(
+        r#"
+        [runtime.composition]
+        cache-dir = "{}"
+
+        [component.client]
+        uri = "{}"
+        interceptors = ["interceptor"]
+
+        [component.interceptor]
+        uri = "{}"
+
+        [component.handler]
+        uri = "{}"
+        imports = ["client"]
+        "#,
+        cache_dir.path().display(),
+        client_wasm.display(),
+        interceptor_wasm.display(),
+        handler_wasm.display()
+    );
+    let toml_file = common::create_toml_test_file(&toml_content);
+
+    let graph = common::load_graph_and_assert_ok(&[toml_file.to_path_buf()]);
+    let (first, _) = common::build_registries_and_assert_ok(&graph).await;
+    // The interceptor composed with the original client, and the handler
+    // composed with the result.
+    let entries = cache_entries(cache_dir.path());
+    assert_eq!(entries, 2);
+
+    let graph = common::load_graph_and_assert_ok(&[toml_file.to_path_buf()]);
+    let (second, _) = common::build_registries_and_assert_ok(&graph).await;
+    assert_eq!(cache_entries(cache_dir.path()), entries);
+    for name in ["client", "handler"] {
+        assert_eq!(
+            first.get_component(name).unwrap().bytes,
+            second.get_component(name).unwrap().bytes,
+            "{name}"
+        );
+    }
+}
+
+#[tokio::test]
+async fn changed_inputs_are_composed_again() {
+    let cache_dir = tempfile::tempdir().unwrap();
+    let client_wasm = common::client_wasm();
+    let handler_wasm = common::handler_wasm();
+    let build = async |handler_uri: &std::path::Path| {
+        let toml_content = format!(
+            r#"
+            [runtime.composition]
+            cache-dir = "{}"
+
+            [component.client]
+            uri = "{}"
+
+            [component.handler]
+            uri = "{}"
+            imports = ["client"]
+            "#,
+            cache_dir.path().display(),
+            client_wasm.display(),
+            handler_uri.display()
+        );
+        let toml_file = common::create_toml_test_file(&toml_content);
+        let graph = common::load_graph_and_assert_ok(&[toml_file.to_path_buf()]);
+        common::build_registries_and_assert_ok(&graph).await;
+    };
+
+    build(&handler_wasm).await;
+    assert_eq!(cache_entries(cache_dir.path()), 1);
+
+    // The same handler, with a custom section that changes its bytes.
+    let mut bytes = std::fs::read(&*handler_wasm).unwrap();
+    bytes.extend_from_slice(&[0, 5, 4, b'n', b'o', b't', b'e']);
+    let changed = tempfile::Builder::new().suffix(".wasm").tempfile().unwrap();
+    std::fs::write(changed.path(), bytes).unwrap();
+    build(changed.path()).await;
+    assert_eq!(cache_entries(cache_dir.path()), 2);
+}
@@ -0,0 +1,128 @@
+mod common;
+
+use composable_runtime::{ComponentBusy, ComponentGraph, InvocationError, Runtime};
+use std::time::Duration;
+
+fn component_returning_value() -> common::TestFile {
+    let wat = r#"
+        (component
+            (core module $m
+                (func (export "get-value") (result i32)
+                    (i32.const 7)
+                )
+            )
+            (core instance $i (instantiate $m))
+            (func $get_value (result u32) (canon lift (core func $i "get-value")))
+            (export "get-value" (func $get_value))
+        )
+    "#;
+    common::create_wasm_test_file(wat)
+}
+
+async fn build_runtime(component_wasm: &common::TestFile, limits: &str) -> Runtime {
+    let toml_content = format!(
+        r#"
+        [component.guest]
+        uri = "{}"
+        {limits}
+        "#,
+        component_wasm.display()
+    );
+    let toml_file = common::create_toml_test_file(&toml_content);
+    Runtime::builder()
+        .from_path(&*toml_file)
+        .build()
+        .await
+        .expect("Failed to create runtime")
+}
+
+#[test]
+fn concurrency_limits_rejects_invalid_values() {
+    let cases = [
+        ("max-concurrency = 0", "'max-concurrency' must be positive"),
+        ("max-queue = 4", "'max-queue' requires 'max-concurrency'"),
+    ];
+    for (limits, expected) in cases {
+        let toml_content = format!("[component.guest]\nuri = \"guest.wasm\"\n{limits}");
+        let toml_file = common::create_toml_test_file(&toml_content);
+        let err = ComponentGraph::builder()
+            .from_path(&*toml_file)
+            .build()
+            .expect_err(limits)
+            .to_string();
+        assert!(
+            err.contains(expected),
+            "Expected error containing '{expected}', got: {err}"
+        );
+    }
+}
+
+#[tokio::test]
+async fn full_queue_turns_invocations_away() {
+    let wasm = component_returning_value();
+    let runtime = build_runtime(&wasm, "max-concurrency = 1\nmax-queue = 0").await;
+
+    // A live instance holds the component's only slot.
+    let instance = runtime
+        .instantiate("guest", None)
+        .await
+        .expect("Failed to instantiate");
+
+    let err = runtime
+        .invoker()
+        .invoke("guest", "get-value", vec![], None)
+        .await
+        .expect_err("invocation should be turned away");
+    let InvocationError::Busy(busy) = err else {
+        panic!("unexpected error: {err}");
+    };
+    assert_eq!(
+        busy,
+        ComponentBusy {
+            component: "guest".to_string(),
+            max_concurrency: 1,
+            max_queue: Some(0),
+        }
+    );
+
+    drop(instance);
+    let result = runtime
+        .invoker()
+        .invoke("guest", "get-value", vec![], None)
+        .await
+        .expect("Failed to invoke");
+    assert_eq!(result, serde_json::json!(7));
+}
+
+#[tokio::test]
+async fn queued_invocation_waits_for_slot() {
+    let wasm = component_returning_value();
+    let runtime = build_runtime(&wasm, "max-concurrency = 1\nmax-queue = 1").await;
+
+    let instance = runtime
+        .instantiate("guest", None)
+        .await
+        .expect("Failed to instantiate");
+
+    let invoker = runtime.invoker();
+    let queued =
+        tokio::spawn(async move { invoker.invoke("guest", "get-value", vec![], None).await });
+
+    // Let the spawned invocation take the only place in the queue.
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert!(!queued.is_finished());
+
+    let busy = runtime
+        .invoker()
+        .invoke("guest", "get-value", vec![], None)
+        .await
+        .expect_err("invocation should be turned away");
+    assert!(
+        matches!(busy, InvocationError::Busy(_)),
+        "unexpected error: {busy}"
+    );
+
+    drop(instance);
+    let result = queued.await.unwrap().expect("queued invocation should run");
+    assert_eq!(result, serde_json::json!(7));
+}
@@ -0,0 +1,281 @@
+mod common;
+
+use composable_runtime::{
+    ComponentGraph, ExecutionLimitExceeded, InvocationError, ResourceLimitExceeded, Runtime,
+};
+
+fn component_spinning_forever() -> common::TestFile {
+    let wat = r#"
+        (component
+            (core module $m
+                (func (export "spin")
+                    (loop $l (br $l))
+                )
+            )
+            (core instance $i (instantiate $m))
+            (func $spin (canon lift (core func $i "spin")))
+            (export "spin" (func $spin))
+        )
+    "#;
+    common::create_wasm_test_file(wat)
+}
+
+async fn build_runtime(component_wasm: &common::TestFile, limits: &str) -> Runtime {
+    let toml_content = format!(
+        r#"
+        [component.guest]
+        uri = "{}"
+        {limits}
+        "#,
+        component_wasm.display()
+    );
+    let toml_file = common::create_toml_test_file(&toml_content);
+    Runtime::builder()
+        .from_path(&*toml_file)
+        .build()
+        .await
+        .expect("Failed to create runtime")
+}
+
+#[test]
+fn limits_parsed() {
+    let toml_file = common::create_toml_test_file(
+        r#"
+        [component.guest]
+        uri = "guest.wasm"
+        timeout-ms = 250
+        fuel = 1000000
+        "#,
+    );
+    let graph = common::load_graph_and_assert_ok(&[toml_file.to_path_buf()]);
+    let definition = common::get_component_definition(&graph, "guest");
+    assert_eq!(definition.limits.timeout_ms, Some(250));
+    assert_eq!(definition.limits.fuel, Some(1_000_000));
+}
+
+#[test]
+fn limits_reject_invalid_values() {
+    let cases = ["timeout-ms = -1", "timeout-ms = \"1s\"", "fuel = 1.5"];
+    for limit in cases {
+        let toml_content = format!("[component.guest]\nuri = \"guest.wasm\"\n{limit}");
+        let toml_file = common::create_toml_test_file(&toml_content);
+        let err = ComponentGraph::builder()
+            .from_path(&*toml_file)
+            .build()
+            .expect_err(limit)
+            .to_string();
+        assert!(
+            err.contains("a non-negative integer"),
+            "Expected type mismatch error, got: {err}"
+        );
+    }
+
+    for (limit, expected) in [
+        ("timeout-ms = 0", "'timeout-ms' must be positive"),
+        ("fuel = 0", "'fuel' must be positive"),
+    ] {
+        let toml_content = format!("[component.guest]\nuri = \"guest.wasm\"\n{limit}");
+        let toml_file = common::create_toml_test_file(&toml_content);
+        let err = ComponentGraph::builder()
+            .from_path(&*toml_file)
+            .build()
+            .expect_err(limit)
+            .to_string();
+        assert!(err.contains(expected), "Expected '{expected}', got: {err}");
+    }
+}
+
+#[tokio::test]
+async fn timeout_stops_runaway_guest() {
+    let wasm = component_spinning_forever();
+    let runtime = build_runtime(&wasm, "timeout-ms = 50").await;
+    let err = runtime
+        .invoker()
+        .invoke("guest", "spin", vec![], None)
+        .await
+        .expect_err("spinning guest should time out");
+    let InvocationError::LimitExceeded(exceeded) = err else {
+        panic!("unexpected error: {err}");
+    };
+    assert_eq!(
+        exceeded,
+        ExecutionLimitExceeded::Timeout {
+            component: "guest".to_string(),
+            timeout_ms: 50,
+        }
+    );
+}
+
+#[tokio::test]
+async fn fuel_stops_runaway_guest() {
+    let wasm = component_spinning_forever();
+    let runtime = build_runtime(&wasm, "fuel = 10000").await;
+    let err = runtime
+        .invoker()
+        .invoke("guest", "spin", vec![], None)
+        .await
+        .expect_err("spinning guest should run out of fuel");
+    let InvocationError::LimitExceeded(exceeded) = err else {
+        panic!("unexpected error: {err}");
+    };
+    assert_eq!(
+        exceeded,
+        ExecutionLimitExceeded::OutOfFuel {
+            component: "guest".to_string(),
+            fuel: 10000,
+        }
+    );
+}
+
+fn component_growing_memory() -> common::TestFile {
+    let wat = r#"
+        (component
+            (core module $m
+                (memory (export "memory") 1)
+                (func (export "grow") (param i32) (result i32)
+                    (memory.grow (local.get 0))
+                )
+            )
+            (core instance $i (instantiate $m))
+            (func $grow (param "pages" u32) (result s32)
+                (canon lift (core func $i "grow")))
+            (export "grow" (func $grow))
+        )
+    "#;
+    common::create_wasm_test_file(wat)
+}
+
+#[test]
+fn resource_limits_parsed() {
+    let toml_file = common::create_toml_test_file(
+        r#"
+        [component.guest]
+        uri = "guest.wasm"
+        max-memory-bytes = 1048576
+        max-tables = 2
+        max-table-elements = 1000
+        max-instances = 4
+        "#,
+    );
+    let graph = common::load_graph_and_assert_ok(&[toml_file.to_path_buf()]);
+    let limits = &common::get_component_definition(&graph, "guest").limits;
+    assert_eq!(limits.max_memory_bytes, Some(1_048_576));
+    assert_eq!(limits.max_tables, Some(2));
+    assert_eq!(limits.max_table_elements, Some(1000));
+    assert_eq!(limits.max_instances, Some(4));
+}
+
+#[tokio::test]
+async fn memory_growth_within_limit() {
+    let wasm = component_growing_memory();
+    let runtime = build_runtime(&wasm, "max-memory-bytes = 262144").await;
+    let result = runtime
+        .invoker()
+        .invoke("guest", "grow", vec![serde_json::json!(3)], None)
+        .await
+        .expect("growth within the limit should succeed");
+    // memory.grow returns the previous size in pages
+    assert_eq!(result, serde_json::json!(1));
+}
+
+#[tokio::test]
+async fn memory_growth_past_limit_fails() {
+    let wasm = component_growing_memory();
+    let runtime = build_runtime(&wasm, "max-memory-bytes = 262144").await;
+    let err = runtime
+        .invoker()
+        .invoke("guest", "grow", vec![serde_json::json!(4)], None)
+        .await
+        .expect_err("growth past the limit should fail");
+    let InvocationError::ResourceLimitExceeded(exceeded) = err else {
+        panic!("unexpected error: {err:?}");
+    };
+    assert_eq!(
+        exceeded,
+        ResourceLimitExceeded {
+            component: "guest".to_string(),
+            limit: "max-memory-bytes",
+            maximum: 262144,
+        }
+    );
+}
+
+#[tokio::test]
+async fn initial_memory_past_limit_fails_instantiation() {
+    let wasm = component_growing_memory();
+    let runtime = build_runtime(&wasm, "max-memory-bytes = 1024").await;
+    let err = runtime
+        .invoker()
+        .invoke("guest", "grow", vec![serde_json::json!(0)], None)
+        .await
+        .expect_err("instantiation should fail");
+    assert_eq!(
+        err.to_string(),
+        "component 'guest' exceeded max-memory-bytes (1024)"
+    );
+}
+
+#[tokio::test]
+async fn instance_count_past_limit_fails_instantiation() {
+    let wasm = component_growing_memory();
+    let runtime = build_runtime(&wasm, "max-instances = 0").await;
+    let err = runtime
+        .invoker()
+        .invoke("guest", "grow", vec![serde_json::json!(0)], None)
+        .await
+        .expect_err("instantiation should fail");
+    assert_eq!(
+        err.to_string(),
+        "component 'guest' exceeded max-instances (0)"
+    );
+}
+
+fn component_with_nested_instances() -> common::TestFile {
+    // Two core instances of its own, plus one for each of the two
+    // instantiations of the nested component, and two tables.
+    let wat = r#"
+        (component
+            (component $inner
+                (core module $m (table 1 funcref))
+                (core instance (instantiate $m))
+            )
+            (instance (instantiate $inner))
+            (instance (instantiate $inner))
+            (core module $m
+                (table 1 funcref)
+                (func (export "run") (result i32) (i32.const 1))
+            )
+            (core module $empty)
+            (core instance $i (instantiate $m))
+            (core instance (instantiate $empty))
+            (func $run (result s32) (canon lift (core func $i "run")))
+            (export "run" (func $run))
+        )
+    "#;
+    common::create_wasm_test_file(wat)
+}
+
+#[tokio::test]
+async fn instance_and_table_counts_include_nested_components() {
+    let wasm = component_with_nested_instances();
+    let cases = [
+        ("max-instances = 4\nmax-tables = 3", None),
+        ("max-instances = 3", Some("max-instances (3)")),
+        ("max-tables = 2", Some("max-tables (2)")),
+    ];
+    for (limits, expected) in cases {
+        let runtime = build_runtime(&wasm, limits).await;
+        let result = runtime.invoker().invoke("guest", "run", vec![], None).await;
+        match expected {
+            None => assert_eq!(result.unwrap(), serde_json::json!(1)),
+            Some(expected) => {
+                let err = result.expect_err(limits);
+                assert_eq!(
+                    err.to_string(),
+                    format!("component 'guest' exceeded {expected}")
+                );
+                assert!(matches!(err, InvocationError::ResourceLimitExceeded(_)));
+            }
+        }
+    }
+}
@@ -178,6 +178,49 @@ async fn test_host_capability_invoked() {
     assert_eq!(result, serde_json::json!(42));
 }
 
+#[tokio::test]
+async fn test_components_sharing_capabilities_invoked_repeatedly() {
+    let first_wasm = component_calling_host_get_value();
+    let second_wasm = component_calling_host_get_value();
+
+    let toml_content = format!(
+        r#"
+        [capability.value-provider]
+        type = "value-provider"
+
+        [component.first]
+        uri = "{}"
+        imports = ["value-provider"]
+
+        [component.second]
+        uri = "{}"
+        imports = ["value-provider"]
+        "#,
+        first_wasm.display(),
+        second_wasm.display()
+    );
+
+    let toml_file = common::create_toml_test_file(&toml_content);
+
+    let runtime = Runtime::builder()
+        .from_path(&*toml_file)
+        .with_capability::<ValueProviderCapability>("value-provider")
+        .build()
+        .await
+        .expect("Failed to create runtime");
+
+    // Both components are pre-linked against the same linker at build time,
+    // and each invocation instantiates into a fresh store.
+    let invoker = runtime.invoker();
+    for name in ["first", "second", "first", "second"] {
+        let result = invoker
+            .invoke(name, "get-value", vec![], None)
+            .await
+            .expect("Failed to invoke");
+        assert_eq!(result, serde_json::json!(42));
+    }
+}
+
 // --- Tests for TOML config ---
 
 // Host Capability that reads config from TOML and uses it in host function
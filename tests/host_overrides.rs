@@ -0,0 +1,48 @@
+mod common;
+
+use composable_runtime::Runtime;
+
+async fn build(toml_content: &str) -> anyhow::Result<Runtime> {
+    let toml_file = common::create_toml_test_file(toml_content);
+    Runtime::builder()
+        .from_path(toml_file.to_path_buf())
+        .build()
+        .await
+}
+
+#[tokio::test]
+async fn socket_overrides_cannot_have_ports() {
+    let err = build(
+        r#"
+        [capability.net]
+        type = "wasi:sockets"
+        host-overrides = { "db.partner.com:5432" = "127.0.0.1" }
+        "#,
+    )
+    .await
+    .err()
+    .expect("a port in a socket override should fail the build")
+    .to_string();
+    assert!(
+        err.contains("Capability 'net'") && err.contains("cannot have a port"),
+        "unexpected error: {err}"
+    );
+}
+
+#[tokio::test]
+async fn valid_overrides_build() {
+    build(
+        r#"
+        [capability.http]
+        type = "wasi:http"
+        host-overrides = { "api.partner.com:443" = "127.0.0.1:8443", "auth.partner.com" = "localhost" }
+        preserve-host = false
+
+        [capability.net]
+        type = "wasi:sockets"
+        host-overrides = { "db.partner.com" = "127.0.0.1" }
+        "#,
+    )
+    .await
+    .unwrap();
+}
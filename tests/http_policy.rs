@@ -0,0 +1,61 @@
+mod common;
+
+use composable_runtime::Runtime;
+
+async fn build_error(properties: &str) -> String {
+    let toml_content = format!(
+        r#"
+        [capability.http]
+        type = "wasi:http"
+        {properties}
+        "#
+    );
+    let toml_file = common::create_toml_test_file(&toml_content);
+    Runtime::builder()
+        .from_path(toml_file.to_path_buf())
+        .build()
+        .await
+        .err()
+        .expect("invalid policy should fail the build")
+        .to_string()
+}
+
+#[tokio::test]
+async fn invalid_policy_fails_build() {
+    let err = build_error(r#"denied-cidrs = ["link-local", "10.0.0.0/40"]"#).await;
+    assert!(
+        err.contains("Capability 'http'") && err.contains("invalid CIDR '10.0.0.0/40'"),
+        "unexpected error: {err}"
+    );
+
+    let err = build_error(r#"allowed-hosts = ["api.example.com:https"]"#).await;
+    assert!(
+        err.contains("invalid port in host pattern"),
+        "unexpected error: {err}"
+    );
+
+    let err = build_error("max-request-body-bytes = -1").await;
+    assert!(
+        err.contains("'max-request-body-bytes': must be a non-negative integer"),
+        "unexpected error: {err}"
+    );
+}
+
+#[tokio::test]
+async fn valid_policy_builds() {
+    let toml_file = common::create_toml_test_file(
+        r#"
+        [capability.http]
+        type = "wasi:http"
+        allowed-hosts = ["api.example.com", "*.example.org:8443", "localhost:*"]
+        allowed-methods = ["GET", "POST"]
+        denied-cidrs = ["link-local", "10.0.0.0/8", "fd00::/8"]
+        max-request-body-bytes = 1048576
+        "#,
+    );
+    Runtime::builder()
+        .from_path(toml_file.to_path_buf())
+        .build()
+        .await
+        .unwrap();
+}
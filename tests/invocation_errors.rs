@@ -0,0 +1,117 @@
+mod common;
+
+use composable_runtime::{InvocationError, Runtime};
+
+fn component_with_failures() -> common::TestFile {
+    let wat = r#"
+        (component
+            (core module $m
+                (memory (export "memory") 1)
+                ;; Returns `err(value)` for values below 10, `ok(value)` otherwise.
+                (func (export "check") (param i32) (result i32)
+                    (i32.store (i32.const 0) (i32.lt_u (local.get 0) (i32.const 10)))
+                    (i32.store (i32.const 4) (local.get 0))
+                    (i32.const 0)
+                )
+                (func $boom (export "boom")
+                    unreachable
+                )
+            )
+            (core instance $i (instantiate $m))
+            (func $check (param "value" u32) (result (result u32 (error u32)))
+                (canon lift (core func $i "check") (memory $i "memory")))
+            (func $boom (canon lift (core func $i "boom")))
+            (export "check" (func $check))
+            (export "boom" (func $boom))
+        )
+    "#;
+    common::create_wasm_test_file(wat)
+}
+
+async fn invoke(
+    function: &str,
+    args: Vec<serde_json::Value>,
+) -> Result<serde_json::Value, InvocationError> {
+    let wasm = component_with_failures();
+    let toml_content = format!("[component.guest]\nuri = \"{}\"", wasm.display());
+    let toml_file = common::create_toml_test_file(&toml_content);
+    let runtime = Runtime::builder()
+        .from_path(toml_file.to_path_buf())
+        .build()
+        .await
+        .unwrap();
+    runtime
+        .invoker()
+        .invoke("guest", function, args, None)
+        .await
+}
+
+#[tokio::test]
+async fn err_result_carries_payload() {
+    assert_eq!(
+        invoke("check", vec![serde_json::json!(42)]).await.unwrap(),
+        serde_json::json!(42)
+    );
+
+    let err = invoke("check", vec![serde_json::json!(3)])
+        .await
+        .unwrap_err();
+    let InvocationError::ErrorResult {
+        component,
+        function,
+        error,
+    } = err
+    else {
+        panic!("unexpected error: {err}");
+    };
+    assert_eq!(component, "guest");
+    assert_eq!(function, "check");
+    assert_eq!(error, serde_json::json!(3));
+}
+
+#[tokio::test]
+async fn trap_carries_backtrace() {
+    let err = invoke("boom", vec![]).await.unwrap_err();
+    assert!(!err.is_transient());
+    let InvocationError::Trap {
+        message, backtrace, ..
+    } = err
+    else {
+        panic!("unexpected error: {err}");
+    };
+    assert!(
+        message.contains("unreachable"),
+        "unexpected message: {message}"
+    );
+    assert!(backtrace.is_some());
+}
+
+#[tokio::test]
+async fn invalid_arguments() {
+    let err = invoke("check", vec![serde_json::json!("three")])
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(err, InvocationError::InvalidArguments { .. }),
+        "unexpected error: {err}"
+    );
+
+    let err = invoke("check", vec![]).await.unwrap_err();
+    assert!(
+        matches!(err, InvocationError::InvalidArguments { .. }),
+        "unexpected error: {err}"
+    );
+}
+
+#[tokio::test]
+async fn unknown_function() {
+    let err = invoke("missing", vec![]).await.unwrap_err();
+    assert!(
+        matches!(
+            &err,
+            InvocationError::FunctionNotFound { component, function }
+                if component == "guest" && function == "missing"
+        ),
+        "unexpected error: {err}"
+    );
+}
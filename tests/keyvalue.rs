@@ -0,0 +1,250 @@
+mod common;
+
+use composable_runtime::Runtime;
+
+// Exports `increment(bucket, key) -> s64`: opens the bucket and increments
+// the key by one. Returns -1 if the bucket cannot be opened, and -2 if the
+// increment fails.
+fn counter_component() -> common::TestFile {
+    let wat = r#"
+        (component $C
+            (import "wasi:keyvalue/store@0.2.0-draft" (instance $store
+                (export "bucket" (type $bucket (sub resource)))
+                (type $error (variant
+                    (case "no-such-store")
+                    (case "access-denied")
+                    (case "other" string)))
+                (export "error" (type $error' (eq $error)))
+                (export "open" (func
+                    (param "identifier" string)
+                    (result (result (own $bucket) (error $error')))))
+            ))
+            (alias export $store "bucket" (type $bucket))
+            (alias export $store "error" (type $error))
+            (import "wasi:keyvalue/atomics@0.2.0-draft" (instance $atomics
+                (alias outer $C $bucket (type $bucket))
+                (alias outer $C $error (type $error))
+                (export "increment" (func
+                    (param "bucket" (borrow $bucket))
+                    (param "key" string)
+                    (param "delta" u64)
+                    (result (result u64 (error $error)))))
+            ))
+
+            (core module $libc
+                (memory (export "memory") 1)
+                (global $heap (mut i32) (i32.const 1024))
+                (func (export "realloc") (param i32 i32 i32 i32) (result i32)
+                    (local $ptr i32)
+                    (local.set $ptr
+                        (i32.and
+                            (i32.add (global.get $heap) (i32.sub (local.get 2) (i32.const 1)))
+                            (i32.sub (i32.const 0) (local.get 2))))
+                    (global.set $heap (i32.add (local.get $ptr) (local.get 3)))
+                    (local.get $ptr))
+            )
+            (core instance $libc (instantiate $libc))
+
+            (core func $open (canon lower (func $store "open")
+                (memory $libc "memory") (realloc (func $libc "realloc"))))
+            (core func $increment (canon lower (func $atomics "increment")
+                (memory $libc "memory") (realloc (func $libc "realloc"))))
+            (core func $drop (canon resource.drop $bucket))
+            (core instance $kv
+                (export "open" (func $open))
+                (export "increment" (func $increment))
+                (export "drop" (func $drop)))
+
+            (core module $m
+                (import "libc" "memory" (memory 1))
+                (import "kv" "open" (func $open (param i32 i32 i32)))
+                (import "kv" "increment" (func $increment (param i32 i32 i32 i64 i32)))
+                (import "kv" "drop" (func $drop (param i32)))
+                (func (export "increment")
+                    (param $bucket_ptr i32) (param $bucket_len i32)
+                    (param $key_ptr i32) (param $key_len i32)
+                    (result i64)
+                    (local $bucket i32)
+                    (local $result i64)
+                    ;; result<own<bucket>, error> at 0: discriminant, then handle at 4
+                    (call $open (local.get $bucket_ptr) (local.get $bucket_len) (i32.const 0))
+                    (if (i32.load8_u (i32.const 0)) (then (return (i64.const -1))))
+                    (local.set $bucket (i32.load (i32.const 4)))
+                    ;; result<u64, error> at 16: discriminant, then value at 24
+                    (call $increment
+                        (local.get $bucket) (local.get $key_ptr) (local.get $key_len)
+                        (i64.const 1) (i32.const 16))
+                    (local.set $result
+                        (if (result i64) (i32.load8_u (i32.const 16))
+                            (then (i64.const -2))
+                            (else (i64.load (i32.const 24)))))
+                    (call $drop (local.get $bucket))
+                    (local.get $result))
+            )
+            (core instance $i (instantiate $m
+                (with "libc" (instance $libc))
+                (with "kv" (instance $kv))))
+
+            (func $increment (param "bucket" string) (param "key" string) (result s64)
+                (canon lift (core func $i "increment")
+                    (memory $libc "memory") (realloc (func $libc "realloc"))))
+            (export "increment" (func $increment))
+        )
+    "#;
+    common::create_wasm_test_file(wat)
+}
+
+async fn runtime(toml_content: &str) -> Runtime {
+    let toml_file = common::create_toml_test_file(toml_content);
+    Runtime::builder()
+        .from_path(toml_file.to_path_buf())
+        .build()
+        .await
+        .unwrap()
+}
+
+async fn increment(runtime: &Runtime, component: &str, bucket: &str, key: &str) -> i64 {
+    runtime
+        .invoker()
+        .invoke(
+            component,
+            "increment",
+            vec![serde_json::json!(bucket), serde_json::json!(key)],
+            None,
+        )
+        .await
+        .unwrap()
+        .as_i64()
+        .unwrap()
+}
+
+#[tokio::test]
+async fn counter_persists_across_invocations() {
+    let wasm = counter_component();
+    let runtime = runtime(&format!(
+        r#"
+        [component.counter]
+        uri = "{}"
+        imports = ["kv"]
+
+        [capability.kv]
+        type = "wasi:keyvalue"
+        "#,
+        wasm.display()
+    ))
+    .await;
+
+    assert_eq!(increment(&runtime, "counter", "hits", "a").await, 1);
+    assert_eq!(increment(&runtime, "counter", "hits", "a").await, 2);
+    assert_eq!(increment(&runtime, "counter", "hits", "b").await, 1);
+    assert_eq!(increment(&runtime, "counter", "other", "a").await, 1);
+}
+
+#[tokio::test]
+async fn capabilities_do_not_share_data() {
+    let wasm = counter_component();
+    let runtime = runtime(&format!(
+        r#"
+        [component.first]
+        uri = "{0}"
+        imports = ["kv-first"]
+
+        [component.second]
+        uri = "{0}"
+        imports = ["kv-second"]
+
+        [capability.kv-first]
+        type = "wasi:keyvalue"
+
+        [capability.kv-second]
+        type = "wasi:keyvalue"
+        backend = "memory"
+        "#,
+        wasm.display()
+    ))
+    .await;
+
+    assert_eq!(increment(&runtime, "first", "hits", "a").await, 1);
+    assert_eq!(increment(&runtime, "first", "hits", "a").await, 2);
+    assert_eq!(increment(&runtime, "second", "hits", "a").await, 1);
+}
+
+#[tokio::test]
+async fn bucket_allow_list() {
+    let wasm = counter_component();
+    let runtime = runtime(&format!(
+        r#"
+        [component.counter]
+        uri = "{}"
+        imports = ["kv"]
+
+        [capability.kv]
+        type = "wasi:keyvalue"
+        buckets = ["hits"]
+        "#,
+        wasm.display()
+    ))
+    .await;
+
+    assert_eq!(increment(&runtime, "counter", "hits", "a").await, 1);
+    assert_eq!(increment(&runtime, "counter", "secrets", "a").await, -1);
+}
+
+#[tokio::test]
+async fn file_backend_persists_across_runtimes() {
+    let wasm = counter_component();
+    let dir = tempfile::tempdir().unwrap();
+    let toml_content = format!(
+        r#"
+        [component.counter]
+        uri = "{}"
+        imports = ["kv"]
+
+        [capability.kv]
+        type = "wasi:keyvalue"
+        backend = "file"
+        path = "{}"
+        "#,
+        wasm.display(),
+        dir.path().display()
+    );
+
+    let first = runtime(&toml_content).await;
+    assert_eq!(increment(&first, "counter", "hits", "a/../b").await, 1);
+    assert_eq!(increment(&first, "counter", "hits", "a/../b").await, 2);
+    drop(first);
+
+    let second = runtime(&toml_content).await;
+    assert_eq!(increment(&second, "counter", "hits", "a/../b").await, 3);
+}
+
+#[tokio::test]
+async fn file_backends_cannot_share_a_path() {
+    let dir = tempfile::tempdir().unwrap();
+    let toml_content = format!(
+        r#"
+        [capability.one]
+        type = "wasi:keyvalue"
+        backend = "file"
+        path = "{0}"
+
+        [capability.two]
+        type = "wasi:keyvalue"
+        backend = "file"
+        path = "{0}"
+        "#,
+        dir.path().display()
+    );
+    let toml_file = common::create_toml_test_file(&toml_content);
+    let err = Runtime::builder()
+        .from_path(toml_file.to_path_buf())
+        .build()
+        .await
+        .err()
+        .expect("two capabilities must not share a path")
+        .to_string();
+    assert!(
+        err.contains("is already used by"),
+        "unexpected error: {err}"
+    );
+}
@@ -0,0 +1,197 @@
+mod common;
+
+use composable_runtime::{ComponentGraph, Lifecycle, Runtime};
+
+// Counts its calls in a global, so a reused instance returns 1, 2, 3, ...
+fn counter_component() -> common::TestFile {
+    let wat = r#"
+        (component
+            (core module $m
+                (global $count (mut i32) (i32.const 0))
+                (func (export "next") (result i32)
+                    (global.set $count (i32.add (global.get $count) (i32.const 1)))
+                    (global.get $count)
+                )
+                (func (export "fail")
+                    (unreachable)
+                )
+            )
+            (core instance $i (instantiate $m))
+            (func $next (result u32) (canon lift (core func $i "next")))
+            (func $fail (canon lift (core func $i "fail")))
+            (export "next" (func $next))
+            (export "fail" (func $fail))
+        )
+    "#;
+    common::create_wasm_test_file(wat)
+}
+
+async fn build_runtime(component_wasm: &common::TestFile, lifecycle: &str) -> Runtime {
+    let toml_content = format!(
+        r#"
+        [component.counter]
+        uri = "{}"
+        {lifecycle}
+        "#,
+        component_wasm.display()
+    );
+    let toml_file = common::create_toml_test_file(&toml_content);
+    Runtime::builder()
+        .from_path(&*toml_file)
+        .build()
+        .await
+        .expect("Failed to create runtime")
+}
+
+async fn call_next(runtime: &Runtime, times: usize) -> Vec<serde_json::Value> {
+    let invoker = runtime.invoker();
+    let mut results = Vec::new();
+    for _ in 0..times {
+        let result = invoker
+            .invoke("counter", "next", vec![], None)
+            .await
+            .expect("Failed to invoke");
+        results.push(result);
+    }
+    results
+}
+
+#[test]
+fn lifecycle_parsed() {
+    let cases = [
+        ("", Lifecycle::PerInvocation),
+        (
+            "lifecycle = \"singleton\"",
+            Lifecycle::Singleton { max_calls: None },
+        ),
+        (
+            "lifecycle = \"pool\"\npool-size = 4\nmax-calls = 100",
+            Lifecycle::Pool {
+                size: 4,
+                max_calls: Some(100),
+            },
+        ),
+    ];
+    for (lifecycle, expected) in cases {
+        let toml_content = format!("[component.counter]\nuri = \"counter.wasm\"\n{lifecycle}");
+        let toml_file = common::create_toml_test_file(&toml_content);
+        let graph = common::load_graph_and_assert_ok(&[toml_file.to_path_buf()]);
+        let definition = common::get_component_definition(&graph, "counter");
+        assert_eq!(definition.lifecycle, expected);
+    }
+}
+
+#[test]
+fn lifecycle_rejects_invalid_values() {
+    let cases = [
+        ("lifecycle = \"forever\"", "invalid lifecycle 'forever'"),
+        ("lifecycle = \"pool\"", "requires a positive 'pool-size'"),
+        (
+            "lifecycle = \"pool\"\npool-size = 0",
+            "requires a positive 'pool-size'",
+        ),
+        (
+            "lifecycle = \"singleton\"\npool-size = 2",
+            "'pool-size' requires lifecycle = \"pool\"",
+        ),
+        (
+            "max-calls = 10",
+            "'max-calls' requires lifecycle = \"singleton\" or \"pool\"",
+        ),
+        (
+            "lifecycle = \"singleton\"\nmax-calls = 0",
+            "'max-calls' must be positive",
+        ),
+    ];
+    for (lifecycle, expected) in cases {
+        let toml_content = format!("[component.counter]\nuri = \"counter.wasm\"\n{lifecycle}");
+        let toml_file = common::create_toml_test_file(&toml_content);
+        let err = ComponentGraph::builder()
+            .from_path(&*toml_file)
+            .build()
+            .expect_err(lifecycle)
+            .to_string();
+        assert!(
+            err.contains(expected),
+            "Expected error containing '{expected}', got: {err}"
+        );
+    }
+}
+
+#[tokio::test]
+async fn per_invocation_uses_fresh_instances() {
+    let wasm = counter_component();
+    let runtime = build_runtime(&wasm, "").await;
+    assert_eq!(call_next(&runtime, 3).await, [1, 1, 1]);
+}
+
+#[tokio::test]
+async fn singleton_reuses_instance() {
+    let wasm = counter_component();
+    let runtime = build_runtime(&wasm, "lifecycle = \"singleton\"").await;
+    assert_eq!(call_next(&runtime, 3).await, [1, 2, 3]);
+}
+
+#[tokio::test]
+async fn pool_recycles_after_max_calls() {
+    let wasm = counter_component();
+    let runtime = build_runtime(&wasm, "lifecycle = \"pool\"\npool-size = 2\nmax-calls = 2").await;
+    assert_eq!(call_next(&runtime, 5).await, [1, 2, 1, 2, 1]);
+}
+
+#[tokio::test]
+async fn singleton_recycles_after_trap() {
+    let wasm = counter_component();
+    let runtime = build_runtime(&wasm, "lifecycle = \"singleton\"").await;
+    assert_eq!(call_next(&runtime, 2).await, [1, 2]);
+
+    runtime
+        .invoker()
+        .invoke("counter", "fail", vec![], None)
+        .await
+        .expect_err("trapping call should fail");
+
+    assert_eq!(call_next(&runtime, 2).await, [1, 2]);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn pool_serves_concurrent_invocations() {
+    let wasm = counter_component();
+    let runtime = build_runtime(&wasm, "lifecycle = \"pool\"\npool-size = 2").await;
+    let mut calls = tokio::task::JoinSet::new();
+    for _ in 0..8 {
+        let invoker = runtime.invoker();
+        calls.spawn(async move { invoker.invoke("counter", "next", vec![], None).await });
+    }
+    let results = calls.join_all().await;
+    assert_eq!(results.len(), 8);
+
+    // Every call returns its instance's count, so the number of first calls is
+    // the number of instances created.
+    let instances = results
+        .into_iter()
+        .map(|result| result.expect("Failed to invoke"))
+        .filter(|count| *count == 1)
+        .count();
+    assert!(
+        (1..=2).contains(&instances),
+        "{instances} instances created"
+    );
+}
+
+#[tokio::test]
+async fn reused_instances_reject_env_vars() {
+    let wasm = counter_component();
+    let runtime = build_runtime(&wasm, "lifecycle = \"singleton\"").await;
+    let env = std::collections::HashMap::from([("KEY".to_string(), "value".to_string())]);
+    let err = runtime
+        .invoker()
+        .invoke("counter", "next", vec![], Some(env))
+        .await
+        .expect_err("env vars should be rejected")
+        .to_string();
+    assert!(
+        err.contains("does not accept per-invocation env vars"),
+        "{err}"
+    );
+}
@@ -0,0 +1,209 @@
+mod common;
+
+use composable_runtime::{ComponentGraph, Runtime};
+
+fn component_returning(value: i32) -> Vec<u8> {
+    wat::parse_str(format!(
+        r#"
+        (component
+            (core module $m
+                (func (export "get-value") (result i32)
+                    (i32.const {value})
+                )
+            )
+            (core instance $i (instantiate $m))
+            (func $get_value (result u32) (canon lift (core func $i "get-value")))
+            (export "get-value" (func $get_value))
+        )
+    "#
+    ))
+    .unwrap()
+}
+
+fn sha256(bytes: &[u8]) -> String {
+    use sha2::Digest;
+    format!("sha256:{:x}", sha2::Sha256::digest(bytes))
+}
+
+async fn build(toml_content: &str, lockfile: Option<&std::path::Path>) -> anyhow::Result<Runtime> {
+    let toml_file = common::create_toml_test_file(toml_content);
+    let mut builder = Runtime::builder().from_path(toml_file.to_path_buf());
+    if let Some(lockfile) = lockfile {
+        builder = builder.with_lockfile(lockfile);
+    }
+    builder.build().await
+}
+
+#[tokio::test]
+async fn digest_property_is_verified() {
+    let dir = tempfile::tempdir().unwrap();
+    let wasm = dir.path().join("guest.wasm");
+    let bytes = component_returning(7);
+    std::fs::write(&wasm, &bytes).unwrap();
+    let definitions = |digest: &str| {
+        format!(
+            "[component.guest]\nuri = \"{}\"\ndigest = \"{digest}\"",
+            wasm.display()
+        )
+    };
+
+    build(&definitions(&sha256(&bytes)), None).await.unwrap();
+
+    let other = sha256(b"other");
+    let err = build(&definitions(&other), None)
+        .await
+        .err()
+        .expect("a different digest should fail the build")
+        .to_string();
+    assert!(
+        err.contains(&format!(
+            "has digest '{}', expected '{other}'",
+            sha256(&bytes)
+        )),
+        "unexpected error: {err}"
+    );
+
+    let err = build(&definitions("sha256:ABC"), None)
+        .await
+        .err()
+        .expect("a malformed digest should fail the build")
+        .to_string();
+    assert!(
+        err.contains("invalid digest 'sha256:ABC'"),
+        "unexpected error: {err}"
+    );
+
+    let unused = format!(
+        "{}\nprecompiled-digest = \"{}\"",
+        definitions(&sha256(&bytes)),
+        sha256(&bytes)
+    );
+    let err = build(&unused, None)
+        .await
+        .err()
+        .expect("a precompiled-digest without an artifact should fail the build")
+        .to_string();
+    assert!(
+        err.contains("'precompiled-digest' requires 'precompiled'"),
+        "unexpected error: {err}"
+    );
+}
+
+#[tokio::test]
+async fn lockfile_pins_component_bytes() {
+    let dir = tempfile::tempdir().unwrap();
+    let wasm = dir.path().join("guest.wasm");
+    let bytes = component_returning(7);
+    std::fs::write(&wasm, &bytes).unwrap();
+    let toml_content = format!("[component.guest]\nuri = \"{}\"", wasm.display());
+    let toml_file = common::create_toml_test_file(&toml_content);
+
+    let lockfile = dir.path().join("composable.lock");
+    let graph = ComponentGraph::builder()
+        .from_path(toml_file.to_path_buf())
+        .build()
+        .unwrap();
+    assert_eq!(graph.lock(&lockfile).await.unwrap(), 1);
+    let content = std::fs::read_to_string(&lockfile).unwrap();
+    assert!(content.contains(&format!("\"guest.wasm\" = \"{}\"", sha256(&bytes))));
+
+    let runtime = build(&toml_content, Some(&lockfile)).await.unwrap();
+    let result = runtime
+        .invoker()
+        .invoke("guest", "get-value", vec![], None)
+        .await
+        .unwrap();
+    assert_eq!(result, serde_json::json!(7));
+
+    // Replacing the file behind the locked uri fails the build.
+    std::fs::write(&wasm, component_returning(8)).unwrap();
+    let err = build(&toml_content, Some(&lockfile))
+        .await
+        .err()
+        .expect("changed bytes should fail the build")
+        .to_string();
+    assert!(
+        err.contains(&format!("expected '{}'", sha256(&bytes))),
+        "unexpected error: {err}"
+    );
+    build(&toml_content, None).await.unwrap();
+
+    // So does a `digest` property that disagrees with the lockfile.
+    let pinned = format!("{toml_content}\ndigest = \"{}\"", sha256(b"other"));
+    let err = build(&pinned, Some(&lockfile))
+        .await
+        .err()
+        .expect("conflicting digests should fail the build")
+        .to_string();
+    assert!(
+        err.contains("does not match") && err.contains("in lockfile"),
+        "unexpected error: {err}"
+    );
+}
+
+#[tokio::test]
+async fn lockfile_matches_however_definitions_are_spelled() {
+    let dir = tempfile::tempdir().unwrap();
+    let app = dir.path().join("app");
+    std::fs::create_dir(&app).unwrap();
+    let bytes = component_returning(7);
+    std::fs::write(app.join("guest.wasm"), &bytes).unwrap();
+    let definitions = app.join("defs.toml");
+    std::fs::write(&definitions, "[component.guest]\nuri = \"guest.wasm\"\n").unwrap();
+
+    let lockfile = dir.path().join("composable.lock");
+    let graph = ComponentGraph::builder()
+        .from_path(definitions.clone())
+        .build()
+        .unwrap();
+    assert_eq!(graph.lock(&lockfile).await.unwrap(), 1);
+    let content = std::fs::read_to_string(&lockfile).unwrap();
+    let key = std::path::Path::new("app").join("guest.wasm");
+    assert!(
+        content.contains(&format!("\"{}\" = \"{}\"", key.display(), sha256(&bytes))),
+        "{content}"
+    );
+
+    // Replaced bytes are refused under another spelling of the same path.
+    std::fs::write(app.join("guest.wasm"), component_returning(8)).unwrap();
+    let respelled = dir
+        .path()
+        .join(".")
+        .join("app")
+        .join("..")
+        .join("app")
+        .join("defs.toml");
+    let err = Runtime::builder()
+        .from_path(respelled.clone())
+        .with_lockfile(&lockfile)
+        .build()
+        .await
+        .err()
+        .expect("changed bytes should fail the build")
+        .to_string();
+    assert!(
+        err.contains(&format!("expected '{}'", sha256(&bytes))),
+        "unexpected error: {err}"
+    );
+
+    // A component the lockfile does not know fails the build too.
+    std::fs::write(app.join("guest.wasm"), &bytes).unwrap();
+    std::fs::write(app.join("other.wasm"), component_returning(9)).unwrap();
+    std::fs::write(
+        &definitions,
+        "[component.guest]\nuri = \"guest.wasm\"\n\n[component.other]\nuri = \"other.wasm\"\n",
+    )
+    .unwrap();
+    let err = Runtime::builder()
+        .from_path(respelled)
+        .with_lockfile(&lockfile)
+        .build()
+        .await
+        .err()
+        .expect("an unlocked component should fail the build")
+        .to_string();
+    assert!(
+        err.contains("Component 'other'") && err.contains("is not in lockfile"),
+        "unexpected error: {err}"
+    );
+}
@@ -0,0 +1,129 @@
+mod common;
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use composable_runtime::{PROPAGATION_CONTEXT, PropagationContext, Runtime};
+
+// Exports `log(level: u8, message: string)`, which forwards the message to
+// `wasi:logging` with the given level and the context "greeting".
+fn logger_component() -> common::TestFile {
+    let wat = r#"
+        (component
+            (import "wasi:logging/logging@0.1.0-draft" (instance $logging
+                (type $level (enum "trace" "debug" "info" "warn" "error" "critical"))
+                (export "level" (type $level' (eq $level)))
+                (export "log" (func
+                    (param "level" $level')
+                    (param "context" string)
+                    (param "message" string)))
+            ))
+
+            (core module $libc
+                (memory (export "memory") 1)
+                (global $heap (mut i32) (i32.const 1024))
+                (func (export "realloc") (param i32 i32 i32 i32) (result i32)
+                    (local $ptr i32)
+                    (local.set $ptr (global.get $heap))
+                    (global.set $heap (i32.add (local.get $ptr) (local.get 3)))
+                    (local.get $ptr))
+            )
+            (core instance $libc (instantiate $libc))
+            (core func $log (canon lower (func $logging "log") (memory $libc "memory")))
+
+            (core module $m
+                (import "libc" "memory" (memory 1))
+                (import "logging" "log" (func $log (param i32 i32 i32 i32 i32)))
+                (data (i32.const 0) "greeting")
+                (func (export "log") (param $level i32) (param $ptr i32) (param $len i32)
+                    (call $log
+                        (local.get $level)
+                        (i32.const 0) (i32.const 8)
+                        (local.get $ptr) (local.get $len)))
+            )
+            (core instance $i (instantiate $m
+                (with "libc" (instance $libc))
+                (with "logging" (instance (export "log" (func $log))))))
+
+            (func $log (param "level" u8) (param "message" string)
+                (canon lift (core func $i "log")
+                    (memory $libc "memory") (realloc (func $libc "realloc"))))
+            (export "log" (func $log))
+        )
+    "#;
+    common::create_wasm_test_file(wat)
+}
+
+#[derive(Clone, Default)]
+struct Output(Arc<Mutex<Vec<u8>>>);
+
+impl std::io::Write for Output {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn log_calls_become_tracing_events() {
+    let wasm = logger_component();
+    let toml_content = format!(
+        r#"
+        [component.logger]
+        uri = "{}"
+        imports = ["log"]
+
+        [capability.log]
+        type = "wasi:logging"
+        "#,
+        wasm.display()
+    );
+    let toml_file = common::create_toml_test_file(&toml_content);
+    let runtime = Runtime::builder()
+        .from_path(toml_file.to_path_buf())
+        .build()
+        .await
+        .unwrap();
+
+    let output = Output::default();
+    let writer = output.clone();
+    let subscriber = tracing_subscriber::fmt()
+        .with_writer(move || writer.clone())
+        .with_ansi(false)
+        .with_env_filter("composable_runtime::guest=debug")
+        .finish();
+    let _guard = tracing::subscriber::set_default(subscriber);
+
+    let invoker = runtime.invoker();
+    let log = |level: u8, message: &str| {
+        let args = vec![serde_json::json!(level), serde_json::json!(message)];
+        invoker.invoke("logger", "log", args, None)
+    };
+    log(0, "filtered out").await.unwrap();
+    log(2, "hello").await.unwrap();
+    log(5, "on fire").await.unwrap();
+    let ctx = PropagationContext {
+        entries: HashMap::from([(
+            "traceparent".to_string(),
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01".to_string(),
+        )]),
+    };
+    PROPAGATION_CONTEXT
+        .scope(Some(ctx), log(3, "traced"))
+        .await
+        .unwrap();
+
+    let output = String::from_utf8(output.0.lock().unwrap().clone()).unwrap();
+    let lines: Vec<&str> = output.lines().collect();
+    assert_eq!(lines.len(), 3, "unexpected output: {output}");
+    assert!(lines[0].contains("INFO composable_runtime::guest: hello"));
+    assert!(lines[0].contains("component=\"logger\" context=\"greeting\""));
+    assert!(!lines[0].contains("trace_id"));
+    assert!(lines[1].contains("ERROR composable_runtime::guest: on fire"));
+    assert!(lines[2].contains("WARN composable_runtime::guest: traced"));
+    assert!(lines[2].contains("trace_id=\"4bf92f3577b34da6a3ce929d0e0e4736\""));
+}
@@ -0,0 +1,264 @@
+mod common;
+
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::time::Duration;
+
+use composable_runtime::{
+    CategoryClaim, Condition, ConfigHandler, Operator, PropertyMap, Runtime, Selector,
+};
+
+fn component_returning_value() -> common::TestFile {
+    let wat = r#"
+        (component
+            (core module $m
+                (func (export "get-value") (result i32)
+                    (i32.const 7)
+                )
+            )
+            (core instance $i (instantiate $m))
+            (func $get_value (result u32) (canon lift (core func $i "get-value")))
+            (export "get-value" (func $get_value))
+        )
+    "#;
+    common::create_wasm_test_file(wat)
+}
+
+fn free_port() -> u16 {
+    std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port()
+}
+
+fn http_get(port: u16, path: &str) -> String {
+    let mut stream = TcpStream::connect(("127.0.0.1", port)).unwrap();
+    stream
+        .set_read_timeout(Some(Duration::from_secs(5)))
+        .unwrap();
+    write!(
+        stream,
+        "GET {path} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n"
+    )
+    .unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    response
+}
+
+#[tokio::test]
+async fn invocations_are_counted() {
+    let wasm = component_returning_value();
+    let toml_content = format!("[component.guest]\nuri = \"{}\"", wasm.display());
+    let toml_file = common::create_toml_test_file(&toml_content);
+    let runtime = Runtime::builder()
+        .from_path(toml_file.to_path_buf())
+        .build()
+        .await
+        .unwrap();
+
+    let invoker = runtime.invoker();
+    for _ in 0..3 {
+        invoker
+            .invoke("guest", "get-value", vec![], None)
+            .await
+            .unwrap();
+    }
+    invoker
+        .invoke("guest", "get-value", vec![serde_json::json!(1)], None)
+        .await
+        .unwrap_err();
+    invoker
+        .invoke("guest", "missing", vec![], None)
+        .await
+        .unwrap_err();
+
+    let metrics = runtime.metrics();
+    assert!(
+        metrics.contains(
+            "composable_invocations_total{component=\"guest\",function=\"get-value\",outcome=\"ok\"} 3\n"
+        ),
+        "unexpected metrics: {metrics}"
+    );
+    assert!(metrics.contains(
+        "composable_invocations_total{component=\"guest\",function=\"get-value\",outcome=\"invalid_arguments\"} 1\n"
+    ));
+    assert!(metrics.contains(
+        "composable_invocation_duration_seconds_count{component=\"guest\",function=\"get-value\"} 4\n"
+    ));
+    assert!(
+        metrics
+            .contains("composable_instantiation_duration_seconds_count{component=\"guest\"} 4\n")
+    );
+    assert!(!metrics.contains("missing"));
+}
+
+#[tokio::test]
+async fn metrics_server_serves_openmetrics() {
+    let wasm = component_returning_value();
+    let port = free_port();
+    let toml_content = format!(
+        r#"
+        [component.guest]
+        uri = "{}"
+
+        [server.metrics]
+        type = "metrics"
+        port = {port}
+        "#,
+        wasm.display()
+    );
+    let toml_file = common::create_toml_test_file(&toml_content);
+    let runtime = Runtime::builder()
+        .from_path(toml_file.to_path_buf())
+        .build()
+        .await
+        .unwrap();
+    runtime.start().unwrap();
+    runtime
+        .invoker()
+        .invoke("guest", "get-value", vec![], None)
+        .await
+        .unwrap();
+
+    let response = tokio::task::spawn_blocking(move || http_get(port, "/metrics"))
+        .await
+        .unwrap();
+    assert!(response.starts_with("HTTP/1.1 200 OK"), "{response}");
+    assert!(response.contains("content-type: application/openmetrics-text; version=1.0.0"));
+    assert!(response.contains("outcome=\"ok\"} 1\n"));
+    assert!(response.ends_with("# EOF\n"));
+
+    let response = tokio::task::spawn_blocking(move || http_get(port, "/other"))
+        .await
+        .unwrap();
+    assert!(response.starts_with("HTTP/1.1 404"), "{response}");
+
+    runtime.shutdown().await;
+}
+
+#[tokio::test]
+async fn failed_reload_keeps_server_config() {
+    let wasm = component_returning_value();
+    let port = free_port();
+    let definitions = |port: u16, uri: &str| {
+        format!(
+            "[component.guest]\nuri = \"{uri}\"\n\n[server.metrics]\ntype = \"metrics\"\nport = {port}\n"
+        )
+    };
+    let toml_file = common::create_toml_test_file(&definitions(port, &wasm.display().to_string()));
+    let runtime = Runtime::builder()
+        .from_path(toml_file.to_path_buf())
+        .build()
+        .await
+        .unwrap();
+
+    // The server definition parses, but the component does not load.
+    std::fs::write(&*toml_file, definitions(free_port(), "missing.wasm")).unwrap();
+    assert!(runtime.reload().await.is_err());
+
+    runtime.start().unwrap();
+    let response = tokio::task::spawn_blocking(move || http_get(port, "/metrics"))
+        .await
+        .unwrap();
+    assert!(response.starts_with("HTTP/1.1 200 OK"), "{response}");
+    runtime.shutdown().await;
+}
+
+#[tokio::test]
+async fn metrics_server_address() {
+    let toml_content = format!(
+        "[server.metrics]\ntype = \"metrics\"\nport = {}\naddress = \"127.0.0.1\"\n",
+        free_port()
+    );
+    let toml_file = common::create_toml_test_file(&toml_content);
+    let runtime = Runtime::builder()
+        .from_path(toml_file.to_path_buf())
+        .build()
+        .await
+        .unwrap();
+    runtime.start().unwrap();
+    runtime.shutdown().await;
+
+    let toml_content =
+        "[server.metrics]\ntype = \"metrics\"\nport = 9000\naddress = \"localhost\"\n";
+    let toml_file = common::create_toml_test_file(toml_content);
+    let err = Runtime::builder()
+        .from_path(toml_file.to_path_buf())
+        .build()
+        .await
+        .err()
+        .expect("'address' must be an IP address")
+        .to_string();
+    assert!(
+        err.contains("'address' must be an IP address"),
+        "unexpected error: {err}"
+    );
+}
+
+// Claims `[server.*]` definitions with `type = "other"`, and the same
+// properties as the metrics server.
+struct OtherServerHandler;
+
+impl ConfigHandler for OtherServerHandler {
+    fn claimed_categories(&self) -> Vec<CategoryClaim> {
+        vec![CategoryClaim::with_selector(
+            "server",
+            Selector {
+                conditions: vec![Condition {
+                    key: "type".to_string(),
+                    operator: Operator::Equals("other".to_string()),
+                }],
+            },
+        )]
+    }
+
+    fn claimed_properties(&self) -> HashMap<&str, &[&str]> {
+        HashMap::from([("server", ["type", "port", "route"].as_slice())])
+    }
+
+    fn handle_category(&mut self, _: &str, _: &str, _: PropertyMap) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn server_types_share_properties() {
+    let toml_content = format!(
+        r#"
+        [server.metrics]
+        type = "metrics"
+        port = {}
+
+        [server.other]
+        type = "other"
+        port = 8080
+        "#,
+        free_port()
+    );
+    let toml_file = common::create_toml_test_file(&toml_content);
+    Runtime::builder()
+        .from_path(toml_file.to_path_buf())
+        .with_config_handler(Box::new(OtherServerHandler))
+        .build()
+        .await
+        .expect("server types should be able to claim the same properties");
+
+    // Properties only another server type knows are still rejected.
+    let toml_content = "[server.metrics]\ntype = \"metrics\"\nport = 9000\nroute = {}\n";
+    let toml_file = common::create_toml_test_file(toml_content);
+    let err = Runtime::builder()
+        .from_path(toml_file.to_path_buf())
+        .with_config_handler(Box::new(OtherServerHandler))
+        .build()
+        .await
+        .err()
+        .expect("'route' is not a metrics server property")
+        .to_string();
+    assert!(
+        err.contains("unknown properties"),
+        "unexpected error: {err}"
+    );
+}
@@ -0,0 +1,133 @@
+mod common;
+
+use composable_runtime::{ComponentGraph, RegistryCredentials, Runtime};
+use sha2::{Digest, Sha256};
+
+const OCI_REF: &str = "registry.example.com/team/greeter:1.0";
+
+fn component_returning_value() -> Vec<u8> {
+    wat::parse_str(
+        r#"
+        (component
+            (core module $m
+                (func (export "get-value") (result i32)
+                    (i32.const 7)
+                )
+            )
+            (core instance $i (instantiate $m))
+            (func $get_value (result u32) (canon lift (core func $i "get-value")))
+            (export "get-value" (func $get_value))
+        )
+    "#,
+    )
+    .unwrap()
+}
+
+#[test]
+fn auth_resolves_placeholders() {
+    if !common::in_child_with_env(
+        "auth_resolves_placeholders",
+        &[("OCI_TEST_REGISTRY_PASSWORD", "s3cret")],
+    ) {
+        return;
+    }
+    let toml_file = common::create_toml_test_file(&format!(
+        r#"
+        [component.greeter]
+        uri = "oci://{OCI_REF}"
+        auth = {{ username = "ci", password = "${{process:env:OCI_TEST_REGISTRY_PASSWORD}}" }}
+        "#
+    ));
+    let graph = common::load_graph_and_assert_ok(&[toml_file.to_path_buf()]);
+    let definition = common::get_component_definition(&graph, "greeter");
+    assert_eq!(
+        definition.auth,
+        Some(RegistryCredentials::Basic {
+            username: "ci".to_string(),
+            password: "s3cret".to_string(),
+        })
+    );
+    assert!(!format!("{graph:?}").contains("s3cret"));
+}
+
+#[test]
+fn invalid_auth_rejected() {
+    let cases = [
+        (
+            r#"uri = "greeter.wasm"
+            auth = { token = "t0ken" }"#,
+            "'auth' requires an oci:// uri",
+        ),
+        (
+            r#"uri = "oci://registry.example.com/greeter:1.0"
+            auth = { username = "ci" }"#,
+            "'auth' must have either 'username' and 'password', or 'token'",
+        ),
+        (
+            r#"uri = "oci://registry.example.com/greeter:1.0"
+            auth = { token = "t0ken", scope = "pull" }"#,
+            "'auth' has unknown properties",
+        ),
+    ];
+    for (properties, expected) in cases {
+        let toml_content = format!("[component.greeter]\n{properties}");
+        let toml_file = common::create_toml_test_file(&toml_content);
+        let err = ComponentGraph::builder()
+            .from_path(&*toml_file)
+            .build()
+            .expect_err(&toml_content)
+            .to_string();
+        assert!(
+            err.contains(expected),
+            "Expected error containing '{expected}', got: {err}"
+        );
+    }
+}
+
+#[tokio::test]
+async fn offline_runtime_uses_cached_layer() {
+    let cache_dir = tempfile::tempdir().unwrap();
+    let toml_file = common::create_toml_test_file(&format!(
+        r#"
+        [runtime.oci]
+        cache-dir = "{}"
+        offline = true
+
+        [component.greeter]
+        uri = "oci://{OCI_REF}"
+        "#,
+        cache_dir.path().display()
+    ));
+    let build = || {
+        Runtime::builder()
+            .from_path(toml_file.to_path_buf())
+            .build()
+    };
+    let err = build().await.err().expect("nothing is cached yet");
+    assert!(
+        err.to_string().contains("is not cached (offline)"),
+        "unexpected error: {err}"
+    );
+
+    // Lay out the cache as a previous online pull would have.
+    let bytes = component_returning_value();
+    let hex = format!("{:x}", Sha256::digest(&bytes));
+    let blobs = cache_dir.path().join("blobs").join("sha256");
+    std::fs::create_dir_all(&blobs).unwrap();
+    std::fs::write(blobs.join(&hex), &bytes).unwrap();
+    let refs = cache_dir.path().join("refs");
+    std::fs::create_dir_all(&refs).unwrap();
+    std::fs::write(
+        refs.join(format!("{:x}", Sha256::digest(OCI_REF))),
+        format!("sha256:{hex}"),
+    )
+    .unwrap();
+
+    let runtime = build().await.unwrap();
+    let result = runtime
+        .invoker()
+        .invoke("greeter", "get-value", vec![], None)
+        .await
+        .unwrap();
+    assert_eq!(result, serde_json::json!(7));
+}
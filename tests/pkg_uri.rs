@@ -0,0 +1,134 @@
+mod common;
+
+use composable_runtime::Runtime;
+use std::path::Path;
+
+fn component_returning(value: i32) -> Vec<u8> {
+    wat::parse_str(format!(
+        r#"
+        (component
+            (core module $m
+                (func (export "get-value") (result i32)
+                    (i32.const {value})
+                )
+            )
+            (core instance $i (instantiate $m))
+            (func $get_value (result u32) (canon lift (core func $i "get-value")))
+            (export "get-value" (func $get_value))
+        )
+    "#
+    ))
+    .unwrap()
+}
+
+// A local registry holding acme:greeter 1.2.0, 1.3.0 and 2.0.0, each
+// returning its minor version (or 20 for 2.0.0), and the registry config
+// that serves it.
+fn local_registry(dir: &Path) -> std::path::PathBuf {
+    let package_dir = dir.join("registry").join("acme").join("greeter");
+    std::fs::create_dir_all(&package_dir).unwrap();
+    for (version, value) in [("1.2.0", 2), ("1.3.0", 3), ("2.0.0", 20)] {
+        std::fs::write(
+            package_dir.join(format!("{version}.wasm")),
+            component_returning(value),
+        )
+        .unwrap();
+    }
+    let config_file = dir.join("wasm-pkg.toml");
+    std::fs::write(
+        &config_file,
+        format!(
+            r#"
+            default_registry = "local"
+
+            [registry."local"]
+            type = "local"
+
+            [registry."local".local]
+            root = '{}'
+            "#,
+            dir.join("registry").display()
+        ),
+    )
+    .unwrap();
+    config_file
+}
+
+async fn get_value(runtime: &Runtime, component: &str) -> serde_json::Value {
+    runtime
+        .invoker()
+        .invoke(component, "get-value", vec![], None)
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn resolves_versions_from_local_registry() {
+    let dir = tempfile::tempdir().unwrap();
+    let config_file = local_registry(dir.path());
+    let toml_file = common::create_toml_test_file(&format!(
+        r#"
+        [runtime.pkg]
+        config-file = "{}"
+
+        [component.latest]
+        uri = "pkg:acme:greeter"
+
+        [component.compatible]
+        uri = "pkg:acme:greeter@^1.2"
+
+        [component.exact]
+        uri = "pkg:acme:greeter@1.2.0"
+        "#,
+        config_file.display()
+    ));
+    let runtime = Runtime::builder()
+        .from_path(toml_file.to_path_buf())
+        .build()
+        .await
+        .unwrap();
+    assert_eq!(get_value(&runtime, "latest").await, serde_json::json!(20));
+    assert_eq!(
+        get_value(&runtime, "compatible").await,
+        serde_json::json!(3)
+    );
+    assert_eq!(get_value(&runtime, "exact").await, serde_json::json!(2));
+
+    let toml_file = common::create_toml_test_file(&format!(
+        r#"
+        [runtime.pkg]
+        config-file = "{}"
+
+        [component.greeter]
+        uri = "pkg:acme:greeter@^3"
+        "#,
+        config_file.display()
+    ));
+    let err = Runtime::builder()
+        .from_path(toml_file.to_path_buf())
+        .build()
+        .await
+        .err()
+        .expect("no release matches")
+        .to_string();
+    assert!(
+        err.contains("No release of 'pkg:acme:greeter@^3' matches '^3'"),
+        "unexpected error: {err}"
+    );
+}
+
+#[tokio::test]
+async fn package_uri_on_command_line() {
+    let dir = tempfile::tempdir().unwrap();
+    let config_file = local_registry(dir.path());
+    let toml_file = common::create_toml_test_file(&format!(
+        "[runtime.pkg]\nconfig-file = \"{}\"",
+        config_file.display()
+    ));
+    let runtime = Runtime::builder()
+        .from_paths(&[toml_file.to_path_buf(), "pkg:acme:greeter@~1.2".into()])
+        .build()
+        .await
+        .unwrap();
+    assert_eq!(get_value(&runtime, "greeter").await, serde_json::json!(2));
+}
@@ -0,0 +1,281 @@
+mod common;
+
+use composable_runtime::{ComponentGraph, Lifecycle, Runtime};
+
+fn component_returning_value() -> common::TestFile {
+    let wat = r#"
+        (component
+            (core module $m
+                (func (export "get-value") (result i32)
+                    (i32.const 7)
+                )
+            )
+            (core instance $i (instantiate $m))
+            (func $get_value (result u32) (canon lift (core func $i "get-value")))
+            (export "get-value" (func $get_value))
+        )
+    "#;
+    common::create_wasm_test_file(wat)
+}
+
+async fn build_runtime(paths: &[std::path::PathBuf]) -> anyhow::Result<Runtime> {
+    Runtime::builder().from_paths(paths).build().await
+}
+
+#[tokio::test]
+async fn compiled_bundle_runs_without_recompiling() {
+    let wasm = component_returning_value();
+    let toml_content = format!(
+        r#"
+        [component.guest]
+        uri = "{}"
+        max-concurrency = 2
+        lifecycle = "singleton"
+        labels = {{ tier = "edge" }}
+        "#,
+        wasm.display()
+    );
+    let toml_file = common::create_toml_test_file(&toml_content);
+    let runtime = build_runtime(&[toml_file.to_path_buf()]).await.unwrap();
+
+    let bundle_dir = tempfile::tempdir().unwrap();
+    let definitions = runtime.compile_to(bundle_dir.path()).unwrap();
+    assert!(bundle_dir.path().join("guest.cwasm").exists());
+
+    let graph = common::load_graph_and_assert_ok(std::slice::from_ref(&definitions));
+    let definition = common::get_component_definition(&graph, "guest");
+    let precompiled = bundle_dir.path().join("guest.cwasm");
+    assert_eq!(
+        definition.precompiled.as_deref(),
+        Some(precompiled.to_str().unwrap())
+    );
+    assert_eq!(definition.limits.max_concurrency, Some(2));
+    assert_eq!(
+        definition.lifecycle,
+        Lifecycle::Singleton { max_calls: None }
+    );
+    assert_eq!(definition.labels["tier"], "edge");
+
+    let bundled = build_runtime(&[definitions])
+        .await
+        .expect("Failed to load bundle");
+    let result = bundled
+        .invoker()
+        .invoke("guest", "get-value", vec![], None)
+        .await
+        .expect("Failed to invoke");
+    assert_eq!(result, serde_json::json!(7));
+}
+
+#[tokio::test]
+async fn bundle_can_be_moved_and_pins_its_bytes() {
+    let wasm = component_returning_value();
+    let toml_content = format!("[component.guest]\nuri = \"{}\"", wasm.display());
+    let toml_file = common::create_toml_test_file(&toml_content);
+    let runtime = build_runtime(&[toml_file.to_path_buf()]).await.unwrap();
+
+    let bundle_dir = tempfile::tempdir().unwrap();
+    let definitions = runtime.compile_to(bundle_dir.path()).unwrap();
+    let content = std::fs::read_to_string(&definitions).unwrap();
+    assert!(content.contains("uri = \"guest.wasm\""), "{content}");
+    assert!(
+        content.contains("precompiled = \"guest.cwasm\""),
+        "{content}"
+    );
+    assert!(
+        content.contains("precompiled-digest = \"sha256:"),
+        "{content}"
+    );
+
+    let moved_dir = tempfile::tempdir().unwrap();
+    for file in ["components.toml", "guest.wasm", "guest.cwasm"] {
+        std::fs::rename(bundle_dir.path().join(file), moved_dir.path().join(file)).unwrap();
+    }
+    let moved = moved_dir.path().join("components.toml");
+    let bundled = build_runtime(std::slice::from_ref(&moved))
+        .await
+        .expect("Failed to load moved bundle");
+    let result = bundled
+        .invoker()
+        .invoke("guest", "get-value", vec![], None)
+        .await
+        .unwrap();
+    assert_eq!(result, serde_json::json!(7));
+
+    // A replaced artifact is refused before it is deserialized.
+    let artifact = moved_dir.path().join("guest.cwasm");
+    let compiled = std::fs::read(&artifact).unwrap();
+    std::fs::write(&artifact, b"replaced").unwrap();
+    let err = build_runtime(std::slice::from_ref(&moved))
+        .await
+        .err()
+        .expect("a replaced artifact should fail the build")
+        .to_string();
+    assert!(
+        err.contains("precompiled artifact") && err.contains("has digest"),
+        "unexpected error: {err}"
+    );
+    std::fs::write(&artifact, compiled).unwrap();
+
+    // A rebuilt component no longer matches its compiled artifact.
+    let rebuilt = wat::parse_str(
+        r#"(component
+            (core module $m (func (export "get-value") (result i32) (i32.const 8)))
+            (core instance $i (instantiate $m))
+            (func $get_value (result u32) (canon lift (core func $i "get-value")))
+            (export "get-value" (func $get_value)))"#,
+    )
+    .unwrap();
+    std::fs::write(moved_dir.path().join("guest.wasm"), rebuilt).unwrap();
+    let err = build_runtime(&[moved])
+        .await
+        .err()
+        .expect("a rebuilt component should not run the stale artifact")
+        .to_string();
+    assert!(err.contains("has digest"), "unexpected error: {err}");
+}
+
+#[tokio::test]
+async fn pinned_bytes_need_a_pinned_artifact() {
+    let wasm = component_returning_value();
+    let toml_content = format!("[component.guest]\nuri = \"{}\"", wasm.display());
+    let toml_file = common::create_toml_test_file(&toml_content);
+    let runtime = build_runtime(&[toml_file.to_path_buf()]).await.unwrap();
+
+    let bundle_dir = tempfile::tempdir().unwrap();
+    let definitions = runtime.compile_to(bundle_dir.path()).unwrap();
+    let content = std::fs::read_to_string(&definitions).unwrap();
+    let unpinned: String = content
+        .lines()
+        .filter(|line| !line.starts_with("precompiled-digest"))
+        .map(|line| format!("{line}\n"))
+        .collect();
+    std::fs::write(&definitions, unpinned).unwrap();
+    let err = build_runtime(std::slice::from_ref(&definitions))
+        .await
+        .err()
+        .expect("a pinned component with an unpinned artifact should fail the build")
+        .to_string();
+    assert!(
+        err.contains("not its 'precompiled' artifact"),
+        "unexpected error: {err}"
+    );
+
+    // A lockfile pins the artifact too.
+    let lockfile = bundle_dir.path().join("composable.lock");
+    let graph = ComponentGraph::builder()
+        .from_path(definitions.clone())
+        .build()
+        .unwrap();
+    assert_eq!(graph.lock(&lockfile).await.unwrap(), 2);
+    let bundled = Runtime::builder()
+        .from_path(definitions)
+        .with_lockfile(&lockfile)
+        .build()
+        .await
+        .expect("Failed to load locked bundle");
+    let result = bundled
+        .invoker()
+        .invoke("guest", "get-value", vec![], None)
+        .await
+        .unwrap();
+    assert_eq!(result, serde_json::json!(7));
+}
+
+#[tokio::test]
+async fn precompiled_artifact_rejected_by_incompatible_engine() {
+    let wasm = component_returning_value();
+    let toml_content = format!("[component.guest]\nuri = \"{}\"", wasm.display());
+    let toml_file = common::create_toml_test_file(&toml_content);
+    let runtime = build_runtime(&[toml_file.to_path_buf()]).await.unwrap();
+
+    let bundle_dir = tempfile::tempdir().unwrap();
+    let definitions = runtime.compile_to(bundle_dir.path()).unwrap();
+
+    // A `fuel` limit anywhere enables fuel metering for the whole engine,
+    // which the artifact was not compiled with.
+    let other_toml = format!(
+        "[component.metered]\nuri = \"{}\"\nfuel = 1000",
+        wasm.display()
+    );
+    let other_file = common::create_toml_test_file(&other_toml);
+    let err = build_runtime(&[definitions, other_file.to_path_buf()])
+        .await
+        .err()
+        .expect("incompatible artifact should be rejected")
+        .to_string();
+    assert!(
+        err.contains("is not compatible with this runtime"),
+        "unexpected error: {err}"
+    );
+}
+
+#[tokio::test]
+async fn precompiled_must_be_an_artifact() {
+    let wasm = component_returning_value();
+    let toml_content = format!(
+        "[component.guest]\nuri = \"{0}\"\nprecompiled = \"{0}\"",
+        wasm.display()
+    );
+    let toml_file = common::create_toml_test_file(&toml_content);
+    let err = build_runtime(&[toml_file.to_path_buf()])
+        .await
+        .err()
+        .expect("a .wasm file is not a precompiled artifact")
+        .to_string();
+    assert!(
+        err.contains("is not a precompiled component"),
+        "unexpected error: {err}"
+    );
+}
+
+#[tokio::test]
+async fn composed_component_loads_from_manifest() {
+    let wasm = component_returning_value();
+    let toml_content = format!(
+        r#"
+        [component.guest]
+        uri = "{}"
+        imports = ["cli"]
+
+        [capability.cli]
+        type = "wasi:cli"
+
+        [capability.unused]
+        type = "wasi:clocks"
+        "#,
+        wasm.display()
+    );
+    let toml_file = common::create_toml_test_file(&toml_content);
+    let runtime = build_runtime(&[toml_file.to_path_buf()]).await.unwrap();
+
+    let out_dir = tempfile::tempdir().unwrap();
+    let path = out_dir.path().join("guest.wasm");
+    let manifest = runtime.compose_to("guest", &path).unwrap();
+    assert_eq!(manifest, out_dir.path().join("guest.toml"));
+    assert!(path.exists());
+    assert!(!out_dir.path().join("guest.cwasm").exists());
+
+    let content = std::fs::read_to_string(&manifest).unwrap();
+    assert!(content.starts_with("# Composed component 'guest'\n# Imports: none\n"));
+    let graph = common::load_graph_and_assert_ok(std::slice::from_ref(&manifest));
+    let definition = common::get_component_definition(&graph, "guest");
+    assert_eq!(definition.precompiled, None);
+    assert!(content.contains("[capability.cli]"));
+    assert!(!content.contains("unused"));
+
+    let composed = build_runtime(&[manifest])
+        .await
+        .expect("Failed to load manifest");
+    let result = composed
+        .invoker()
+        .invoke("guest", "get-value", vec![], None)
+        .await
+        .expect("Failed to invoke");
+    assert_eq!(result, serde_json::json!(7));
+
+    let err = runtime
+        .compose_to("missing", out_dir.path().join("missing.wasm"))
+        .unwrap_err();
+    assert_eq!(err.to_string(), "Component 'missing' not found");
+}
@@ -0,0 +1,106 @@
+mod common;
+
+use composable_runtime::{InvocationError, Runtime};
+
+fn component_returning(value: i32) -> common::TestFile {
+    let wat = format!(
+        r#"
+        (component
+            (core module $m
+                (func (export "get-value") (result i32)
+                    (i32.const {value})
+                )
+            )
+            (core instance $i (instantiate $m))
+            (func $get_value (result u32) (canon lift (core func $i "get-value")))
+            (export "get-value" (func $get_value))
+        )
+    "#
+    );
+    common::create_wasm_test_file(&wat)
+}
+
+fn definitions(entries: &[(&str, &common::TestFile)]) -> String {
+    entries
+        .iter()
+        .map(|(name, wasm)| format!("[component.{name}]\nuri = \"{}\"\n", wasm.display()))
+        .collect()
+}
+
+async fn get_value(
+    runtime: &Runtime,
+    component: &str,
+) -> Result<serde_json::Value, InvocationError> {
+    runtime
+        .invoker()
+        .invoke(component, "get-value", vec![], None)
+        .await
+}
+
+#[tokio::test]
+async fn reload_switches_to_changed_definitions() {
+    let seven = component_returning(7);
+    let nine = component_returning(9);
+    let toml_file = common::create_toml_test_file(&definitions(&[("guest", &seven)]));
+    let runtime = Runtime::builder()
+        .from_path(toml_file.to_path_buf())
+        .build()
+        .await
+        .unwrap();
+    runtime.start().unwrap();
+    let invoker = runtime.invoker();
+
+    std::fs::write(
+        &*toml_file,
+        definitions(&[("guest", &nine), ("added", &seven)]),
+    )
+    .unwrap();
+    runtime.reload().await.expect("Failed to reload");
+
+    // Invokers handed out before the reload see the new components too.
+    let result = invoker
+        .invoke("guest", "get-value", vec![], None)
+        .await
+        .unwrap();
+    assert_eq!(result, serde_json::json!(9));
+    assert_eq!(get_value(&runtime, "added").await.unwrap(), 7);
+    assert_eq!(runtime.list_components(None).len(), 2);
+    runtime.shutdown().await;
+}
+
+#[tokio::test]
+async fn failed_reload_keeps_previous_components() {
+    let seven = component_returning(7);
+    let toml_file = common::create_toml_test_file(&definitions(&[("guest", &seven)]));
+    let runtime = Runtime::builder()
+        .from_path(toml_file.to_path_buf())
+        .build()
+        .await
+        .unwrap();
+
+    std::fs::write(&*toml_file, "[component.guest]\nuri = \"missing.wasm\"\n").unwrap();
+    assert!(runtime.reload().await.is_err());
+
+    assert_eq!(get_value(&runtime, "guest").await.unwrap(), 7);
+}
+
+#[tokio::test]
+async fn instance_outlives_reload() {
+    let seven = component_returning(7);
+    let nine = component_returning(9);
+    let toml_file = common::create_toml_test_file(&definitions(&[("guest", &seven)]));
+    let runtime = Runtime::builder()
+        .from_path(toml_file.to_path_buf())
+        .build()
+        .await
+        .unwrap();
+    let function = runtime.get_component("guest").unwrap().functions["get-value"].clone();
+    let mut instance = runtime.instantiate("guest", None).await.unwrap();
+
+    std::fs::write(&*toml_file, definitions(&[("guest", &nine)])).unwrap();
+    runtime.reload().await.expect("Failed to reload");
+
+    let result = instance.call(&function, vec![]).await.unwrap().unwrap();
+    assert_eq!(result.as_json(), Some(&serde_json::json!(7)));
+    assert_eq!(get_value(&runtime, "guest").await.unwrap(), 9);
+}
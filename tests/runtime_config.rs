@@ -0,0 +1,189 @@
+mod common;
+
+use composable_runtime::types::{OciConfig, OptLevel, PoolingConfig};
+use composable_runtime::{ComponentGraph, Runtime};
+
+fn component_returning_value() -> common::TestFile {
+    let wat = r#"
+        (component
+            (core module $m
+                (memory (export "memory") 1)
+                (func (export "get-value") (result i32)
+                    (i32.const 7)
+                )
+            )
+            (core instance $i (instantiate $m))
+            (func $get_value (result u32) (canon lift (core func $i "get-value")))
+            (export "get-value" (func $get_value))
+        )
+    "#;
+    common::create_wasm_test_file(wat)
+}
+
+#[test]
+fn runtime_config_parsed() {
+    let cache_dir = tempfile::tempdir().unwrap();
+    let toml_content = format!(
+        r#"
+        [runtime.engine]
+        cache-dir = "{}"
+        debug-info = true
+        opt-level = "speed-and-size"
+
+        [runtime.allocator]
+        strategy = "pooling"
+        total-component-instances = 20
+        total-memories = 10
+
+        [runtime.composition]
+        cache-dir = "{}/compositions"
+
+        [runtime.oci]
+        cache-dir = "{}/oci"
+        offline = true
+        docker-config = "/etc/composable/docker.json"
+
+        [runtime.pkg]
+        config-file = "/etc/composable/wasm-pkg.toml"
+        "#,
+        cache_dir.path().display(),
+        cache_dir.path().display(),
+        cache_dir.path().display()
+    );
+    let toml_file = common::create_toml_test_file(&toml_content);
+    let graph = common::load_graph_and_assert_ok(&[toml_file.to_path_buf()]);
+
+    let config = graph.runtime_config();
+    assert_eq!(config.cache_dir.as_deref(), Some(cache_dir.path()));
+    assert!(config.debug_info);
+    assert_eq!(config.opt_level, Some(OptLevel::SpeedAndSize));
+    assert_eq!(
+        config.pooling,
+        Some(PoolingConfig {
+            total_component_instances: Some(20),
+            total_memories: Some(10),
+            total_tables: None,
+        })
+    );
+    assert_eq!(
+        config.composition_cache_dir,
+        Some(cache_dir.path().join("compositions"))
+    );
+    assert_eq!(
+        config.oci,
+        OciConfig {
+            cache_dir: Some(cache_dir.path().join("oci")),
+            offline: true,
+            docker_config: Some("/etc/composable/docker.json".into()),
+        }
+    );
+    assert_eq!(
+        config.pkg_config_file.as_deref(),
+        Some(std::path::Path::new("/etc/composable/wasm-pkg.toml"))
+    );
+}
+
+#[test]
+fn runtime_config_defaults() {
+    let toml_file = common::create_toml_test_file("");
+    let graph = common::load_graph_and_assert_ok(&[toml_file.to_path_buf()]);
+
+    let config = graph.runtime_config();
+    assert!(config.cache_dir.is_none());
+    assert!(!config.debug_info);
+    assert!(config.opt_level.is_none());
+    assert!(config.pooling.is_none());
+    assert!(config.composition_cache_dir.is_none());
+    assert_eq!(config.oci, OciConfig::default());
+    assert!(config.pkg_config_file.is_none());
+}
+
+#[test]
+fn runtime_config_rejects_invalid_values() {
+    let cases = [
+        (
+            "[runtime.allocator]\nstrategy = \"bump\"",
+            "invalid strategy 'bump'",
+        ),
+        (
+            "[runtime.allocator]\ntotal-memories = 10",
+            "pool limits require strategy = \"pooling\"",
+        ),
+        (
+            "[runtime.allocator]\nstrategy = \"pooling\"\ntotal-tables = -1",
+            "'total-tables' must be a non-negative 32-bit integer",
+        ),
+        (
+            "[runtime.engine]\nopt-level = \"fast\"",
+            "invalid opt-level 'fast'",
+        ),
+        (
+            "[runtime.engine]\ndebug = true",
+            "runtime 'engine' has unknown properties",
+        ),
+        (
+            "[runtime.composition]\ncache = \"/tmp\"",
+            "runtime 'composition' has unknown properties",
+        ),
+        (
+            "[runtime.oci]\noffline = true",
+            "'offline' requires 'cache-dir'",
+        ),
+        (
+            "[runtime.pkg]\nregistry = \"wa.dev\"",
+            "runtime 'pkg' has unknown properties",
+        ),
+        (
+            "[runtime.jit]\nenabled = true",
+            "Unknown runtime definition 'jit'",
+        ),
+    ];
+    for (toml_content, expected) in cases {
+        let toml_file = common::create_toml_test_file(toml_content);
+        let err = ComponentGraph::builder()
+            .from_path(&*toml_file)
+            .build()
+            .expect_err(toml_content)
+            .to_string();
+        assert!(
+            err.contains(expected),
+            "Expected error containing '{expected}', got: {err}"
+        );
+    }
+}
+
+#[tokio::test]
+async fn pooling_allocator_invocations() {
+    let component_wasm = component_returning_value();
+    let toml_content = format!(
+        r#"
+        [runtime.allocator]
+        strategy = "pooling"
+        total-component-instances = 4
+        total-memories = 4
+        total-tables = 4
+
+        [component.guest]
+        uri = "{}"
+        "#,
+        component_wasm.display()
+    );
+    let toml_file = common::create_toml_test_file(&toml_content);
+
+    let runtime = Runtime::builder()
+        .from_path(&*toml_file)
+        .build()
+        .await
+        .expect("Failed to create runtime");
+
+    // More invocations than pool slots: each instance's slot is returned to
+    // the pool when its store is dropped.
+    let invoker = runtime.invoker();
+    for _ in 0..16 {
+        let result = invoker
+            .invoke("guest", "get-value", vec![], None)
+            .await
+            .expect("Failed to invoke");
+        assert_eq!(result, serde_json::json!(7));
+    }
+}
@@ -0,0 +1,43 @@
+mod common;
+
+use composable_runtime::Runtime;
+
+async fn build(properties: &str) -> anyhow::Result<Runtime> {
+    let toml_file = common::create_toml_test_file(&format!(
+        r#"
+        [capability.net]
+        type = "wasi:sockets"
+        {properties}
+        "#
+    ));
+    Runtime::builder()
+        .from_path(toml_file.to_path_buf())
+        .build()
+        .await
+}
+
+#[tokio::test]
+async fn invalid_rule_fails_build() {
+    let err = build(r#"allow-connect = ["10.0.4.12:5432", "db.internal:5432"]"#)
+        .await
+        .err()
+        .expect("invalid rule should fail the build")
+        .to_string();
+    assert!(
+        err.contains("Capability 'net'") && err.contains("invalid rule 'db.internal:5432'"),
+        "unexpected error: {err}"
+    );
+}
+
+#[tokio::test]
+async fn valid_rules_build() {
+    build(
+        r#"
+        allow-connect = ["10.0.4.12:5432", "[fd00::/64]:5432"]
+        allow-bind = ["127.0.0.1:8000-8099"]
+        allow-udp = ["*:53"]
+        "#,
+    )
+    .await
+    .unwrap();
+}